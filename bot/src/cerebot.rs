@@ -1,42 +1,46 @@
 use std::pin::Pin;
-use std::time::Duration;
 
 use futures::channel::mpsc::UnboundedReceiver;
-use futures::future::join;
-use futures::{SinkExt, StreamExt};
+use futures::future::{self, abortable, join};
+use futures::{stream, SinkExt, StreamExt};
 use tmi_rs::stream::{ClientMessageStream, SendStreamExt};
-use tmi_rs::{ClientMessage, TwitchChatConnection, TwitchClient, TwitchClientConfigBuilder};
-use tokio::{task, time};
+use tmi_rs::ClientMessage;
+use tokio::task;
 
 use persistence::channel::Channel;
-use persistence::chat_event::persist_event_queue;
 use persistence::permissions::create_default_permissions;
 use persistence::DbContext;
 
 use crate::config::CerebotConfig;
+use crate::control;
 use crate::dispatch::matchers::{MatchAll, MatchMessages};
 use crate::dispatch::{EventDispatch, EventHandler, HandlerBuilder, MatcherBuilder};
-use crate::error::Error;
 use crate::event::CbEvent;
-use crate::handlers::{BotStateHandler, CommandRouter, LoggingHandler};
+use crate::handlers::{
+    AdminHandler, AlertHandler, BotStateHandler, CommandRouter, LoggingHandler,
+};
+use crate::maintenance;
 use crate::state::*;
+use crate::transport::{ChatConnection, ChatTransport};
 use crate::Result;
 
 pub struct Cerebot {
-    chat_client: TwitchClient,
+    transport: Box<dyn ChatTransport>,
+    /// connections to other chat platforms, connected while the main transport is
+    extra_transports: Vec<Box<dyn ChatTransport>>,
 }
 
 impl Cerebot {
-    pub fn create() -> Result<Self> {
-        let config = CerebotConfig::get()?;
-        Ok(Cerebot {
-            chat_client: TwitchClientConfigBuilder::default()
-                .username(config.username().to_string())
-                .token(config.auth_token().to_string())
-                .build()
-                .map_err(Error::TmiConfig)?
-                .into(),
-        })
+    pub fn create(transport: Box<dyn ChatTransport>) -> Self {
+        Cerebot {
+            transport,
+            extra_transports: vec![],
+        }
+    }
+
+    /// Add another chat platform, for example Discord next to Twitch
+    pub fn add_transport(&mut self, transport: Box<dyn ChatTransport>) {
+        self.extra_transports.push(transport);
     }
 
     pub async fn run(&mut self) -> Result<RunResult> {
@@ -48,13 +52,27 @@ impl Cerebot {
         info!("Running migrations");
         db_context.run_pending_migrations()?;
 
-        debug!("Connecting to Twitch chat...");
-        let TwitchChatConnection {
+        let ChatConnection {
             sender,
             receiver,
             error_receiver,
-        } = self.chat_client.connect().await?;
-        info!("Twitch chat connected.");
+        } = self.transport.connect().await?;
+
+        let mut extra_receivers = vec![];
+        let mut error_receivers = vec![error_receiver];
+        for transport in &mut self.extra_transports {
+            let connection = transport.connect().await?;
+            extra_receivers.push(connection.receiver);
+            error_receivers.push(connection.error_receiver);
+        }
+        // the other connections are closed when the main connection ends
+        let receiver = stream::select(
+            receiver.map(Some).chain(stream::once(future::ready(None))),
+            stream::select_all(extra_receivers).map(Some),
+        )
+        .take_while(|event| future::ready(event.is_some()))
+        .filter_map(future::ready);
+        let error_receiver = stream::select_all(error_receivers);
 
         let context: BotContext = BotContext::create(db_context, sender).await?;
 
@@ -73,34 +91,42 @@ impl Cerebot {
             sender.send(ClientMessage::join(channel.name)).await?;
         }
 
-        let heartbeat_ctx = context.db_context.clone();
-        task::spawn(async move {
-            let ctx = heartbeat_ctx;
-            let mut interval = time::interval(Duration::from_secs(2));
-            loop {
-                interval.tick().await;
-                persist_event_queue(&ctx).await.unwrap();
-            }
-        });
+        let (persist_events, persist_events_handle) =
+            abortable(maintenance::persist_events(context.db_context.clone()));
+        task::spawn(persist_events);
 
         if create_default_permissions(&context.db_context).await? > 0 {
             context.reload_permissions().await?;
         }
 
-        let dispatch = EventDispatch::<CbEvent>::default();
-        dispatch
-            .match_events(MatchAll)
-            .handle(Box::new(BotStateHandler::create(&context).await?))
-            .handle(Box::new(LoggingHandler::create(&context).await?))
-            .match_events(MatchMessages)
-            .handle(Box::new(CommandRouter::create(&context).await?));
+        // apply actions sent by other processes like the web backend, stopped before restarting
+        let (control_listener, control_handle) = abortable(control::listen(context.clone()));
+        task::spawn(async move {
+            if let Ok(Err(err)) = control_listener.await {
+                error!("Control channel listener failed: {}", err);
+            }
+        });
+
+        let (refresh_stats, refresh_stats_handle) =
+            abortable(maintenance::refresh_stats(context.db_context.clone()));
+        task::spawn(refresh_stats);
+        let (create_partitions, create_partitions_handle) =
+            abortable(maintenance::create_partitions(context.db_context.clone()));
+        task::spawn(create_partitions);
+        let (apply_retention, apply_retention_handle) = abortable(maintenance::apply_retention(
+            context.db_context.clone(),
+            config.archive_dir().map(ToOwned::to_owned),
+        ));
+        task::spawn(apply_retention);
+
+        let dispatch = create_dispatch(&context).await?;
         info!("Initialized message handlers");
 
         // process messages and do stuff with the data
         let dispatch = &dispatch;
         let context = &context;
         let process_messages = receiver
-            .map(|event| dispatch.dispatch(CbEvent::from(event)))
+            .map(|event| dispatch.dispatch(event))
             .buffer_unordered(10)
             .for_each(|dispatch_result| {
                 async move {
@@ -123,6 +149,11 @@ impl Cerebot {
         });
 
         let _ = join(process_messages, process_errors).await;
+        control_handle.abort();
+        persist_events_handle.abort();
+        refresh_stats_handle.abort();
+        create_partitions_handle.abort();
+        apply_retention_handle.abort();
         if context.should_restart() {
             info!("Restarting...");
             Ok(RunResult::Restart)
@@ -132,6 +163,21 @@ impl Cerebot {
     }
 }
 
+/// Create the event dispatch with all event handlers of the bot
+pub async fn create_dispatch(context: &BotContext) -> Result<EventDispatch<CbEvent>> {
+    let dispatch = EventDispatch::<CbEvent>::default();
+    dispatch
+        .match_events(MatchAll)
+        .handle(Box::new(BotStateHandler::create(context).await?))
+        .handle(Box::new(LoggingHandler::create(context).await?))
+        .handle(Box::new(AlertHandler::create(context).await?))
+        .match_events(MatchMessages)
+        // before the router, so the first command of an admin already has root
+        .handle(Box::new(AdminHandler::create(context).await?))
+        .handle(Box::new(CommandRouter::create(context).await?));
+    Ok(dispatch)
+}
+
 fn send_middleware_setup(
     stream: UnboundedReceiver<ClientMessage<String>>,
 ) -> Pin<Box<dyn ClientMessageStream>> {
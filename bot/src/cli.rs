@@ -0,0 +1,355 @@
+//! Command line interface of the bot binary. Runs the bot if no subcommand is given, the other
+//! subcommands are maintenance tasks using the database directly.
+
+use std::collections::BTreeMap;
+
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use structopt::clap::AppSettings;
+use structopt::StructOpt;
+
+use persistence::api_tokens::UserApiToken;
+use persistence::channel::{Channel, InsertChannel};
+use persistence::commands::alias::CommandAlias;
+use persistence::commands::attributes::CommandAttributes;
+use persistence::permissions::{create_default_permissions, Permission};
+use persistence::user::User;
+use persistence::DbContext;
+
+use crate::config::CerebotConfig;
+use crate::error::Error;
+use crate::handlers::grant_permission;
+use crate::Result;
+
+/// The run options can't be combined with a subcommand, e.g. `cerebot2 --console migrate`
+#[derive(StructOpt, Debug)]
+#[structopt(name = "cerebot2", setting = AppSettings::ArgsNegateSubcommands)]
+pub struct Opts {
+    #[structopt(flatten)]
+    pub run: RunOpts,
+    #[structopt(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(StructOpt, Debug)]
+pub struct RunOpts {
+    /// Chat in the terminal instead of connecting to Twitch
+    #[structopt(long)]
+    pub console: bool,
+    /// User to chat as in console mode
+    #[structopt(long, default_value = "console_user")]
+    pub user: String,
+    /// Channel to chat in in console mode
+    #[structopt(long, default_value = "console")]
+    pub channel: String,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CliCommand {
+    /// Run the bot (default)
+    Run(RunOpts),
+    /// Run pending database migrations
+    Migrate,
+    /// Add or list channels
+    Channel(ChannelCommand),
+    /// Manage user permissions
+    Permission(PermissionCommand),
+    /// List commands
+    Command(CommandCommand),
+    /// Manage personal API tokens for the web backend
+    Token(TokenCommand),
+    /// Check the configuration and the database and redis connections
+    CheckConfig,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum ChannelCommand {
+    /// Add a channel
+    Add {
+        /// Channel name
+        name: String,
+        /// Join the channel on startup
+        #[structopt(long)]
+        join: bool,
+        /// Command prefix, commands are disabled without one
+        #[structopt(long)]
+        prefix: Option<String>,
+        /// Don't send any messages to the channel
+        #[structopt(long)]
+        silent: bool,
+    },
+    /// List all channels
+    List,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum PermissionCommand {
+    /// Allow a permission for a user, e.g. `permission grant someone root` to add the first admin
+    Grant {
+        /// User name, the user needs to have chatted in a channel of the bot before
+        user: String,
+        /// Permission name
+        permission: String,
+        /// Twitch user ID of the user, required if more than one user has the name
+        #[structopt(long)]
+        twitch_id: Option<i32>,
+    },
+}
+
+#[derive(StructOpt, Debug)]
+pub enum CommandCommand {
+    /// List all commands with their aliases
+    List,
+}
+
+#[derive(StructOpt, Debug)]
+pub enum TokenCommand {
+    /// Create a token for a user and print it
+    Create {
+        /// Internal user ID
+        user_id: i32,
+    },
+    /// Delete all tokens of a user
+    Revoke {
+        /// Internal user ID
+        user_id: i32,
+    },
+}
+
+/// Source of permission grants in the audit log
+const AUDIT_SOURCE: &str = "cli";
+
+/// Length of generated API tokens
+const TOKEN_LENGTH: usize = 40;
+
+/// Run a subcommand other than `run`
+pub async fn run(command: CliCommand) -> Result<()> {
+    match command {
+        CliCommand::Run(_) => unreachable!("the bot is run by main"),
+        CliCommand::Migrate => {
+            connect().await?;
+            println!("Migrations done.");
+        }
+        CliCommand::Channel(ChannelCommand::Add {
+            name,
+            join,
+            prefix,
+            silent,
+        }) => {
+            let db_context = connect().await?;
+            let name = name.to_lowercase();
+            if Channel::get(&db_context, &name).await?.is_some() {
+                return Err(Error::Cli(format!("Channel {} already exists.", name)));
+            }
+            let channel = Channel::create_channel(
+                &db_context,
+                InsertChannel {
+                    twitch_room_id: None,
+                    name,
+                    join_on_start: Some(join),
+                    command_prefix: prefix,
+                    silent: Some(silent),
+                    log_retention_days: None,
+                    archive_logs: None,
+                },
+            )
+            .await?;
+            println!("Channel {} added.", channel.name);
+        }
+        CliCommand::Channel(ChannelCommand::List) => {
+            let db_context = connect().await?;
+            for channel in Channel::all(&db_context.db_pool).await? {
+                println!(
+                    "{} (prefix: {}, join on start: {}, silent: {})",
+                    channel.name,
+                    channel.command_prefix.as_deref().unwrap_or("none"),
+                    channel.join_on_start,
+                    channel.silent
+                );
+            }
+        }
+        CliCommand::Permission(PermissionCommand::Grant {
+            user,
+            permission,
+            twitch_id,
+        }) => {
+            let db_context = connect().await?;
+            create_default_permissions(&db_context).await?;
+            let user = unique_user(&db_context, &user, twitch_id).await?;
+            let permission = Permission::get_by_name(&db_context.db_pool, &permission)
+                .await?
+                .ok_or_else(|| Error::Cli(format!("Permission {} not found.", permission)))?;
+            grant_permission(&db_context, &user, &permission, AUDIT_SOURCE).await?;
+            println!("Granted {} to {}.", permission.name, user.name);
+        }
+        CliCommand::Command(CommandCommand::List) => {
+            let db_context = connect().await?;
+            let mut aliases: BTreeMap<i32, Vec<String>> = BTreeMap::new();
+            for alias in CommandAlias::all(&db_context.db_pool).await? {
+                aliases
+                    .entry(alias.command_id)
+                    .or_default()
+                    .push(alias.name);
+            }
+            let mut commands = CommandAttributes::list_all(&db_context.db_pool).await?;
+            commands.sort_by_key(|command| command.id);
+            for command in commands {
+                println!(
+                    "{} [{}{}] {}: {}",
+                    command.id,
+                    command.handler_name,
+                    if command.enabled { "" } else { ", disabled" },
+                    aliases
+                        .get(&command.id)
+                        .map(|aliases| aliases.join(", "))
+                        .unwrap_or_default(),
+                    command.description.as_deref().unwrap_or("No description")
+                );
+            }
+        }
+        CliCommand::Token(TokenCommand::Create { user_id }) => {
+            let db_context = connect().await?;
+            let user = User::get_by_id(&db_context.db_pool, user_id).await?;
+            let token = thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(TOKEN_LENGTH)
+                .collect::<String>();
+            UserApiToken::create(&db_context.db_pool, user.id, &token).await?;
+            println!("Token for {}: {}", user.name, token);
+        }
+        CliCommand::Token(TokenCommand::Revoke { user_id }) => {
+            let db_context = connect().await?;
+            let revoked = UserApiToken::revoke_all(&db_context.db_pool, user_id).await?;
+            println!("Revoked {} tokens.", revoked);
+        }
+        CliCommand::CheckConfig => {
+            let config = CerebotConfig::load()?;
+            println!("Configuration loaded.");
+            println!("username: {}", config.username().unwrap_or("not set"));
+            println!(
+                "twitch: {}",
+                if config.twitch_credentials().is_ok() {
+                    "enabled"
+                } else {
+                    "disabled, console mode only"
+                }
+            );
+            println!(
+                "rapidapi key: {}",
+                if config.rapidapi_key().is_some() {
+                    "set"
+                } else {
+                    "not set"
+                }
+            );
+            println!(
+                "archive dir: {}",
+                config
+                    .archive_dir()
+                    .map(|dir| dir.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "not set".into())
+            );
+            println!(
+                "template http hosts: {}",
+                config.template_http_hosts().join(", ")
+            );
+            println!(
+                "discord: {}",
+                if config.discord_token().is_some() {
+                    "enabled"
+                } else {
+                    "disabled"
+                }
+            );
+            println!(
+                "twitch client id: {}",
+                config.twitch_client_id().unwrap_or("not set")
+            );
+            println!("admins: {}", config.admins().join(", "));
+            DbContext::create(config.db(), config.redis()).await?;
+            println!("Database and redis connections OK.");
+        }
+    }
+    Ok(())
+}
+
+/// Connect to the database and redis, runs pending migrations first so the subcommands work on a
+/// fresh database
+async fn connect() -> Result<DbContext> {
+    let config = CerebotConfig::get()?;
+    let db_context = DbContext::create(config.db(), config.redis()).await?;
+    db_context.run_pending_migrations()?;
+    Ok(db_context)
+}
+
+/// Find the only user with a name and Twitch ID if given, fails if the name is used by more than
+/// one user
+async fn unique_user(db_context: &DbContext, name: &str, twitch_id: Option<i32>) -> Result<User> {
+    let mut users = User::list_by_name(&db_context.db_pool, name).await?;
+    if let Some(twitch_id) = twitch_id {
+        users.retain(|user| user.twitch_user_id == Some(twitch_id));
+    }
+    match users.len() {
+        0 => Err(Error::Cli(format!(
+            "User {} not found, users are added when they first chat in a channel of the bot.",
+            name
+        ))),
+        1 => Ok(users.remove(0)),
+        _ => Err(Error::Cli(format!(
+            "More than one user is named {} (Twitch IDs: {}), use --twitch-id instead.",
+            name,
+            users
+                .iter()
+                .map(|user| match user.twitch_user_id {
+                    Some(twitch_id) => twitch_id.to_string(),
+                    None => "none".into(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use structopt::StructOpt;
+
+    use super::{CliCommand, Opts, PermissionCommand};
+
+    fn parse(args: &[&str]) -> Option<Opts> {
+        Opts::from_iter_safe(std::iter::once("cerebot2").chain(args.iter().copied())).ok()
+    }
+
+    #[test]
+    fn test_run_opts_conflict_with_subcommands() {
+        assert!(parse(&["--console"]).unwrap().run.console);
+        assert!(parse(&["run", "--console"]).is_some());
+        assert!(parse(&["migrate"]).is_some());
+        assert!(parse(&["--console", "migrate"]).is_none());
+        assert!(parse(&["--user", "someone", "check-config"]).is_none());
+    }
+
+    #[test]
+    fn test_grant_args() {
+        match parse(&[
+            "permission",
+            "grant",
+            "someone",
+            "root",
+            "--twitch-id",
+            "12",
+        ]) {
+            Some(Opts {
+                command:
+                    Some(CliCommand::Permission(PermissionCommand::Grant {
+                        user,
+                        permission,
+                        twitch_id: Some(12),
+                    })),
+                ..
+            }) => assert_eq!((user.as_str(), permission.as_str()), ("someone", "root")),
+            opts => panic!("unexpected options {:?}", opts),
+        }
+        assert!(parse(&["permission", "grant", "root"]).is_none());
+    }
+}
@@ -0,0 +1,46 @@
+use futures::{SinkExt, StreamExt};
+use tmi_rs::ClientMessage;
+
+use persistence::control::BotAction;
+
+use crate::state::BotContext;
+use crate::Result;
+
+/// Subscribe to the control channel and apply all received actions to the running bot. Runs until
+/// the subscription ends.
+pub async fn listen(ctx: BotContext) -> Result<()> {
+    let mut actions = BotAction::subscribe(&ctx.db_context).await?;
+    info!("Listening for actions on the control channel");
+
+    while let Some(action) = actions.next().await {
+        match action {
+            Ok(action) => {
+                if let Err(err) = apply(&ctx, action).await {
+                    error!("Control channel action failed: {}", err);
+                }
+            }
+            Err(err) => error!("Received invalid control channel message: {}", err),
+        }
+    }
+    Ok(())
+}
+
+async fn apply(ctx: &BotContext, action: BotAction) -> Result<()> {
+    debug!("Applying control channel action {:?}", action);
+    let mut sender = &ctx.sender;
+    match action {
+        BotAction::ReloadCommands => ctx.reload_commands().await?,
+        BotAction::ReloadPermissions => ctx.reload_permissions().await?,
+        BotAction::ReloadTemplates => ctx.reload_templates().await?,
+        BotAction::JoinChannel(channel) => {
+            sender.send(ClientMessage::join(channel.as_str())).await?
+        }
+        BotAction::PartChannel(channel) => sender.send(ClientMessage::Part(channel)).await?,
+        BotAction::Say { channel, message } => {
+            sender
+                .send(ClientMessage::message(channel.as_str(), message.as_str()))
+                .await?
+        }
+    }
+    Ok(())
+}
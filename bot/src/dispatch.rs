@@ -146,7 +146,6 @@ impl<T: Send + Sync> HandlerBuilder<T> for (HandlerGroupId, &EventDispatch<T>) {
 }
 
 pub mod matchers {
-    use tmi_rs::event::Event;
 
     use async_trait::async_trait;
 
@@ -169,10 +168,7 @@ pub mod matchers {
     #[async_trait]
     impl EventMatcher<CbEvent> for MatchMessages {
         async fn match_event(&self, e: &CbEvent) -> bool {
-            match &**e {
-                Event::PrivMsg(_) | Event::Whisper(_) => true,
-                _ => false,
-            }
+            e.message().is_some()
         }
     }
 }
@@ -3,6 +3,7 @@ use thiserror::Error;
 use crate::event::LazyFetchError;
 use crate::handlers::error::CommandError;
 use crate::state::BotStateError;
+use crate::template_renderer::RenderError;
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -21,6 +22,8 @@ pub enum Error {
     TmiConfig(String),
     #[error("Chat connector error: {0}")]
     Tmi(#[from] tmi_rs::Error),
+    #[error("Discord error: {0}")]
+    Discord(#[from] serenity::Error),
     #[error("Internal bot state error: {0}")]
     BotState(#[from] BotStateError),
     #[error("{0}")]
@@ -31,6 +34,13 @@ pub enum Error {
     Join(#[from] tokio::task::JoinError),
     #[error("Template error: {0}")]
     TemplateError(#[from] tera::Error),
+    #[error("Template rendering failed: {0}")]
+    Render(#[from] RenderError),
     #[error("{0}")]
     PersistenceError(#[from] persistence::Error),
+    #[error("Twitch API error: {0}")]
+    TwitchApi(#[from] reqwest::Error),
+    /// Invalid input to a command line subcommand
+    #[error("{0}")]
+    Cli(String),
 }
@@ -1,15 +1,21 @@
-use std::ops::Deref;
 use std::sync::Arc;
 
 use async_double_checked_cell::DoubleCheckedCell;
 use tmi_rs::event::tags::*;
 use tmi_rs::event::*;
 
-use persistence::user::{ChatUserInfo, User};
+use persistence::channel::Channel;
+use persistence::user::{ChatUserInfo, PlatformUserId, User};
 
 use crate::error::Error;
 use crate::state::{BotContext, BotStateError, ChannelInfo};
+use crate::transport::discord::DiscordMessage;
 
+/// Command prefix of Discord channels added by the bot
+const DISCORD_COMMAND_PREFIX: &str = "!";
+
+/// An event received from one of the chat platforms. Handlers for messages on all platforms use
+/// `message`, platform specific events are available with `twitch` and `discord`.
 #[derive(Debug, Clone)]
 pub struct CbEvent {
     data: Arc<InnerEventData>,
@@ -17,21 +23,86 @@ pub struct CbEvent {
 
 #[derive(Debug)]
 struct InnerEventData {
-    event: Arc<Event<String>>,
+    event: PlatformEvent,
     user: DoubleCheckedCell<Result<Option<User>, LazyFetchError>>,
 }
 
+#[derive(Debug)]
+enum PlatformEvent {
+    Twitch(Arc<Event<String>>),
+    Discord(DiscordMessage),
+}
+
+/// Chat platform an event was received from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Twitch,
+    Discord,
+}
+
+/// Platform independent view of a channel message or whisper
+#[derive(Debug, Clone, Copy)]
+pub struct ChatMessage<'a> {
+    /// name of the channel, `None` for whispers and direct messages
+    pub channel: Option<&'a str>,
+    /// name of the user who sent the message
+    pub sender: &'a str,
+    pub text: &'a str,
+}
+
 impl CbEvent {
-    pub fn inner(&self) -> &Arc<Event<String>> {
-        &self.data.event
+    pub fn platform(&self) -> Platform {
+        match &self.data.event {
+            PlatformEvent::Twitch(_) => Platform::Twitch,
+            PlatformEvent::Discord(_) => Platform::Discord,
+        }
+    }
+
+    /// The Twitch chat event, `None` for events from other platforms
+    pub fn twitch(&self) -> Option<&Event<String>> {
+        match &self.data.event {
+            PlatformEvent::Twitch(event) => Some(&**event),
+            _ => None,
+        }
+    }
+
+    /// The Discord message, `None` for events from other platforms
+    pub fn discord(&self) -> Option<&DiscordMessage> {
+        match &self.data.event {
+            PlatformEvent::Discord(message) => Some(message),
+            _ => None,
+        }
+    }
+
+    /// The channel message or whisper of this event, `None` for other kinds of events
+    pub fn message(&self) -> Option<ChatMessage<'_>> {
+        match &self.data.event {
+            PlatformEvent::Twitch(event) => match &**event {
+                Event::PrivMsg(data) => Some(ChatMessage {
+                    channel: Some(data.channel().as_str()),
+                    sender: data.sender().as_ref()?,
+                    text: data.message(),
+                }),
+                Event::Whisper(data) => Some(ChatMessage {
+                    channel: None,
+                    sender: data.sender().as_ref()?,
+                    text: data.message(),
+                }),
+                _ => None,
+            },
+            PlatformEvent::Discord(message) => Some(ChatMessage {
+                channel: message.channel.as_deref(),
+                sender: &message.author,
+                text: &message.content,
+            }),
+        }
     }
 
     pub async fn user(&self, ctx: &BotContext) -> Result<Option<&User>, Error> {
         self.data
             .user
             .get_or_init(async {
-                let event = &self.data.event;
-                if let Some(user_info) = event_user_info(event)? {
+                if let Some(user_info) = event_user_info(&self.data.event)? {
                     let user = User::get_or_insert(&ctx.db_context, user_info)
                         .await
                         .map_err(|e| LazyFetchError::new(e.into()))?;
@@ -47,7 +118,11 @@ impl CbEvent {
     }
 
     pub async fn channel_info(&self, ctx: &BotContext) -> Result<Option<Arc<ChannelInfo>>, Error> {
-        let channel = match &*self.data.event {
+        let event = match &self.data.event {
+            PlatformEvent::Twitch(event) => event,
+            PlatformEvent::Discord(message) => return discord_channel_info(message, ctx).await,
+        };
+        let channel = match &**event {
             Event::PrivMsg(e) => Some(e.channel()),
             Event::Join(e) => Some(e.channel()),
             Event::Mode(e) => Some(e.channel()),
@@ -109,44 +184,88 @@ impl LazyFetchError {
 
 impl From<Arc<Event<String>>> for CbEvent {
     fn from(evt: Arc<Event<String>>) -> Self {
+        CbEvent::new(PlatformEvent::Twitch(evt))
+    }
+}
+
+impl From<DiscordMessage> for CbEvent {
+    fn from(message: DiscordMessage) -> Self {
+        CbEvent::new(PlatformEvent::Discord(message))
+    }
+}
+
+impl CbEvent {
+    fn new(event: PlatformEvent) -> Self {
         CbEvent {
             data: Arc::new(InnerEventData {
-                event: evt,
+                event,
                 user: DoubleCheckedCell::new(),
             }),
         }
     }
 }
 
-impl Deref for CbEvent {
-    type Target = Event<String>;
-
-    fn deref(&self) -> &Self::Target {
-        &*self.data.event
-    }
-}
-
-fn event_has_user_info(event: &Event<String>) -> bool {
-    match event {
-        Event::UserNotice(_) | Event::PrivMsg(_) | Event::Whisper(_) => true,
-        _ => false,
+/// Get the channel of a Discord message. Discord channels are added to the database and the bot
+/// state when the first message is received.
+async fn discord_channel_info(
+    message: &DiscordMessage,
+    ctx: &BotContext,
+) -> Result<Option<Arc<ChannelInfo>>, Error> {
+    let name = match &message.channel {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    if let Some(channel_info) = ctx.get_channel(name).await {
+        return Ok(Some(channel_info));
     }
+    let channel = Channel::get_or_persist_discord(
+        &ctx.db_context,
+        message.channel_id as i64,
+        name,
+        DISCORD_COMMAND_PREFIX,
+    )
+    .await?;
+    debug!(
+        "Discord channel {} is {}",
+        message.channel_display.as_deref().unwrap_or_default(),
+        channel.name
+    );
+    ctx.update_channel(ChannelInfo {
+        data: channel,
+        state: None,
+    })
+    .await;
+    let channel_info = ctx
+        .get_channel(name)
+        .await
+        .ok_or_else::<Error, _>(|| BotStateError::MissingChannel.into())?;
+    Ok(Some(channel_info))
 }
 
-fn event_user_info(event: &Event<String>) -> Result<Option<ChatUserInfo>, Error> {
-    Ok(Some(match event {
+fn event_user_info(event: &PlatformEvent) -> Result<Option<ChatUserInfo>, Error> {
+    let event = match event {
+        PlatformEvent::Twitch(event) => event,
+        PlatformEvent::Discord(message) => {
+            return Ok(Some(ChatUserInfo {
+                platform_id: PlatformUserId::Discord(message.author_id as i64),
+                name: &message.author,
+                display_name: message.author_nick.as_deref(),
+            }))
+        }
+    };
+    Ok(Some(match &**event {
         Event::UserNotice(data) => ChatUserInfo {
-            twitch_user_id: data.user_id()? as i32,
+            platform_id: PlatformUserId::Twitch(data.user_id()? as i32),
             name: data.login()?,
             display_name: data.display_name(),
         },
         Event::PrivMsg(data) => ChatUserInfo {
-            twitch_user_id: data.user_id()? as i32,
+            platform_id: PlatformUserId::Twitch(data.user_id()? as i32),
             name: data.sender().as_ref().expect("privmsg without sender"),
             display_name: data.display_name(),
         },
         Event::Whisper(data) => ChatUserInfo {
-            twitch_user_id: data.user_id()? as i32,
+            platform_id: PlatformUserId::Twitch(data.user_id()? as i32),
             name: data.sender().as_ref().expect("whisper without sender"),
             display_name: data.display_name(),
         },
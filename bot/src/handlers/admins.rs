@@ -0,0 +1,168 @@
+use fnv::FnvHashSet;
+
+use async_trait::async_trait;
+use persistence::audit::AuditLog;
+use persistence::permissions::{Permission, PermissionState, UserPermission};
+use persistence::user::{PlatformUserId, User};
+use persistence::DbContext;
+use util::sync::RwLock;
+
+use crate::config::CerebotConfig;
+use crate::dispatch::EventHandler;
+use crate::event::CbEvent;
+use crate::state::BotContext;
+use crate::twitch_api::TwitchApi;
+use crate::Result;
+
+const ROOT_PERMISSION: &str = "root";
+
+/// Source of the admin grants in the audit log
+const AUDIT_SOURCE: &str = "config";
+
+/// Grants the `root` permission to the admins from the bot config. The names are looked up on
+/// Twitch on startup, so renamed or impersonated accounts don't get it. Admins already known to
+/// the database get it right away, the others when their first message is received.
+#[derive(Debug)]
+pub struct AdminHandler {
+    ctx: BotContext,
+    /// Twitch user IDs of admins that haven't been seen yet
+    pending: RwLock<FnvHashSet<i32>>,
+}
+
+#[async_trait]
+impl EventHandler<CbEvent> for AdminHandler {
+    async fn create(ctx: &BotContext) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        let mut pending = FnvHashSet::default();
+        for twitch_id in admin_twitch_ids().await {
+            match User::get(&ctx.db_context, PlatformUserId::Twitch(twitch_id)).await {
+                Ok(user) => grant_root(ctx, &user).await?,
+                Err(persistence::Error::NotFound) => {
+                    info!(
+                        "Admin with Twitch ID {} will get root on their first message",
+                        twitch_id
+                    );
+                    pending.insert(twitch_id);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(AdminHandler {
+            ctx: (*ctx).clone(),
+            pending: RwLock::new(pending),
+        })
+    }
+
+    async fn run(&self, event: &CbEvent) -> Result<()> {
+        if self.pending.read().await.is_empty() {
+            return Ok(());
+        }
+        let user = match event.user(&self.ctx).await? {
+            Some(user) => user,
+            None => return Ok(()),
+        };
+        let twitch_id = match user.platform_id().twitch() {
+            Some(twitch_id) if self.pending.read().await.contains(&twitch_id) => twitch_id,
+            _ => return Ok(()),
+        };
+        grant_root(&self.ctx, user).await?;
+        self.pending.write().await.remove(&twitch_id);
+        Ok(())
+    }
+}
+
+/// Look up the Twitch user IDs of the admins. Lookup errors are logged, the bot runs without
+/// admins then.
+async fn admin_twitch_ids() -> Vec<i32> {
+    // the bot fails earlier without a valid config, the test harness runs without one
+    let config = match CerebotConfig::get() {
+        Ok(config) if !config.admins().is_empty() => config,
+        _ => return vec![],
+    };
+    let names = config
+        .admins()
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect::<Vec<_>>();
+    let helix_users = match TwitchApi::new(&config) {
+        Ok(api) => api.users_by_login(&names).await,
+        Err(err) => Err(err),
+    };
+    let helix_users = match helix_users {
+        Ok(helix_users) => helix_users,
+        Err(err) => {
+            error!("Looking up the admins on Twitch failed: {}", err);
+            return vec![];
+        }
+    };
+    for name in &names {
+        if !helix_users.iter().any(|user| &user.login == name) {
+            warn!("Admin {} not found on Twitch", name);
+        }
+    }
+    helix_users
+        .into_iter()
+        .filter_map(|user| match user.id.parse() {
+            Ok(twitch_id) => Some(twitch_id),
+            Err(_) => {
+                warn!("Invalid Twitch ID {} of admin {}", user.id, user.login);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Allow `root` for a user unless it's already allowed or explicitly denied
+async fn grant_root(ctx: &BotContext, user: &User) -> Result<()> {
+    match UserPermission::get_named(&ctx.db_context, user.id, ROOT_PERMISSION).await {
+        Ok(PermissionState::Allow) => return Ok(()),
+        Ok(PermissionState::Deny) => {
+            warn!(
+                "Not granting {} to admin {} (user id {}), it is denied explicitly",
+                ROOT_PERMISSION, user.name, user.id
+            );
+            return Ok(());
+        }
+        Err(persistence::Error::NotFound) => {}
+        Err(err) => return Err(err.into()),
+    }
+    let permission = Permission::get_by_name(&ctx.db_context.db_pool, ROOT_PERMISSION)
+        .await?
+        .ok_or(persistence::Error::NotFound)?;
+    grant_permission(&ctx.db_context, user, &permission, AUDIT_SOURCE).await
+}
+
+/// Allow a permission for a user and add the grant to the audit log. `source` is where the grant
+/// came from, e.g. "config" for the admins from the bot config.
+pub async fn grant_permission(
+    db_context: &DbContext,
+    user: &User,
+    permission: &Permission,
+    source: &str,
+) -> Result<()> {
+    UserPermission::set_state(
+        &db_context.db_pool,
+        user.id,
+        permission.id,
+        PermissionState::Allow,
+    )
+    .await?;
+    AuditLog::add(
+        &db_context.db_pool,
+        user.id,
+        &format!("granted {}", permission.name),
+        source,
+    )
+    .await?;
+    info!(
+        target: "audit",
+        "Granted {} to {} (user id {}), source: {}",
+        permission.name,
+        user.name,
+        user.id,
+        source
+    );
+    Ok(())
+}
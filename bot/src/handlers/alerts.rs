@@ -0,0 +1,211 @@
+use fnv::FnvHashMap;
+use futures::SinkExt;
+use tmi_rs::event::*;
+use tmi_rs::ClientMessage;
+
+use async_trait::async_trait;
+use persistence::alerts::{start_gift_bomb, take_gift_bomb_gift, AlertKind, ChannelAlert};
+use persistence::chat_event::{UserNotice, UserNoticeKind};
+
+use crate::dispatch::EventHandler;
+use crate::event::CbEvent;
+use crate::state::BotContext;
+use crate::Result;
+
+/// Posts the configured chat alerts for subscriptions, gifted subs, raids and cheers
+#[derive(Debug)]
+pub struct AlertHandler {
+    ctx: BotContext,
+}
+
+/// An event that can trigger an alert
+struct AlertEvent {
+    kind: AlertKind,
+    /// value compared to the alert thresholds
+    value: i32,
+    /// login name of the user that caused the event
+    login: String,
+    context: tera::Context,
+}
+
+#[async_trait]
+impl EventHandler<CbEvent> for AlertHandler {
+    async fn create(ctx: &BotContext) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(AlertHandler {
+            ctx: (*ctx).clone(),
+        })
+    }
+
+    async fn run(&self, event: &CbEvent) -> Result<()> {
+        let (channel_name, alert_event) = match event.twitch() {
+            Some(Event::UserNotice(data)) => match data.tags() {
+                Some(tags) => (data.channel(), user_notice_alert(tags, data.message())),
+                None => return Ok(()),
+            },
+            Some(Event::PrivMsg(data)) => match (data.tags(), data.sender()) {
+                (Some(tags), Some(sender)) => {
+                    (data.channel(), cheer_alert(tags, sender, data.message()))
+                }
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+        let alert_event = match alert_event {
+            Some(alert_event) => alert_event,
+            None => return Ok(()),
+        };
+        let channel = match event.channel_info(&self.ctx).await? {
+            Some(channel) if !channel.data.silent => channel,
+            _ => return Ok(()),
+        };
+        let channel_id = channel.data.id;
+        let redis_pool = &self.ctx.db_context.redis_pool;
+
+        match alert_event.kind {
+            AlertKind::Giftbomb => {
+                start_gift_bomb(
+                    redis_pool,
+                    channel_id,
+                    &alert_event.login,
+                    alert_event.value,
+                )
+                .await?;
+            }
+            AlertKind::Subgift => {
+                // gifts belonging to a gift bomb are covered by its alert
+                if take_gift_bomb_gift(redis_pool, channel_id, &alert_event.login).await? {
+                    return Ok(());
+                }
+            }
+            _ => {}
+        }
+
+        let alert = ChannelAlert::get_matching(
+            &self.ctx.db_context.db_pool,
+            channel_id,
+            alert_event.kind,
+            alert_event.value,
+        )
+        .await?;
+        let alert = match alert {
+            Some(alert) => alert,
+            None => return Ok(()),
+        };
+        if !alert.claim_interval(redis_pool).await? {
+            debug!("Alert {} skipped, minimum interval not reached", alert.id);
+            return Ok(());
+        }
+
+        let output = self
+            .ctx
+            .templates
+            .load()
+            .render_alert(alert.id, channel_id, alert_event.context, &self.ctx)
+            .await?;
+        let trimmed_output = output.trim();
+        if !trimmed_output.is_empty() {
+            let mut sender = &self.ctx.sender;
+            sender
+                .send(ClientMessage::message(
+                    channel_name.as_str(),
+                    trimmed_output,
+                ))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn user_notice_alert(tags: &FnvHashMap<String, String>, message: &str) -> Option<AlertEvent> {
+    let notice = UserNotice::from_tags(tags);
+    let login = tag(tags, "login")?;
+    let user = display_name(tags).unwrap_or_else(|| login.clone());
+    let tier = notice.sub_plan.as_deref().map(sub_tier).unwrap_or(1);
+    let mut context = tera::Context::new();
+
+    let (kind, value) = match notice.notice_kind? {
+        UserNoticeKind::Sub => {
+            context.insert("user", &user);
+            context.insert("tier", &tier);
+            context.insert("message", message);
+            (AlertKind::Sub, tier)
+        }
+        UserNoticeKind::Resub => {
+            let months = notice.months.unwrap_or(1);
+            context.insert("user", &user);
+            context.insert("tier", &tier);
+            context.insert("months", &months);
+            context.insert("message", message);
+            (AlertKind::Resub, months)
+        }
+        UserNoticeKind::Subgift | UserNoticeKind::Anonsubgift => {
+            context.insert("gifter", &user);
+            context.insert("recipient", &notice.recipient_name);
+            context.insert("tier", &tier);
+            context.insert("months", &notice.months);
+            (AlertKind::Subgift, tier)
+        }
+        UserNoticeKind::Submysterygift => {
+            let count = notice.gift_count.unwrap_or(1);
+            context.insert("gifter", &user);
+            context.insert("tier", &tier);
+            context.insert("count", &count);
+            (AlertKind::Giftbomb, count)
+        }
+        UserNoticeKind::Raid => {
+            let viewers = notice.viewer_count.unwrap_or(0);
+            let raider = tag(tags, "msg-param-displayName").unwrap_or_else(|| user.clone());
+            context.insert("raider", &raider);
+            context.insert("viewers", &viewers);
+            (AlertKind::Raid, viewers)
+        }
+        _ => return None,
+    };
+    Some(AlertEvent {
+        kind,
+        value,
+        login,
+        context,
+    })
+}
+
+fn cheer_alert(
+    tags: &FnvHashMap<String, String>,
+    sender: &str,
+    message: &str,
+) -> Option<AlertEvent> {
+    let bits: i32 = tags.get("bits")?.parse().ok()?;
+    let login = sender.to_owned();
+    let user = display_name(tags).unwrap_or_else(|| login.clone());
+    let mut context = tera::Context::new();
+    context.insert("user", &user);
+    context.insert("bits", &bits);
+    context.insert("message", message);
+    Some(AlertEvent {
+        kind: AlertKind::Cheer,
+        value: bits,
+        login,
+        context,
+    })
+}
+
+fn tag(tags: &FnvHashMap<String, String>, name: &str) -> Option<String> {
+    tags.get(name).filter(|value| !value.is_empty()).cloned()
+}
+
+fn display_name(tags: &FnvHashMap<String, String>) -> Option<String> {
+    tag(tags, "display-name")
+}
+
+/// Sub tier from the `msg-param-sub-plan` tag, 0 for prime subs
+fn sub_tier(plan: &str) -> i32 {
+    match plan {
+        "Prime" => 0,
+        "2000" => 2,
+        "3000" => 3,
+        _ => 1,
+    }
+}
@@ -27,12 +27,12 @@ impl EventHandler<CbEvent> for BotStateHandler {
 
     async fn run(&self, event: &CbEvent) -> Result<()> {
         let ctx = &self.ctx;
-        match &**event {
-            Event::Reconnect(_) => {
+        match event.twitch() {
+            Some(Event::Reconnect(_)) => {
                 // mark for restart on next message
                 ctx.restart().await?;
             }
-            Event::RoomState(data) => {
+            Some(Event::RoomState(data)) => {
                 let channel = Channel::get_or_persist_roomstate(
                     &ctx.db_context,
                     UpdateChannelId {
@@ -53,11 +53,13 @@ impl EventHandler<CbEvent> for BotStateHandler {
                 })
                 .await;
             }
-            Event::PrivMsg(data) => {
-                info!("{}: {}", data.sender().as_ref().unwrap(), data.message());
-            }
             _ => {}
         }
+        if let Some(message) = event.message() {
+            if message.channel.is_some() {
+                info!("{}: {}", message.sender, message.text);
+            }
+        }
         Ok(())
     }
 }
@@ -1,10 +1,12 @@
+use std::num::NonZeroU16;
+
 use futures::SinkExt;
 use structopt::StructOpt;
 use tmi_rs::ClientMessage;
 
 use async_trait::async_trait;
 use persistence::channel::{Channel, InsertChannel, UpdateChannelSettings};
-use persistence::commands::attributes::InsertCommandAttributes;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
 use persistence::permissions::{
     create_permissions, AddPermission, NewPermissionAttributes, PermissionState,
 };
@@ -51,6 +53,10 @@ impl CommandHandler for ChannelManagerCommand {
         }
     }
 
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<ChannelCommandArgs>()
+    }
+
     async fn create(ctx: &BotContext) -> Result<Box<dyn CommandHandler>>
     where
         Self: Sized,
@@ -141,6 +147,21 @@ struct ChannelSettingsArgs {
 
     #[structopt(long, conflicts_with = "prefix")]
     no_prefix: bool,
+
+    /// days to keep chat logs
+    #[structopt(long)]
+    retention: Option<NonZeroU16>,
+
+    /// keep chat logs forever
+    #[structopt(long, conflicts_with = "retention")]
+    no_retention: bool,
+
+    /// export expired chat logs to files before removing them
+    #[structopt(long)]
+    archive: bool,
+
+    #[structopt(long, conflicts_with = "archive")]
+    no_archive: bool,
 }
 
 impl ChannelSettingsArgs {
@@ -167,6 +188,20 @@ impl ChannelSettingsArgs {
             } else {
                 None
             },
+            log_retention_days: if self.retention.is_some() {
+                Some(self.retention.map(|days| i32::from(days.get())))
+            } else if self.no_retention {
+                Some(None)
+            } else {
+                None
+            },
+            archive_logs: if self.archive {
+                Some(true)
+            } else if self.no_archive {
+                Some(false)
+            } else {
+                None
+            },
         }
     }
 
@@ -189,6 +224,14 @@ impl ChannelSettingsArgs {
             } else {
                 None
             },
+            log_retention_days: self.retention.map(|days| i32::from(days.get())),
+            archive_logs: if self.archive {
+                Some(true)
+            } else if self.no_archive {
+                Some(false)
+            } else {
+                None
+            },
         }
     }
 }
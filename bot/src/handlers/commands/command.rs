@@ -2,7 +2,7 @@ use structopt::StructOpt;
 
 use async_trait::async_trait;
 use persistence::commands::alias::CommandAlias;
-use persistence::commands::attributes::InsertCommandAttributes;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
 use persistence::permissions::{
     create_permissions, AddPermission, NewPermissionAttributes, PermissionState,
 };
@@ -49,6 +49,10 @@ impl CommandHandler for CommandManagerCommand {
         Ok(())
     }
 
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<CommandsCommandArgs>()
+    }
+
     async fn create(ctx: &BotContext) -> Result<Box<dyn CommandHandler>>
     where
         Self: Sized,
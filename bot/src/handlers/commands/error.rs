@@ -7,6 +7,11 @@ pub enum CommandError {
     ReplyError(&'static str),
     #[error("Quote mismatch in command arguments")]
     QuoteMismatch,
+    #[error("Invalid command pipeline: {0}")]
+    Pipeline(&'static str),
+    /// a command of a pipeline didn't run, with the command name and the reason
+    #[error("Command pipeline stopped, {0} {1}")]
+    PipelineStage(String, &'static str),
     #[error("{0}")]
     ArgumentError(structopt::clap::Error),
     #[error("Permission requirement {0:?} is not fulfilled")]
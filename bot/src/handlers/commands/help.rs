@@ -0,0 +1,214 @@
+use fnv::FnvHashMap;
+use structopt::StructOpt;
+
+use async_trait::async_trait;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
+use persistence::commands::channel_config::ChannelCommandConfig;
+use persistence::permissions::UserPermission;
+
+use crate::handlers::commands::{
+    structopt_usage, CommandContext, CommandHandler, OPTS_HELP_TEMPLATE,
+};
+use crate::state::BotContext;
+use crate::util::initialize_command;
+use crate::Result;
+
+#[derive(Debug)]
+pub struct HelpCommandHandler {
+    ctx: BotContext,
+}
+
+const NAME: &str = "help";
+
+/// Maximum length of the command list, longer lists are cut off
+const MAX_LIST_CHARS: usize = 450;
+
+/// Show the commands you can use or details about a command
+#[derive(StructOpt, Debug)]
+#[structopt(name = "help", template(OPTS_HELP_TEMPLATE))]
+struct HelpArgs {
+    /// Command to show details for
+    command: Option<String>,
+}
+
+#[async_trait]
+impl CommandHandler for HelpCommandHandler {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
+        let args = match cmd.parse_args::<HelpArgs>(&self.ctx).await? {
+            Some(args) => args,
+            None => return Ok(()),
+        };
+        match args.command {
+            Some(command) => self.command_help(cmd, &command).await,
+            None => self.command_list(cmd).await,
+        }
+    }
+
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<HelpArgs>()
+    }
+
+    async fn create(ctx: &BotContext) -> Result<Box<dyn CommandHandler>>
+    where
+        Self: Sized,
+    {
+        initialize_command(
+            ctx,
+            InsertCommandAttributes {
+                handler_name: NAME.into(),
+                description: Some("Show the available commands and how to use them".into()),
+                enabled: true,
+                default_active: true,
+                cooldown: Some(5000),
+                whisper_enabled: true,
+            },
+            vec!["commands:read"],
+            vec!["help"],
+        )
+        .await?;
+
+        Ok(Box::new(HelpCommandHandler { ctx: ctx.clone() }) as Box<dyn CommandHandler>)
+    }
+}
+
+impl HelpCommandHandler {
+    /// List the commands the user is allowed to use here, commands that are not active in the
+    /// channel are marked
+    async fn command_list(&self, cmd: &CommandContext<'_>) -> Result<()> {
+        let user_permission_ids = match cmd.event.user(&self.ctx).await? {
+            Some(user) => UserPermission::get_by_user_id(&self.ctx.db_context, user.id).await?,
+            None => vec![],
+        };
+        let permission_store = self.ctx.permissions.load();
+        let command_store = self.ctx.commands.load();
+        let channel_configs: FnvHashMap<i32, ChannelCommandConfig> = match cmd.channel {
+            Some(channel) => ChannelCommandConfig::list_for_channel(
+                &self.ctx.db_context.db_pool,
+                channel.data.id,
+            )
+            .await?
+            .into_iter()
+            .map(|config| (config.command_id, config))
+            .collect(),
+            None => FnvHashMap::default(),
+        };
+
+        let mut commands = vec![];
+        for attributes in command_store.commands() {
+            if !attributes.enabled || (cmd.channel.is_none() && !attributes.whisper_enabled) {
+                continue;
+            }
+            let alias = match command_store.aliases_of(attributes.id).first() {
+                Some(alias) => alias.to_string(),
+                None => continue,
+            };
+            let permissions = permission_store
+                .get_by_command(&self.ctx.db_context, attributes.id)
+                .await?;
+            if !permissions.requirements().check(&user_permission_ids) {
+                continue;
+            }
+            let active = cmd.channel.is_none()
+                || channel_configs
+                    .get(&attributes.id)
+                    .and_then(|config| config.active)
+                    .unwrap_or(attributes.default_active);
+            if active {
+                commands.push(alias);
+            } else {
+                commands.push(format!("{} (inactive)", alias));
+            }
+        }
+        commands.sort();
+
+        let mut message = format!("Commands: {}", commands.join(", "));
+        if let Some((index, _)) = message.char_indices().nth(MAX_LIST_CHARS) {
+            message.truncate(index);
+            message.push('…');
+        }
+        cmd.reply(&message, &self.ctx.sender).await
+    }
+
+    /// Show description, aliases, cooldown and usage of a command
+    async fn command_help(&self, cmd: &CommandContext<'_>, name: &str) -> Result<()> {
+        let prefix = cmd
+            .channel
+            .and_then(|channel| channel.data.command_prefix.as_deref())
+            .unwrap_or_default();
+        let name = name.trim_start_matches(prefix).to_lowercase();
+        let command_store = self.ctx.commands.load();
+        let attributes = match command_store.get_by_alias(&name) {
+            Some(attributes) if attributes.enabled => attributes,
+            _ => return cmd.reply("Command not found.", &self.ctx.sender).await,
+        };
+
+        let mut parts = vec![format!(
+            "{}{}: {}",
+            prefix,
+            name,
+            attributes
+                .description
+                .as_deref()
+                .unwrap_or("No description")
+        )];
+        let aliases = command_store
+            .aliases_of(attributes.id)
+            .into_iter()
+            .filter(|alias| *alias != name)
+            .map(|alias| format!("{}{}", prefix, alias))
+            .collect::<Vec<_>>();
+        if !aliases.is_empty() {
+            parts.push(format!("aliases: {}", aliases.join(", ")));
+        }
+        if let Some(cooldown) = self.cooldown(cmd, attributes).await? {
+            parts.push(format!("cooldown: {}s", cooldown.as_secs()));
+        }
+        if let Some(usage) = cmd.command_usage(attributes) {
+            parts.push(format!("usage: {}", usage));
+        }
+        if !self.is_active(cmd, attributes).await? {
+            parts.push("not active in this channel".to_string());
+        }
+        cmd.reply(&parts.join(" | "), &self.ctx.sender).await
+    }
+
+    /// Whether a command is active in the current channel, always true for whispers
+    async fn is_active(
+        &self,
+        cmd: &CommandContext<'_>,
+        attributes: &CommandAttributes,
+    ) -> Result<bool> {
+        match cmd.channel {
+            Some(channel) => {
+                let config =
+                    ChannelCommandConfig::get(&self.ctx.db_context, channel.data.id, attributes.id)
+                        .await?;
+                Ok(config
+                    .and_then(|config| config.active)
+                    .unwrap_or(attributes.default_active))
+            }
+            None => Ok(true),
+        }
+    }
+
+    /// Cooldown of a command in the current channel
+    async fn cooldown(
+        &self,
+        cmd: &CommandContext<'_>,
+        attributes: &CommandAttributes,
+    ) -> Result<Option<std::time::Duration>> {
+        let channel_cooldown = match cmd.channel {
+            Some(channel) => {
+                ChannelCommandConfig::get(&self.ctx.db_context, channel.data.id, attributes.id)
+                    .await?
+                    .and_then(|config| config.cooldown.as_deref().copied())
+            }
+            None => None,
+        };
+        Ok(channel_cooldown.or_else(|| attributes.cooldown.as_deref().copied()))
+    }
+}
@@ -0,0 +1,134 @@
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use structopt::StructOpt;
+
+use async_trait::async_trait;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
+use persistence::user::{save_discord_link_code, take_discord_link_code, User};
+
+use crate::handlers::commands::{
+    structopt_usage, CommandContext, CommandHandler, OPTS_HELP_TEMPLATE,
+};
+use crate::state::BotContext;
+use crate::util::initialize_command;
+use crate::Result;
+
+#[derive(Debug)]
+pub struct LinkCommandHandler {
+    ctx: BotContext,
+}
+
+const NAME: &str = "link";
+
+const CODE_LENGTH: usize = 8;
+
+/// Link your Discord account to your Twitch account. Send it to the bot on Discord to get a code,
+/// then send the code on Twitch.
+#[derive(StructOpt, Debug)]
+#[structopt(name = "link", template(OPTS_HELP_TEMPLATE))]
+struct LinkArgs {
+    /// Code received on Discord
+    code: Option<String>,
+}
+
+#[async_trait]
+impl CommandHandler for LinkCommandHandler {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
+        let args = match cmd.parse_args::<LinkArgs>(&self.ctx).await? {
+            Some(args) => args,
+            None => return Ok(()),
+        };
+        match cmd.event.discord() {
+            Some(message) => self.create_code(cmd, message.author_id as i64).await,
+            None => self.link(cmd, args.code).await,
+        }
+    }
+
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<LinkArgs>()
+    }
+
+    async fn create(ctx: &BotContext) -> Result<Box<dyn CommandHandler>>
+    where
+        Self: Sized,
+    {
+        initialize_command(
+            ctx,
+            InsertCommandAttributes {
+                handler_name: NAME.into(),
+                description: Some("Link your Discord account to your Twitch account".into()),
+                enabled: true,
+                default_active: true,
+                cooldown: Some(5000),
+                whisper_enabled: true,
+            },
+            Vec::<&str>::new(),
+            vec!["link"],
+        )
+        .await?;
+
+        Ok(Box::new(LinkCommandHandler { ctx: ctx.clone() }) as Box<dyn CommandHandler>)
+    }
+}
+
+impl LinkCommandHandler {
+    /// Send a link code to a Discord user, only in direct messages so nobody else can use it
+    async fn create_code(&self, cmd: &CommandContext<'_>, discord_user_id: i64) -> Result<()> {
+        if cmd.channel.is_some() {
+            return cmd
+                .reply(
+                    "Send me this command as a direct message to get a link code",
+                    &self.ctx.sender,
+                )
+                .await;
+        }
+        let code = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(CODE_LENGTH)
+            .collect::<String>();
+        save_discord_link_code(&self.ctx.db_context.redis_pool, &code, discord_user_id).await?;
+        cmd.reply(
+            &format!(
+                "Send \"link {}\" to the bot on Twitch within 10 minutes to link your accounts",
+                code
+            ),
+            &self.ctx.sender,
+        )
+        .await
+    }
+
+    /// Link the Discord account a code was created for to the Twitch user
+    async fn link(&self, cmd: &CommandContext<'_>, code: Option<String>) -> Result<()> {
+        let code = match code {
+            Some(code) => code,
+            None => {
+                return cmd
+                    .reply(
+                        "Send the link command to the bot on Discord first to get a code",
+                        &self.ctx.sender,
+                    )
+                    .await
+            }
+        };
+        let user = match cmd.event.user(&self.ctx).await? {
+            Some(user) => user,
+            None => return Ok(()),
+        };
+        let discord_user_id =
+            match take_discord_link_code(&self.ctx.db_context.redis_pool, &code).await? {
+                Some(discord_user_id) => discord_user_id,
+                None => {
+                    return cmd
+                        .reply("Unknown or expired link code", &self.ctx.sender)
+                        .await
+                }
+            };
+        User::link_discord(&self.ctx.db_context, user.id, discord_user_id).await?;
+        cmd.reply("Your Discord account is linked now", &self.ctx.sender)
+            .await
+    }
+}
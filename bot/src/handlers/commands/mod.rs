@@ -1,5 +1,5 @@
 use std::fmt::Debug;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use fnv::FnvHashMap;
 use futures::SinkExt;
@@ -7,19 +7,20 @@ use once_cell::sync::Lazy;
 use regex::Regex;
 use structopt::clap::AppSettings;
 use structopt::StructOpt;
-use tmi_rs::event::*;
 use tmi_rs::{ChatSender, ClientMessage};
 
 use async_trait::async_trait;
 use persistence::commands::attributes::CommandAttributes;
 use persistence::commands::channel_config::ChannelCommandConfig;
 use persistence::commands::permission::PermissionRequirement;
+use persistence::live_events::{LiveCommandEvent, LiveEvent};
 use persistence::permissions::{
     create_permissions, AddPermission, NewPermissionAttributes, PermissionState, UserPermission,
 };
+use persistence::stats;
 
 use crate::dispatch::EventHandler;
-use crate::event::CbEvent;
+use crate::event::{CbEvent, ChatMessage};
 use crate::handlers::commands::error::CommandError;
 use crate::state::{BotContext, BotStateError, ChannelInfo};
 use crate::util::split_args;
@@ -29,10 +30,14 @@ use std::borrow::Cow;
 mod channel;
 mod command;
 pub mod error;
+mod help;
+mod link;
 mod netflix;
+mod pipeline;
 mod reload;
 mod restart;
 mod say;
+mod storage;
 mod templates;
 
 #[async_trait]
@@ -41,6 +46,12 @@ pub trait CommandHandler: Send + Sync + Debug {
 
     async fn run(&self, cmd: &CommandContext<'_>) -> Result<()>;
 
+    /// Usage of the command shown by the help command, structopt based commands can use
+    /// `structopt_usage`
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        None
+    }
+
     async fn create(bot: &BotContext) -> Result<Box<dyn CommandHandler>>
     where
         Self: Sized;
@@ -66,6 +77,9 @@ impl EventHandler<CbEvent> for CommandRouter {
             &reload::ReloadCommandHandler::create,
             &restart::RestartCommandHandler::create,
             &netflix::NetflixCommandHandler::create,
+            &storage::LogStorageCommandHandler::create,
+            &help::HelpCommandHandler::create,
+            &link::LinkCommandHandler::create,
         ];
 
         init_command_router_permissions(ctx).await?;
@@ -86,15 +100,19 @@ impl EventHandler<CbEvent> for CommandRouter {
     async fn run(&self, event: &CbEvent) -> Result<()> {
         // will contain everything but the command prefix
         let args;
-        // will contain only the name of the command alias (without prefix)
-        let command_name;
         // channel where the command is called, if applicable
         let channel_opt: Option<Arc<ChannelInfo>>;
 
+        // abort on any non-message events
+        let message = match event.message() {
+            Some(message) => message,
+            None => return Ok(()),
+        };
+
         // first extract available data from the event, depending on if it's a
         // channel or whisper message
-        match &**event {
-            Event::PrivMsg(data) => {
+        match message.channel {
+            Some(_) => {
                 channel_opt = event.channel_info(&self.ctx).await?;
                 let channel = channel_opt
                     .as_ref()
@@ -105,7 +123,7 @@ impl EventHandler<CbEvent> for CommandRouter {
                     return Ok(());
                 }
 
-                let message = data.message().as_str();
+                let message = message.text;
 
                 // match channel command prefix, abort on empty prefix or no match
                 let prefix = channel.data.command_prefix.as_ref().unwrap();
@@ -113,36 +131,64 @@ impl EventHandler<CbEvent> for CommandRouter {
                     return Ok(());
                 }
 
-                // extract name of the command
-                let command_end_index = message.split_at(prefix.len()).1.find(char::is_whitespace);
-                command_name = if let Some(command_end_index) = command_end_index {
-                    &message[prefix.len()..(command_end_index + prefix.len())]
-                } else {
-                    &message[prefix.len()..]
-                };
-
-                debug!("{}", command_name);
-
                 args = &message[prefix.len()..];
             }
-            Event::Whisper(data) => {
+            None => {
                 channel_opt = None;
 
-                let message = data.message().as_str();
+                args = message.text;
+            }
+        }
 
-                // extract name of the command
-                let command_end_index = message.find(char::is_whitespace);
-                command_name = if let Some(command_end_index) = command_end_index {
-                    &message[0..command_end_index]
-                } else {
-                    &message
-                };
+        // ignore messages that don't start with a known command, even if they look like pipelines
+        if self
+            .ctx
+            .commands
+            .load()
+            .get_by_alias(command_name(args))
+            .is_none()
+        {
+            return Ok(());
+        }
 
-                args = message;
+        let parsed = {
+            let command_store = self.ctx.commands.load();
+            pipeline::parse(args, |name| command_store.get_by_alias(name).is_some())
+        };
+        let result = match parsed {
+            Ok(None) => self
+                .run_line(event, channel_opt.as_ref(), args, None)
+                .await
+                .map(|_| ()),
+            Ok(Some(stages)) => self.run_pipeline(event, channel_opt.as_ref(), stages).await,
+            Err(err) => Err(err),
+        };
+        match result {
+            Err(Error::Command(err @ CommandError::Pipeline(_))) => {
+                send_reply(event, &err.to_string(), &self.ctx.sender).await
+            }
+            Err(Error::Command(err @ CommandError::PipelineStage(..))) => {
+                send_reply(event, &err.to_string(), &self.ctx.sender).await
             }
-            // abort on any non-message events
-            _ => return Ok(()),
+            result => result,
         }
+    }
+}
+
+impl CommandRouter {
+    /// Run a single command call. `line` is the message without command prefix. Replies are
+    /// collected in `output` instead of being sent if given. Returns why the command didn't run, if
+    /// it didn't.
+    async fn run_line(
+        &self,
+        event: &CbEvent,
+        channel: Option<&Arc<ChannelInfo>>,
+        line: &str,
+        output: Option<&ReplyBuffer>,
+    ) -> Result<Option<SkipReason>> {
+        let line = line.trim();
+        let command_name = command_name(line);
+        debug!("{}", command_name);
 
         let command_store = self.ctx.commands.load();
         let attributes = command_store.get_by_alias(command_name);
@@ -152,34 +198,94 @@ impl EventHandler<CbEvent> for CommandRouter {
 
         if let (Some(attributes), Some(handler)) = (attributes, handler) {
             debug!("Preparing command handler {}", handler.name());
-            if !attributes.whisper_enabled && channel_opt.is_none() {
+            if !attributes.whisper_enabled && channel.is_none() {
                 debug!("Command can't be used in whispers, ignoring");
-                return Ok(());
+                return Ok(Some("can't be used in whispers"));
             }
 
             self.run_command(
                 &**handler,
                 CommandContext {
-                    args,
+                    args: line,
                     event,
-                    channel: channel_opt.as_ref(),
+                    channel,
                     command_name,
                     attributes,
+                    handlers: &self.command_handlers,
+                    output,
                 },
             )
             .await
         } else {
-            Ok(())
+            Ok(Some("is not a command"))
         }
     }
-}
 
-impl CommandRouter {
+    /// Run the commands of a pipeline in order, see `pipeline`. Only the replies of the last
+    /// command are sent.
+    async fn run_pipeline(
+        &self,
+        event: &CbEvent,
+        channel: Option<&Arc<ChannelInfo>>,
+        stages: Vec<pipeline::Stage<'_>>,
+    ) -> Result<()> {
+        let last_stage = stages.len() - 1;
+        let mut piped_input: Option<String> = None;
+        for (index, stage) in stages.into_iter().enumerate() {
+            let mut line = String::new();
+            for segment in stage {
+                match segment {
+                    pipeline::Segment::Text(text) => line.push_str(text),
+                    pipeline::Segment::Substitution(command) => {
+                        line.push_str(&self.capture(event, channel, command).await?)
+                    }
+                }
+            }
+            if let Some(input) = piped_input.take() {
+                line = format!("{} {}", line.trim(), input);
+            }
+
+            if index == last_stage {
+                if let Some(reason) = self.run_line(event, channel, &line, None).await? {
+                    return Err(stage_error(&line, reason));
+                }
+            } else {
+                piped_input = Some(self.capture(event, channel, &line).await?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Run a command and return its replies instead of sending them
+    async fn capture(
+        &self,
+        event: &CbEvent,
+        channel: Option<&Arc<ChannelInfo>>,
+        line: &str,
+    ) -> Result<String> {
+        let output = ReplyBuffer::default();
+        let result = self.run_line(event, channel, line, Some(&output)).await;
+        let replies = output.into_inner().unwrap().join(" ");
+        match result {
+            Ok(None) => Ok(replies),
+            Ok(Some(reason)) => Err(stage_error(line, reason)),
+            Err(err) => {
+                // replies before an error explain it, e.g. missing permissions
+                if !replies.is_empty() {
+                    send_reply(event, &replies, &self.ctx.sender).await?;
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Run a command after checking whether it's active, its cooldown and permissions. Returns why
+    /// the command didn't run, if it didn't.
     async fn run_command(
         &self,
         command_handler: &dyn CommandHandler,
         cmd_ctx: CommandContext<'_>,
-    ) -> Result<()> {
+    ) -> Result<Option<SkipReason>> {
         let ctx = &self.ctx;
 
         // load channel specific command config
@@ -194,7 +300,7 @@ impl CommandRouter {
                 .unwrap_or(cmd_ctx.attributes.default_active);
 
             if !cmd_ctx.attributes.enabled || !active_in_channel {
-                return Ok(());
+                return Ok(Some("is not active in this channel"));
             }
 
             let channel_cooldown = channel_config
@@ -215,7 +321,7 @@ impl CommandRouter {
                     .await;
                 if let Err(Error::Command(CommandError::PermissionRequired(_))) = permission_check {
                     debug!("Cooldown for {} still active", cmd_ctx.command_name);
-                    return Ok(());
+                    return Ok(Some("is on cooldown"));
                 }
                 // if other errors than missing permission occurred
                 permission_check?;
@@ -241,7 +347,35 @@ impl CommandRouter {
             .await?;
 
         debug!("Running {} command handler", command_handler.name());
-        command_handler.run(&cmd_ctx).await
+        command_handler.run(&cmd_ctx).await?;
+
+        if let Some(channel) = &cmd_ctx.channel {
+            let recorded = stats::record_command_usage(
+                &ctx.db_context.db_pool,
+                channel.data.id,
+                cmd_ctx.attributes.id,
+            )
+            .await;
+            if let Err(err) = recorded {
+                warn!("Recording command usage failed: {}", err);
+            }
+
+            let user = cmd_ctx.event.user(ctx).await?;
+            let published = LiveEvent::CommandExecuted(LiveCommandEvent {
+                channel_id: channel.data.id,
+                command_id: cmd_ctx.attributes.id,
+                command_name: cmd_ctx.command_name.to_string(),
+                sender_user_id: user.map(|u| u.id),
+                sender_name: user.map(|u| u.display_name.as_ref().unwrap_or(&u.name).clone()),
+                executed_at: chrono::Local::now().into(),
+            })
+            .publish(&ctx.db_context)
+            .await;
+            if let Err(err) = published {
+                warn!("Publishing live event failed: {}", err);
+            }
+        }
+        Ok(None)
     }
 }
 
@@ -255,35 +389,67 @@ pub struct CommandContext<'a> {
     /// name of the command
     command_name: &'a str,
     attributes: &'a CommandAttributes,
+    /// all command handlers by name
+    handlers: &'a FnvHashMap<&'static str, Box<dyn CommandHandler>>,
+    /// replies are collected here instead of being sent if set, used in pipelines
+    output: Option<&'a ReplyBuffer>,
+}
+
+/// Replies of a command that are captured instead of being sent
+type ReplyBuffer = Mutex<Vec<String>>;
+
+/// Reason why a command call didn't run, e.g. "is on cooldown"
+type SkipReason = &'static str;
+
+/// Get the name of the command from a message without prefix
+fn command_name(line: &str) -> &str {
+    line.split_whitespace().next().unwrap_or_default()
+}
+
+/// Error for a pipeline command that didn't run
+fn stage_error(line: &str, reason: SkipReason) -> Error {
+    CommandError::PipelineStage(command_name(line).to_string(), reason).into()
+}
+
+/// Reply to a message. Sends a message to the channel this event originated from or a whisper reply
+/// if this event is a whisper message. Fails on all other event types. Discord messages are
+/// answered on Discord, `out` is only used for Twitch.
+async fn send_reply(event: &CbEvent, message: &str, mut out: &ChatSender) -> Result<()> {
+    if let Some(discord_message) = event.discord() {
+        return discord_message.reply(message);
+    }
+    match event.message() {
+        Some(ChatMessage {
+            channel: Some(channel),
+            ..
+        }) => {
+            out.send(ClientMessage::message(channel, message)).await?;
+        }
+        Some(ChatMessage {
+            channel: None,
+            sender,
+            ..
+        }) => {
+            out.send(ClientMessage::whisper(sender, message)).await?;
+        }
+        None => {
+            return Err(
+                CommandError::ReplyError("Can only reply to privmsg and whisper events").into(),
+            )
+        }
+    }
+    Ok(())
 }
 
 impl CommandContext<'_> {
-    /// Reply to the current message. Sends a message to the channel this event originated from or a whisper reply
-    /// if this event is a whisper message. Fails on all other event types.
-    pub async fn reply(&self, message: &str, mut out: &ChatSender) -> Result<()> {
-        match &**self.event {
-            Event::PrivMsg(data) => {
-                out.send(ClientMessage::message(data.channel().as_str(), message))
-                    .await?;
-            }
-            Event::Whisper(data) => {
-                let sender = data
-                    .sender()
-                    .as_ref()
-                    .ok_or_else::<Error, _>(|| {
-                        CommandError::ReplyError("Whisper sender is missing from message").into()
-                    })?
-                    .as_str();
-                out.send(ClientMessage::whisper(sender, message)).await?;
-            }
-            _ => {
-                return Err(CommandError::ReplyError(
-                    "Can only reply to privmsg and whisper events",
-                )
-                .into())
-            }
+    /// Reply to the current message, see `send_reply`. Replies are collected instead if the
+    /// command is part of a pipeline.
+    pub async fn reply(&self, message: &str, out: &ChatSender) -> Result<()> {
+        if let Some(output) = self.output {
+            output.lock().unwrap().push(message.to_string());
+            return Ok(());
         }
-        Ok(())
+        send_reply(self.event, message, out).await
     }
 
     /// Check whether the current user's permissions fulfill a given `PermissionRequirement`
@@ -350,29 +516,49 @@ impl CommandContext<'_> {
         let args = split_args(self.args)?;
         debug!("{:?}", args);
 
-        let result = T::clap()
-            .global_settings(&[
-                AppSettings::DisableVersion,
-                AppSettings::DisableHelpSubcommand,
-                AppSettings::ColorNever,
-            ])
-            .get_matches_from_safe(args);
+        let result = clap_app::<T>().get_matches_from_safe(args);
         match result {
             Ok(matches) => Ok(Some(T::from_clap(&matches))),
             // display help or errors if required
             Err(structopt::clap::Error { message, .. }) => {
-                let inline_help_message_rx = Lazy::new(|| Regex::new("\n\\W*").unwrap());
-
-                self.reply(
-                    &(&*inline_help_message_rx).replace_all(&message, " | "),
-                    &bot.sender,
-                )
-                .await?;
+                self.reply(&inline_help_message(&message), &bot.sender)
+                    .await?;
 
                 Ok(None)
             }
         }
     }
+
+    /// Get the usage of a command from the `usage` hook of its handler
+    pub fn command_usage(&self, attributes: &CommandAttributes) -> Option<String> {
+        self.handlers
+            .get(attributes.handler_name.as_str())
+            .and_then(|handler| handler.usage(attributes))
+    }
+}
+
+/// Usage of a structopt based command, formatted the same way as help messages shown by
+/// `CommandContext::parse_args`
+fn structopt_usage<T: StructOpt>() -> Option<String> {
+    let mut help = Vec::new();
+    clap_app::<T>().write_help(&mut help).ok()?;
+    Some(inline_help_message(&String::from_utf8_lossy(&help)))
+}
+
+fn clap_app<'a, 'b, T: StructOpt>() -> structopt::clap::App<'a, 'b> {
+    T::clap().global_settings(&[
+        AppSettings::DisableVersion,
+        AppSettings::DisableHelpSubcommand,
+        AppSettings::ColorNever,
+    ])
+}
+
+/// Put a multiline clap message on a single line for chat
+fn inline_help_message(message: &str) -> String {
+    static INLINE_HELP_MESSAGE_RX: Lazy<Regex> = Lazy::new(|| Regex::new("\n\\W*").unwrap());
+    INLINE_HELP_MESSAGE_RX
+        .replace_all(message.trim(), " | ")
+        .into_owned()
 }
 
 /// Initialize permissions required for the command router
@@ -3,7 +3,6 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use arc_swap::ArcSwapOption;
-use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
 use async_trait::async_trait;
@@ -13,7 +12,6 @@ use unogs_client::genre_ids::Genre;
 use unogs_client::{List, QuotaState, UnogsClient};
 
 use crate::config::CerebotConfig;
-use crate::error::Error;
 use crate::handlers::commands::error::CommandError;
 use crate::handlers::{CommandContext, CommandHandler};
 use crate::state::BotContext;
@@ -25,11 +23,18 @@ use rand::{thread_rng, Rng};
 #[derive(Debug)]
 pub struct NetflixCommandHandler {
     ctx: BotContext,
-    api_client: OnceCell<UnogsClient>,
+    api_client: ArcSwapOption<ApiClient>,
     genre_list: ArcSwapOption<GenreList>,
     quota: ArcSwapOption<QuotaState>,
 }
 
+/// API client with the key it was created with, replaced when the configured key changes
+#[derive(Debug)]
+struct ApiClient {
+    rapidapi_key: String,
+    client: UnogsClient,
+}
+
 const NAME: &str = "netflix";
 
 #[async_trait]
@@ -100,13 +105,23 @@ impl CommandHandler for NetflixCommandHandler {
 }
 
 impl NetflixCommandHandler {
-    fn get_api_client(&self) -> Result<&UnogsClient> {
-        let api_client = self.api_client.get_or_try_init::<_, Error>(|| {
-            let key = CerebotConfig::get()?
-                .rapidapi_key()
-                .ok_or(CommandError::RapidApiNotConfigured)?;
-            Ok(UnogsClient::new(key).map_err(CommandError::UnogsError)?)
-        })?;
+    fn get_api_client(&self) -> Result<Arc<ApiClient>> {
+        let config = CerebotConfig::get()?;
+        let key = config
+            .rapidapi_key()
+            .ok_or(CommandError::RapidApiNotConfigured)?;
+        if let Some(api_client) = &*self.api_client.load() {
+            if api_client.rapidapi_key == key {
+                return Ok(api_client.clone());
+            }
+        }
+        let api_client = Arc::new(ApiClient {
+            rapidapi_key: key.to_string(),
+            client: UnogsClient::new(key).map_err(CommandError::UnogsError)?,
+        });
+        self.api_client.store(Some(api_client.clone()));
+        // the quota belongs to the previous key
+        self.quota.store(None);
         Ok(api_client)
     }
 
@@ -119,6 +134,7 @@ impl NetflixCommandHandler {
 
         let response = self
             .get_api_client()?
+            .client
             .genre_ids()
             .await
             .map_err(CommandError::UnogsError)?;
@@ -0,0 +1,207 @@
+//! Pipeline syntax for combining commands in chat:
+//!
+//! - `quote random | say` runs `quote random`, then `say` with the reply of `quote` appended to
+//!   its arguments
+//! - `say $(netflix)` replaces `$(netflix)` with the reply of `netflix` before running `say`
+//!
+//! Quoted or escaped `|` and `$(` are left alone. Substitutions can't be nested. The syntax only
+//! applies if every command and substitution starts with a known command, `say a|b` says `a|b`.
+
+use crate::handlers::commands::command_name;
+use crate::handlers::commands::error::CommandError;
+use crate::util::is_quote;
+use crate::Result;
+
+/// Maximum number of commands run for a single message
+pub const MAX_PIPELINE_COMMANDS: usize = 5;
+
+#[derive(Debug, PartialEq)]
+pub enum Segment<'a> {
+    /// text passed on as is
+    Text(&'a str),
+    /// command call to replace with its reply
+    Substitution(&'a str),
+}
+
+/// A command of a pipeline, made of text and substitutions
+pub type Stage<'a> = Vec<Segment<'a>>;
+
+/// Split a command line (without prefix) into pipeline stages. `is_command` tells whether a name is
+/// a known command alias. Returns `None` if the line doesn't use the pipeline syntax.
+pub fn parse(line: &str, is_command: impl Fn(&str) -> bool) -> Result<Option<Vec<Stage<'_>>>> {
+    let mut stages = vec![];
+    let mut segments = vec![];
+    let mut segment_start = 0;
+    let mut quote_state: Option<char> = None;
+    let mut escape = false;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((index, current_char)) = chars.next() {
+        if escape {
+            escape = false;
+        } else if current_char == '\\' {
+            escape = true;
+        } else if let Some(quote) = quote_state {
+            if current_char == quote {
+                quote_state = None;
+            }
+        } else if is_quote(current_char) {
+            quote_state = Some(current_char);
+        } else if current_char == '|' {
+            segments.push(Segment::Text(&line[segment_start..index]));
+            stages.push(segments);
+            segments = vec![];
+            segment_start = index + 1;
+        } else if current_char == '$' {
+            if let Some((_, '(')) = chars.peek() {
+                chars.next();
+                let inner_start = index + 2;
+                let inner_end = match substitution_end(line, inner_start) {
+                    Ok(inner_end) => inner_end,
+                    Err(_) if !is_command(command_name(line[inner_start..].trim())) => {
+                        return Ok(None)
+                    }
+                    Err(err) => return Err(err),
+                };
+                segments.push(Segment::Text(&line[segment_start..index]));
+                segments.push(Segment::Substitution(line[inner_start..inner_end].trim()));
+                segment_start = inner_end + 1;
+                // continue after the closing parenthesis
+                while let Some((next_index, _)) = chars.peek() {
+                    if *next_index >= segment_start {
+                        break;
+                    }
+                    chars.next();
+                }
+            }
+        }
+    }
+    segments.push(Segment::Text(&line[segment_start..]));
+    stages.push(segments);
+
+    if stages.len() == 1 && stages[0].len() == 1 {
+        return Ok(None);
+    }
+    let stages_are_commands = stages.iter().all(|stage| match stage.first() {
+        Some(Segment::Text(text)) => is_command(command_name(text)),
+        _ => false,
+    });
+    let substitutions_are_commands = stages.iter().flatten().all(|segment| match segment {
+        Segment::Substitution(command) => is_command(command_name(command)),
+        Segment::Text(_) => true,
+    });
+    if !stages_are_commands || !substitutions_are_commands {
+        return Ok(None);
+    }
+    let substitution_count = stages
+        .iter()
+        .flatten()
+        .filter(|segment| is_substitution(segment))
+        .count();
+    if stages.len() + substitution_count > MAX_PIPELINE_COMMANDS {
+        return Err(CommandError::Pipeline("too many commands").into());
+    }
+    Ok(Some(stages))
+}
+
+fn is_substitution(segment: &Segment<'_>) -> bool {
+    if let Segment::Substitution(_) = segment {
+        true
+    } else {
+        false
+    }
+}
+
+/// Find the closing parenthesis of a substitution starting at `start`
+fn substitution_end(line: &str, start: usize) -> Result<usize> {
+    let mut quote_state: Option<char> = None;
+    let mut escape = false;
+    for (index, current_char) in line[start..].char_indices() {
+        if escape {
+            escape = false;
+        } else if current_char == '\\' {
+            escape = true;
+        } else if let Some(quote) = quote_state {
+            if current_char == quote {
+                quote_state = None;
+            }
+        } else if is_quote(current_char) {
+            quote_state = Some(current_char);
+        } else if current_char == ')' {
+            return Ok(start + index);
+        } else if current_char == '|' || line[start + index..].starts_with("$(") {
+            return Err(CommandError::Pipeline("substitutions can't contain | or $(").into());
+        }
+    }
+    Err(CommandError::Pipeline("missing ) after $(").into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::Segment::*;
+    use super::*;
+
+    fn parse(line: &str) -> Result<Option<Vec<Stage<'_>>>> {
+        let commands = ["say", "quote", "netflix", "dice"];
+        super::parse(line, |name| commands.contains(&name))
+    }
+
+    #[test]
+    fn test_no_pipeline() {
+        assert_eq!(parse("say hello").unwrap(), None);
+        assert_eq!(parse(r#"say "a | b" 'x $(y)'"#).unwrap(), None);
+        assert_eq!(parse(r#"say a \| b \$(c)"#).unwrap(), None);
+        assert_eq!(parse("say 5$ (or more)").unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_commands() {
+        assert_eq!(parse("say a|b").unwrap(), None);
+        assert_eq!(parse("say a | b c | nope").unwrap(), None);
+        assert_eq!(parse("say it costs $(5)").unwrap(), None);
+        assert_eq!(parse("say $() and $(dice)").unwrap(), None);
+        assert_eq!(parse("say :-$(").unwrap(), None);
+        assert_eq!(parse("say | ").unwrap(), None);
+        assert_eq!(parse("say $(dice) | ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_pipe() {
+        assert_eq!(
+            parse("quote random | say").unwrap().unwrap(),
+            vec![vec![Text("quote random ")], vec![Text(" say")]]
+        );
+    }
+
+    #[test]
+    fn test_substitution() {
+        assert_eq!(
+            parse("say $(netflix \"a)b\") and $( dice )!")
+                .unwrap()
+                .unwrap(),
+            vec![vec![
+                Text("say "),
+                Substitution("netflix \"a)b\""),
+                Text(" and "),
+                Substitution("dice"),
+                Text("!")
+            ]]
+        );
+        assert_eq!(
+            parse("say $(dice) | say").unwrap().unwrap(),
+            vec![
+                vec![Text("say "), Substitution("dice"), Text(" ")],
+                vec![Text(" say")]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(parse("say $(dice").is_err());
+        assert!(parse("say $(say $(dice))").is_err());
+        assert!(parse("say $(dice | say)").is_err());
+        assert!(parse("say | say | say | say | say | say").is_err());
+        assert!(parse("say $(dice) $(dice) | say $(dice) | say").is_err());
+    }
+}
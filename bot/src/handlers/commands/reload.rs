@@ -1,13 +1,35 @@
-use futures::future::join3;
+use futures::future::{join, join3};
+use structopt::StructOpt;
 
 use async_trait::async_trait;
-use persistence::commands::attributes::InsertCommandAttributes;
+use persistence::commands::alias::CommandAlias;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
 
-use crate::handlers::{CommandContext, CommandHandler};
+use crate::handlers::commands::{
+    structopt_usage, CommandContext, CommandHandler, OPTS_HELP_TEMPLATE, SUBCOMMANDS_HELP_TEMPLATE,
+};
 use crate::state::BotContext;
 use crate::util::initialize_command;
 use crate::Result;
 
+/// Reload commands, permissions and templates, everything if no scope is given
+#[derive(StructOpt, Debug)]
+#[structopt(name = "reload", template(SUBCOMMANDS_HELP_TEMPLATE))]
+struct ReloadArgs {
+    #[structopt(subcommand)]
+    scope: Option<ReloadScope>,
+}
+
+#[derive(StructOpt, Debug)]
+enum ReloadScope {
+    /// Reload the attributes, aliases and template of a single command
+    #[structopt(template(OPTS_HELP_TEMPLATE))]
+    Command { alias: String },
+    /// Reload all templates
+    #[structopt(template(OPTS_HELP_TEMPLATE))]
+    Templates,
+}
+
 #[derive(Debug)]
 pub struct ReloadCommandHandler {
     ctx: BotContext,
@@ -22,19 +44,69 @@ impl CommandHandler for ReloadCommandHandler {
     }
 
     async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
-        let (permissions, templates, commands) = join3(
-            self.ctx.reload_permissions(),
-            self.ctx.reload_templates(),
-            self.ctx.reload_commands(),
-        )
-        .await;
-        permissions?;
-        templates?;
-        commands?;
-        cmd.reply("Reload done!", &self.ctx.sender).await?;
+        let args = match cmd.parse_args::<ReloadArgs>(&self.ctx).await? {
+            Some(args) => args,
+            None => return Ok(()),
+        };
+        match args.scope {
+            None => {
+                let (permissions, templates, commands) = join3(
+                    self.ctx.reload_permissions(),
+                    self.ctx.reload_templates(),
+                    self.ctx.reload_commands(),
+                )
+                .await;
+                permissions?;
+                templates?;
+                commands?;
+                cmd.reply("Reload done!", &self.ctx.sender).await?;
+            }
+            Some(ReloadScope::Templates) => {
+                self.ctx.reload_templates().await?;
+                cmd.reply("Templates reloaded!", &self.ctx.sender).await?;
+            }
+            Some(ReloadScope::Command { alias }) => {
+                let alias = alias.trim_start_matches(
+                    cmd.channel
+                        .and_then(|channel| channel.data.command_prefix.as_deref())
+                        .unwrap_or_default(),
+                );
+                // commands deleted in the meantime are only known to the loaded store
+                let loaded_id = self
+                    .ctx
+                    .commands
+                    .load()
+                    .get_by_alias(&alias.to_lowercase())
+                    .map(|attributes| attributes.id);
+                let command_id = match loaded_id {
+                    Some(command_id) => Some(command_id),
+                    None => CommandAlias::get(&self.ctx.db_context.db_pool, alias)
+                        .await?
+                        .map(|alias| alias.command_id),
+                };
+                match command_id {
+                    Some(command_id) => {
+                        let (command, template) = join(
+                            self.ctx.reload_command(command_id),
+                            self.ctx.reload_template(command_id),
+                        )
+                        .await;
+                        command?;
+                        template?;
+                        cmd.reply(&format!("Command {} reloaded!", alias), &self.ctx.sender)
+                            .await?;
+                    }
+                    None => cmd.reply("Command not found.", &self.ctx.sender).await?,
+                }
+            }
+        }
         Ok(())
     }
 
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<ReloadArgs>()
+    }
+
     async fn create(bot: &BotContext) -> Result<Box<dyn CommandHandler>>
     where
         Self: Sized,
@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use persistence::commands::attributes::InsertCommandAttributes;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
 
 use crate::handlers::commands::{CommandContext, CommandHandler};
 use crate::state::BotContext;
@@ -41,6 +41,10 @@ impl CommandHandler for SayCommand {
         Ok(Box::new(SayCommand { ctx: ctx.clone() }) as Box<dyn CommandHandler>)
     }
 
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        Some("say <message>".to_string())
+    }
+
     async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
         if let Some(idx) = cmd.args.find(char::is_whitespace) {
             cmd.reply(cmd.args.split_at(idx).1, &self.ctx.sender)
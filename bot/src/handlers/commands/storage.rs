@@ -0,0 +1,127 @@
+use structopt::StructOpt;
+
+use async_trait::async_trait;
+use persistence::channel::Channel;
+use persistence::commands::attributes::{CommandAttributes, InsertCommandAttributes};
+use persistence::retention::ChannelStorage;
+
+use crate::handlers::commands::{
+    structopt_usage, CommandContext, CommandHandler, OPTS_HELP_TEMPLATE,
+};
+use crate::state::BotContext;
+use crate::util::initialize_command;
+use crate::Result;
+
+#[derive(Debug)]
+pub struct LogStorageCommandHandler {
+    ctx: BotContext,
+}
+
+const NAME: &str = "logstorage";
+
+/// Number of channels listed if no channel is given in a whisper
+const LIST_LIMIT: usize = 5;
+
+/// Show the disk space used by chat logs
+#[derive(StructOpt, Debug)]
+#[structopt(name = "logstorage", template(OPTS_HELP_TEMPLATE))]
+struct LogStorageArgs {
+    /// channel to show, defaults to the current channel
+    channel: Option<String>,
+}
+
+#[async_trait]
+impl CommandHandler for LogStorageCommandHandler {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
+        let args = match cmd.parse_args::<LogStorageArgs>(&self.ctx).await? {
+            Some(args) => args,
+            None => return Ok(()),
+        };
+        let pool = &self.ctx.db_context.db_pool;
+
+        let channel_id = if let Some(name) = &args.channel {
+            match Channel::get(&self.ctx.db_context, name).await? {
+                Some(channel) => Some(channel.id),
+                None => {
+                    cmd.reply("Channel not found.", &self.ctx.sender).await?;
+                    return Ok(());
+                }
+            }
+        } else {
+            cmd.channel.map(|channel| channel.data.id)
+        };
+
+        let reply = if let Some(channel_id) = channel_id {
+            ChannelStorage::get(pool, channel_id)
+                .await?
+                .map(|storage| format_storage(&storage))
+                .unwrap_or_else(|| "No chat logs stored.".to_string())
+        } else {
+            ChannelStorage::all(pool)
+                .await?
+                .iter()
+                .take(LIST_LIMIT)
+                .map(format_storage)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        };
+        cmd.reply(&reply, &self.ctx.sender).await?;
+        Ok(())
+    }
+
+    fn usage(&self, _attributes: &CommandAttributes) -> Option<String> {
+        structopt_usage::<LogStorageArgs>()
+    }
+
+    async fn create(bot: &BotContext) -> Result<Box<dyn CommandHandler>>
+    where
+        Self: Sized,
+    {
+        initialize_command(
+            &bot,
+            InsertCommandAttributes {
+                handler_name: NAME.into(),
+                description: Some("Show the disk space used by chat logs".into()),
+                enabled: true,
+                default_active: false,
+                cooldown: None,
+                whisper_enabled: true,
+            },
+            vec!["channels:read"],
+            vec!["logstorage"],
+        )
+        .await?;
+
+        Ok(Box::new(LogStorageCommandHandler { ctx: bot.clone() }) as Box<dyn CommandHandler>)
+    }
+}
+
+fn format_storage(storage: &ChannelStorage) -> String {
+    let retention = storage
+        .log_retention_days
+        .map(|days| format!("kept for {} days", days))
+        .unwrap_or_else(|| "kept forever".to_string());
+    format!(
+        "{}: {} in {} partitions (~{} events), {}",
+        storage.channel_name,
+        format_bytes(storage.total_bytes),
+        storage.partition_count,
+        storage.estimated_events,
+        retention
+    )
+}
+
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
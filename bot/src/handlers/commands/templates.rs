@@ -1,8 +1,10 @@
 use async_trait::async_trait;
+use persistence::commands::attributes::CommandAttributes;
 
 use crate::handlers::{CommandContext, CommandHandler};
 use crate::state::BotContext;
-use crate::Result;
+use crate::template_renderer::RenderError;
+use crate::{Error, Result};
 
 #[derive(Debug)]
 pub struct TemplateCommandHandler {
@@ -18,12 +20,25 @@ impl CommandHandler for TemplateCommandHandler {
     }
 
     async fn run(&self, cmd: &CommandContext<'_>) -> Result<()> {
-        let render_output = self
+        let render_result = self
             .ctx
             .templates
             .load()
-            .render(cmd.attributes.id, cmd.event, &self.ctx)
-            .await?;
+            .render(cmd.attributes.id, cmd.event, cmd.args, &self.ctx)
+            .await;
+        let render_output = match render_result {
+            Ok(output) => output,
+            Err(Error::Render(RenderError::Args(usage))) => {
+                return cmd.reply(&usage, &self.ctx.sender).await;
+            }
+            Err(Error::Render(err)) => {
+                // tell the user instead of failing silently, details are in the log
+                warn!("Template of command {} failed: {}", cmd.attributes.id, err);
+                let reply = format!("Command failed: {}", err.user_message());
+                return cmd.reply(&reply, &self.ctx.sender).await;
+            }
+            Err(err) => return Err(err),
+        };
         let trimmed_output = render_output.trim();
         if !trimmed_output.is_empty() {
             cmd.reply(trimmed_output, &self.ctx.sender).await?;
@@ -31,6 +46,13 @@ impl CommandHandler for TemplateCommandHandler {
         Ok(())
     }
 
+    fn usage(&self, attributes: &CommandAttributes) -> Option<String> {
+        let usage = self.ctx.templates.load().usage(attributes.id)?;
+        let command_store = self.ctx.commands.load();
+        let alias = command_store.aliases_of(attributes.id).first().copied()?;
+        Some(format!("{} {}", alias, usage))
+    }
+
     async fn create(bot: &BotContext) -> Result<Box<dyn CommandHandler>>
     where
         Self: Sized,
@@ -7,7 +7,8 @@ use uuid::Uuid;
 
 use async_trait::async_trait;
 use persistence::channel::Channel;
-use persistence::chat_event::{log_event, ChatEventType, NewChatEvent};
+use persistence::chat_event::{log_event, ChatEventType, NewChatEvent, UserNotice};
+use persistence::live_events::{LiveChatEvent, LiveEvent};
 
 use crate::dispatch::EventHandler;
 use crate::event::CbEvent;
@@ -33,17 +34,66 @@ impl EventHandler<CbEvent> for LoggingHandler {
     async fn run(&self, event: &CbEvent) -> Result<()> {
         let db_entry = self.event_to_db_entry(event).await?;
         if let Some(db_entry) = db_entry {
+            let live_event = self.live_event(event, &db_entry).await?;
             log_event(&self.ctx.db_context, db_entry).await?;
+            // the live feed is best-effort, the log entry is already queued
+            if let Some(live_event) = live_event {
+                if let Err(err) = live_event.publish(&self.ctx.db_context).await {
+                    warn!("Publishing live event failed: {}", err);
+                }
+            }
         }
         Ok(())
     }
 }
 
 impl LoggingHandler {
+    /// Create the live feed version of a logged event. Events not associated with a channel are
+    /// not published.
+    async fn live_event(&self, event: &CbEvent, entry: &NewChatEvent) -> Result<Option<LiveEvent>> {
+        let channel_id = match entry.channel_id {
+            Some(channel_id) => channel_id,
+            None => return Ok(None),
+        };
+        let sender_name = event
+            .user(&self.ctx)
+            .await?
+            .map(|u| u.display_name.as_ref().unwrap_or(&u.name).clone());
+        Ok(Some(LiveEvent::Chat(LiveChatEvent {
+            event_type: entry.event_type,
+            channel_id,
+            sender_user_id: entry.sender_user_id,
+            sender_name,
+            message: entry.message.clone(),
+            tags: entry.tags.clone(),
+            received_at: entry.received_at,
+        })))
+    }
+
     async fn event_to_db_entry(&self, event: &CbEvent) -> Result<Option<NewChatEvent>> {
         let ctx = &self.ctx.db_context;
         let user_id = event.user(&self.ctx).await?.map(|u| u.id);
-        let event = match &**event {
+        if let Some(message) = event.discord() {
+            let event_type = match message.channel {
+                Some(_) => ChatEventType::Privmsg,
+                None => ChatEventType::Whisper,
+            };
+            return Ok(Some(NewChatEvent {
+                event_type,
+                twitch_message_id: None,
+                message: Some(message.content.clone()),
+                channel_id: event.channel_info(&self.ctx).await?.map(|c| c.data.id),
+                sender_user_id: user_id,
+                tags: None,
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }));
+        }
+        let event = match event.twitch() {
+            Some(event) => event,
+            None => return Ok(None),
+        };
+        let event = match event {
             Event::PrivMsg(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Privmsg,
                 twitch_message_id: Uuid::from_str(data.id()?).ok(),
@@ -52,6 +102,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::Whisper(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Whisper,
@@ -61,6 +112,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::Notice(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Notice,
@@ -70,6 +122,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::UserNotice(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Usernotice,
@@ -79,6 +132,11 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: data
+                    .tags()
+                    .as_ref()
+                    .map(UserNotice::from_tags)
+                    .unwrap_or_default(),
             }),
             Event::Host(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Host,
@@ -90,6 +148,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::ClearChat(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Clearchat,
@@ -99,6 +158,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::ClearMsg(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Clearmsg,
@@ -108,6 +168,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::RoomState(data) => Some(NewChatEvent {
                 event_type: ChatEventType::Roomstate,
@@ -117,6 +178,7 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             Event::ConnectMessage(data) if data.command() == RPL_ENDOFMOTD => Some(NewChatEvent {
                 event_type: ChatEventType::Connect,
@@ -126,6 +188,68 @@ impl LoggingHandler {
                 sender_user_id: user_id,
                 tags: data.tags().clone().map(Into::into),
                 received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::Join(data) => Some(NewChatEvent {
+                event_type: ChatEventType::Join,
+                twitch_message_id: None,
+                // JOIN messages carry no tags, only the login name of the user
+                message: data.sender().clone(),
+                channel_id: Channel::get(ctx, data.channel()).await?.map(|c| c.id),
+                sender_user_id: user_id,
+                tags: None,
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::Part(data) => Some(NewChatEvent {
+                event_type: ChatEventType::Part,
+                twitch_message_id: None,
+                message: data.sender().clone(),
+                channel_id: Channel::get(ctx, data.channel()).await?.map(|c| c.id),
+                sender_user_id: user_id,
+                tags: None,
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::Mode(data) => Some(NewChatEvent {
+                event_type: ChatEventType::Mode,
+                twitch_message_id: None,
+                message: None,
+                channel_id: Channel::get(ctx, data.channel()).await?.map(|c| c.id),
+                sender_user_id: user_id,
+                tags: None,
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::UserState(data) => Some(NewChatEvent {
+                event_type: ChatEventType::Userstate,
+                twitch_message_id: None,
+                message: None,
+                channel_id: Channel::get(ctx, data.channel()).await?.map(|c| c.id),
+                sender_user_id: user_id,
+                tags: data.tags().clone().map(Into::into),
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::GlobalUserState(data) => Some(NewChatEvent {
+                event_type: ChatEventType::Globaluserstate,
+                twitch_message_id: None,
+                message: None,
+                channel_id: None,
+                sender_user_id: user_id,
+                tags: data.tags().clone().map(Into::into),
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
+            }),
+            Event::Reconnect(_) => Some(NewChatEvent {
+                event_type: ChatEventType::Reconnect,
+                twitch_message_id: None,
+                message: None,
+                channel_id: None,
+                sender_user_id: None,
+                tags: None,
+                received_at: chrono::Local::now().into(),
+                user_notice: UserNotice::default(),
             }),
             _ => None,
         };
@@ -1,7 +1,11 @@
+pub use admins::*;
+pub use alerts::*;
 pub use bot_state::*;
 pub use commands::*;
 pub use logging::*;
 
+mod admins;
+mod alerts;
 mod bot_state;
 mod commands;
 mod logging;
@@ -6,23 +6,37 @@ extern crate log;
 use std::result::Result as StdResult;
 
 use dotenv::dotenv;
+use structopt::StructOpt;
 
 use crate::cerebot::{Cerebot, RunResult};
+use crate::cli::{CliCommand, Opts, RunOpts};
+use crate::config::CerebotConfig;
 use crate::error::Error;
+use crate::transport::{
+    console, ChatTransport, ConsoleTransport, DiscordTransport, TwitchTransport,
+};
 
 mod cerebot;
+mod cli;
 mod config;
+mod control;
 mod dispatch;
 mod error;
 mod event;
 mod handlers;
+mod maintenance;
 mod state;
 mod template_renderer;
+#[cfg(test)]
+mod testing;
+mod transport;
+mod twitch_api;
 mod util;
 
 type Result<T> = StdResult<T, Error>;
 
 fn main() {
+    let opts = Opts::from_args();
     dotenv().ok();
     env_logger::init();
     let mut runtime = tokio::runtime::Builder::new()
@@ -32,8 +46,48 @@ fn main() {
         .build()
         .unwrap();
 
-    runtime.block_on(async move {
-        let mut bot = Cerebot::create().unwrap();
-        while let RunResult::Restart = bot.run().await.unwrap() {}
+    let run_opts = match opts.command {
+        None => opts.run,
+        Some(CliCommand::Run(run_opts)) => run_opts,
+        Some(command) => {
+            if let Err(err) = runtime.block_on(cli::run(command)) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+    };
+
+    runtime.block_on(run_bot(run_opts));
+}
+
+async fn run_bot(opts: RunOpts) {
+    let config = CerebotConfig::get().unwrap();
+    let transport: Box<dyn ChatTransport> = if opts.console {
+        Box::new(ConsoleTransport::new(
+            config.username().unwrap_or(console::DEFAULT_BOT_NAME),
+            &opts.user,
+            &opts.channel,
+        ))
+    } else {
+        Box::new(TwitchTransport)
+    };
+    let mut bot = Cerebot::create(transport);
+    if !opts.console && config.discord_token().is_some() {
+        bot.add_transport(Box::new(DiscordTransport));
+    }
+    #[cfg(unix)]
+    tokio::spawn(async {
+        if let Err(err) = config::reload_on_hangup().await {
+            error!("Config reload on SIGHUP is unavailable: {}", err);
+        }
     });
+    while let RunResult::Restart = bot.run().await.unwrap() {
+        if let Err(err) = CerebotConfig::reload() {
+            error!(
+                "Reloading the config failed, keeping the current config: {}",
+                err
+            );
+        }
+    }
 }
@@ -0,0 +1,196 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tokio::{task, time};
+
+use persistence::chat_event::{persist_event_queue, ChatLogCursor, PERSIST_BATCH_SIZE};
+use persistence::retention::ExpiredPartition;
+use persistence::DbContext;
+use persistence::{partitions, stats};
+
+use crate::error::Error;
+use crate::Result;
+
+/// Time between persisting queued chat events
+const PERSIST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Maximum time between retries after persisting chat events failed
+const MAX_PERSIST_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Time between refreshes of the chat statistic rollup tables
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Time between checks for missing chat event partitions
+const PARTITION_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Time between runs of the chat log retention job
+const RETENTION_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Number of events loaded per query while archiving chat logs
+const ARCHIVE_BATCH_SIZE: u32 = 5000;
+
+/// Persist queued chat events. Full batches are followed immediately by the next batch, failed
+/// batches are retried with exponential backoff. Runs until aborted.
+pub async fn persist_events(ctx: DbContext) {
+    let mut delay = PERSIST_INTERVAL;
+    loop {
+        match persist_event_queue(&ctx).await {
+            Ok(persisted) => {
+                delay = PERSIST_INTERVAL;
+                if persisted == PERSIST_BATCH_SIZE {
+                    continue;
+                }
+            }
+            Err(err) => {
+                delay = (delay * 2).min(MAX_PERSIST_BACKOFF);
+                error!(
+                    "Failed to persist chat events, retrying in {}s: {}",
+                    delay.as_secs(),
+                    err
+                );
+            }
+        }
+        time::delay_for(delay).await;
+    }
+}
+
+/// Periodically refresh the chat statistic rollups. Runs until aborted.
+pub async fn refresh_stats(ctx: DbContext) {
+    let mut interval = time::interval(STATS_REFRESH_INTERVAL);
+    loop {
+        interval.tick().await;
+        debug!("Refreshing chat statistics");
+        if let Err(err) = stats::refresh(&ctx.db_pool).await {
+            error!("Failed to refresh chat statistics: {}", err);
+        }
+    }
+}
+
+/// Periodically create the chat event partitions for the upcoming months. Runs until aborted.
+pub async fn create_partitions(ctx: DbContext) {
+    let mut interval = time::interval(PARTITION_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        debug!("Creating missing chat event partitions");
+        if let Err(err) = partitions::create_all_partitions(&ctx.db_pool).await {
+            error!("Failed to create chat event partitions: {}", err);
+        }
+    }
+}
+
+/// Periodically remove chat logs older than the retention period of their channel, exporting them
+/// to `archive_dir` first if the channel is configured to archive its logs. Runs until aborted.
+pub async fn apply_retention(ctx: DbContext, archive_dir: Option<PathBuf>) {
+    let mut interval = time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = match ExpiredPartition::find_all(&ctx.db_pool).await {
+            Ok(expired) => expired,
+            Err(err) => {
+                error!("Failed to find expired chat log partitions: {}", err);
+                continue;
+            }
+        };
+        for partition in expired {
+            if let Err(err) = expire_partition(&ctx, &partition, archive_dir.as_deref()).await {
+                error!(
+                    "Failed to remove expired chat logs from {}: {}",
+                    partition.table_name, err
+                );
+            }
+        }
+    }
+}
+
+async fn expire_partition(
+    ctx: &DbContext,
+    partition: &ExpiredPartition,
+    archive_dir: Option<&Path>,
+) -> Result<()> {
+    if partition.archive {
+        if let Some(archive_dir) = archive_dir {
+            archive_partition(ctx, partition, archive_dir).await?;
+        } else {
+            warn!(
+                "Not removing expired chat logs of {}, archiving is enabled but no archive directory is configured",
+                partition.channel_name
+            );
+            return Ok(());
+        }
+    }
+    info!(
+        "Removing chat logs of {} before {} from {}",
+        partition.channel_name, partition.cutoff, partition.table_name
+    );
+    partition.remove(&ctx.db_pool).await?;
+    Ok(())
+}
+
+/// Export the expired events of a partition to a gzip compressed JSON Lines file. The file is
+/// written under a temporary name and renamed once complete.
+async fn archive_partition(
+    ctx: &DbContext,
+    partition: &ExpiredPartition,
+    archive_dir: &Path,
+) -> Result<()> {
+    let channel_dir = archive_dir.join(partition.channel_name.trim_start_matches('#'));
+    let file_name = if partition.fully_expired {
+        format!("{}.jsonl.gz", partition.table_name)
+    } else {
+        format!(
+            "{}_until_{}.jsonl.gz",
+            partition.table_name,
+            partition.cutoff.format("%Y%m%d%H%M%S")
+        )
+    };
+    let path = channel_dir.join(&file_name);
+    let partial_path = channel_dir.join(format!("{}.partial", file_name));
+
+    let mut events = partition
+        .load_events(&ctx.db_pool, None, ARCHIVE_BATCH_SIZE)
+        .await?;
+    if events.is_empty() {
+        debug!(
+            "Not archiving {}, it has no expired events",
+            partition.table_name
+        );
+        return Ok(());
+    }
+    info!("Archiving expired chat logs to {}", path.to_string_lossy());
+
+    let mut encoder = task::block_in_place(|| {
+        fs::create_dir_all(&channel_dir)
+            .and_then(|_| File::create(&partial_path))
+            .map(|file| GzEncoder::new(BufWriter::new(file), Compression::default()))
+            .map_err(|err| Error::Io("Error creating chat log archive", err))
+    })?;
+
+    loop {
+        let after = events.last().map(ChatLogCursor::from);
+        task::block_in_place(|| {
+            events.iter().try_for_each(|event| {
+                serde_json::to_writer(&mut encoder, event)?;
+                encoder.write_all(b"\n")
+            })
+        })
+        .map_err(|err| Error::Io("Error writing chat log archive", err))?;
+        if events.len() < ARCHIVE_BATCH_SIZE as usize {
+            break;
+        }
+        events = partition
+            .load_events(&ctx.db_pool, after, ARCHIVE_BATCH_SIZE)
+            .await?;
+    }
+
+    task::block_in_place(|| {
+        encoder
+            .finish()
+            .and_then(|mut writer| writer.flush())
+            .and_then(|_| fs::rename(&partial_path, &path))
+            .map_err(|err| Error::Io("Error finishing chat log archive", err))
+    })
+}
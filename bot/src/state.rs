@@ -5,12 +5,15 @@ use std::sync::Arc;
 
 use arc_swap::ArcSwap;
 use fnv::FnvHashMap;
-use futures::future::join3;
+use futures::future::{join, join3};
 use futures::SinkExt;
 use serde::Serialize;
 use tmi_rs::{ChatSender, ClientMessage};
 
 use persistence::channel::Channel;
+use persistence::commands::alias::CommandAlias;
+use persistence::commands::attributes::CommandAttributes;
+use persistence::commands::templates::CommandTemplate;
 use persistence::DbContext;
 use util::sync::RwLock;
 
@@ -125,6 +128,29 @@ impl BotContext {
             .store(Arc::new(CommandStore::load(&self.db_context).await?));
         Ok(())
     }
+
+    /// Reload the template of a single command. Concurrent reloads are applied on top of each
+    /// other, so no update is lost.
+    pub async fn reload_template(&self, command_id: i32) -> Result<()> {
+        let template = CommandTemplate::get(&self.db_context.db_pool, command_id).await?;
+        self.templates
+            .rcu(|current| current.with_command_template(command_id, template.as_ref()));
+        Ok(())
+    }
+
+    /// Reload the attributes and aliases of a single command
+    pub async fn reload_command(&self, command_id: i32) -> Result<()> {
+        let pool = &self.db_context.db_pool;
+        let (attributes, aliases) = join(
+            CommandAttributes::get(pool, command_id),
+            CommandAlias::for_command(pool, command_id),
+        )
+        .await;
+        let (attributes, aliases) = (attributes?, aliases?);
+        self.commands
+            .rcu(|current| current.with_command(command_id, attributes.clone(), aliases.clone()));
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -6,6 +6,7 @@ use persistence::DbContext;
 
 use crate::Result;
 
+#[derive(Clone)]
 pub struct CommandStore {
     /// Map of command alias -> command_id pairs
     aliases: FnvHashMap<String, i32>,
@@ -35,4 +36,43 @@ impl CommandStore {
             .get(name)
             .and_then(|command_id| self.commands.get(command_id))
     }
+
+    /// All commands, in no particular order
+    pub fn commands(&self) -> impl Iterator<Item = &CommandAttributes> {
+        self.commands.values()
+    }
+
+    /// Sorted aliases of a command
+    pub fn aliases_of(&self, command_id: i32) -> Vec<&str> {
+        let mut aliases: Vec<&str> = self
+            .aliases
+            .iter()
+            .filter(|(_, id)| **id == command_id)
+            .map(|(alias, _)| alias.as_str())
+            .collect();
+        aliases.sort();
+        aliases
+    }
+
+    /// Copy of the store with the attributes and aliases of a single command replaced, or
+    /// removed if `attributes` is `None`
+    pub fn with_command(
+        &self,
+        command_id: i32,
+        attributes: Option<CommandAttributes>,
+        aliases: Vec<CommandAlias>,
+    ) -> CommandStore {
+        let mut store = self.clone();
+        store.aliases.retain(|_, id| *id != command_id);
+        store.commands.remove(&command_id);
+        if let Some(attributes) = attributes {
+            store.commands.insert(command_id, attributes);
+            store.aliases.extend(
+                aliases
+                    .into_iter()
+                    .map(|alias| (alias.name, alias.command_id)),
+            );
+        }
+        store
+    }
 }
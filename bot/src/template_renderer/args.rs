@@ -0,0 +1,232 @@
+//! Typed arguments of template commands. Instead of `"complete"` or `"array"`, the `args` entry of
+//! a command's `template_context` can declare a list of named arguments:
+//!
+//! ```json
+//! {"args": [
+//!     {"name": "target", "type": "user", "required": true},
+//!     {"name": "count", "type": "int", "default": 1},
+//!     {"name": "reason", "type": "rest"}
+//! ]}
+//! ```
+//!
+//! The parsed values are available as `args.target`, `args.count` etc. in the template. Missing
+//! optional arguments use their default or are null.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value as JsonValue};
+
+use crate::util::parse_quoted_arg;
+
+static USER_NAME: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9_]{1,25}$").unwrap());
+static DURATION_PART: Lazy<Regex> = Lazy::new(|| Regex::new(r"(\d+)([dhms])").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgType {
+    /// a single word, the default
+    Text,
+    /// a user name, a leading `@` is removed
+    User,
+    /// an integer
+    Int,
+    /// a duration like `1h30m`, `45s` or `90` (seconds), as number of seconds
+    Duration,
+    /// the remaining text, only allowed as last argument
+    Rest,
+}
+
+impl Default for ArgType {
+    fn default() -> Self {
+        ArgType::Text
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArgSpec {
+    pub name: String,
+    #[serde(rename = "type", default)]
+    pub arg_type: ArgType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<JsonValue>,
+}
+
+/// Read and check the argument declaration of a template context request
+pub fn parse_specs(value: &JsonValue) -> Result<Vec<ArgSpec>, String> {
+    let specs: Vec<ArgSpec> = serde_json::from_value(value.clone())
+        .map_err(|err| format!("invalid argument declaration: {}", err))?;
+    if let Some(index) = specs.iter().position(|spec| spec.arg_type == ArgType::Rest) {
+        if index != specs.len() - 1 {
+            return Err("only the last argument can be of type rest".to_string());
+        }
+    }
+    Ok(specs)
+}
+
+/// Usage description of the arguments, e.g. `<target> [count] [reason...]`
+pub fn usage(specs: &[ArgSpec]) -> String {
+    specs
+        .iter()
+        .map(|spec| {
+            let rest = if spec.arg_type == ArgType::Rest {
+                "..."
+            } else {
+                ""
+            };
+            if spec.required {
+                format!("<{}{}>", spec.name, rest)
+            } else {
+                format!("[{}{}]", spec.name, rest)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parse and validate command arguments. Returns a description of the problem if they don't
+/// match the declaration.
+pub fn parse_args(specs: &[ArgSpec], args: &str) -> Result<Map<String, JsonValue>, String> {
+    let mut values = Map::new();
+    let mut remaining = args;
+    for spec in specs {
+        let arg = if spec.arg_type == ArgType::Rest {
+            let rest = remaining.trim().to_string();
+            remaining = "";
+            rest
+        } else {
+            let (new_remaining, arg) = parse_quoted_arg(remaining)
+                .map_err(|_| "quote mismatch in the arguments".to_string())?;
+            remaining = new_remaining;
+            arg
+        };
+
+        let value = if arg.is_empty() {
+            if spec.required {
+                return Err(format!("missing argument {}", spec.name));
+            }
+            spec.default.clone().unwrap_or(JsonValue::Null)
+        } else {
+            parse_value(spec, arg)?
+        };
+        values.insert(spec.name.clone(), value);
+    }
+    if !remaining.trim().is_empty() {
+        return Err("too many arguments".to_string());
+    }
+    Ok(values)
+}
+
+fn parse_value(spec: &ArgSpec, arg: String) -> Result<JsonValue, String> {
+    match spec.arg_type {
+        ArgType::Text | ArgType::Rest => Ok(JsonValue::String(arg)),
+        ArgType::User => {
+            let name = arg.trim_start_matches('@');
+            if USER_NAME.is_match(name) {
+                Ok(JsonValue::String(name.to_string()))
+            } else {
+                Err(format!("{} must be a user name", spec.name))
+            }
+        }
+        ArgType::Int => arg
+            .parse::<i64>()
+            .map(Into::into)
+            .map_err(|_| format!("{} must be a number", spec.name)),
+        ArgType::Duration => parse_duration(&arg)
+            .map(Into::into)
+            .ok_or_else(|| format!("{} must be a duration like 1h30m", spec.name)),
+    }
+}
+
+/// Parse a duration like `1d12h`, `30m` or `90` into seconds
+fn parse_duration(input: &str) -> Option<i64> {
+    if let Ok(seconds) = input.parse::<i64>() {
+        return Some(seconds).filter(|seconds| *seconds >= 0);
+    }
+    let input = input.to_lowercase();
+    let mut matched_len = 0;
+    let mut seconds: i64 = 0;
+    for captures in DURATION_PART.captures_iter(&input) {
+        matched_len += captures[0].len();
+        let count: i64 = captures[1].parse().ok()?;
+        let unit = match &captures[2] {
+            "d" => 24 * 3600,
+            "h" => 3600,
+            "m" => 60,
+            _ => 1,
+        };
+        seconds = seconds.checked_add(count.checked_mul(unit)?)?;
+    }
+    // every character has to be part of the duration
+    if matched_len == 0 || matched_len != input.len() {
+        None
+    } else {
+        Some(seconds)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn specs() -> Vec<ArgSpec> {
+        parse_specs(&json!([
+            {"name": "target", "type": "user", "required": true},
+            {"name": "count", "type": "int", "default": 1},
+            {"name": "time", "type": "duration"},
+            {"name": "reason", "type": "rest"}
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_specs() {
+        assert!(parse_specs(&json!([{"name": "a", "type": "rest"}, {"name": "b"}])).is_err());
+        assert!(parse_specs(&json!([{"name": "a", "type": "float"}])).is_err());
+        assert!(parse_specs(&json!("complete")).is_err());
+        let specs = parse_specs(&json!([{"name": "a"}])).unwrap();
+        assert_eq!(specs[0].arg_type, ArgType::Text);
+        assert!(!specs[0].required);
+    }
+
+    #[test]
+    fn test_usage() {
+        assert_eq!(usage(&specs()), "<target> [count] [time] [reason...]");
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let specs = specs();
+        assert_eq!(
+            JsonValue::Object(parse_args(&specs, "@Cere 3 1h30m \"too\" loud").unwrap()),
+            json!({"target": "Cere", "count": 3, "time": 5400, "reason": "\"too\" loud"})
+        );
+        assert_eq!(
+            JsonValue::Object(parse_args(&specs, "cere").unwrap()),
+            json!({"target": "cere", "count": 1, "time": null, "reason": null})
+        );
+        assert!(parse_args(&specs, "").is_err());
+        assert!(parse_args(&specs, "\"cere").is_err());
+        assert!(parse_args(&specs, "cere many").is_err());
+        assert!(parse_args(&specs, "cere 1 soon").is_err());
+        assert!(parse_args(&specs, "not/a/user").is_err());
+
+        let specs = parse_specs(&json!([{"name": "a"}])).unwrap();
+        assert!(parse_args(&specs, "one two").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("1d2h3m4s"), Some(93784));
+        assert_eq!(parse_duration("15M"), Some(900));
+        assert_eq!(parse_duration("1h 30m"), None);
+        assert_eq!(parse_duration("-5"), None);
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+}
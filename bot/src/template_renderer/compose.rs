@@ -0,0 +1,241 @@
+//! Template functions that reach outside of the current template. `command()` renders another
+//! template command with the current context, `http_get_json()` fetches JSON from the hosts
+//! allowed in the bot configuration.
+//!
+//! Templates are rendered synchronously on a blocking thread, so the commands a template may call
+//! are resolved before rendering and made available to `command()` for the duration of the render
+//! through a thread local scope.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::Arc;
+use std::time::Duration;
+
+use fnv::FnvHashMap;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use reqwest::blocking::Client;
+use reqwest::{redirect, Url};
+use tera::{Error, Tera, Value};
+
+use super::functions::{arg, opt_arg};
+use super::sandbox;
+
+/// Maximum nesting depth of `command()` calls
+pub const MAX_COMMAND_DEPTH: usize = 3;
+/// Timeout of `http_get_json()` requests, they also count towards the render timeout
+const HTTP_TIMEOUT: Duration = Duration::from_millis(300);
+/// Maximum size of `http_get_json()` response bodies
+const MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+static COMMAND_CALL: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"\bcommand\(\s*name\s*=\s*"([^"]+)""#).unwrap());
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        // redirects could lead to hosts that are not allowed
+        .redirect(redirect::Policy::none())
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+thread_local! {
+    static SCOPE: RefCell<Option<RenderScope>> = RefCell::new(None);
+}
+
+type Args = HashMap<String, Value>;
+
+/// State of the render running on the current thread
+struct RenderScope {
+    tera: Arc<Tera>,
+    context: tera::Context,
+    /// command alias -> command id of the template commands that can be called
+    commands: FnvHashMap<String, i32>,
+    depth: usize,
+}
+
+/// Removes the scope when rendering is done, blocking threads are reused
+struct ScopeGuard;
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        SCOPE.with(|scope| scope.borrow_mut().take());
+    }
+}
+
+/// Register the functions with a Tera instance. `allowed_hosts` are the hosts `http_get_json()`
+/// may send requests to.
+pub fn register(tera: &mut Tera, allowed_hosts: Vec<String>) {
+    tera.register_function("command", command);
+    tera.register_function("http_get_json", move |args: &Args| {
+        http_get_json(&allowed_hosts, args)
+    });
+}
+
+/// Aliases of the commands a template calls with a literal name, e.g. `command(name="socials")`.
+/// Only these can be resolved before rendering.
+pub fn command_calls(source: &str) -> Vec<String> {
+    COMMAND_CALL
+        .captures_iter(source)
+        .map(|captures| captures[1].to_lowercase())
+        .collect()
+}
+
+/// Render a template with `commands` available to `command()`
+pub fn render_scoped(
+    tera: Arc<Tera>,
+    name: &str,
+    context: &tera::Context,
+    commands: FnvHashMap<String, i32>,
+) -> tera::Result<String> {
+    SCOPE.with(|scope| {
+        scope.borrow_mut().replace(RenderScope {
+            tera: tera.clone(),
+            context: context.clone(),
+            commands,
+            depth: 0,
+        })
+    });
+    let _guard = ScopeGuard;
+    tera.render(name, context)
+}
+
+/// `command(name="socials", args="...")`: the output of another template command, rendered with
+/// the current context. `args` replaces the arguments of the current command if given.
+fn command(args: &Args) -> tera::Result<Value> {
+    let name: String = arg(args, "command", "name")?;
+    let command_args: Option<Value> = opt_arg(args, "command", "args")?;
+    sandbox::charge(0, 0)?;
+    let (tera, mut context, command_id) = SCOPE.with(|scope| {
+        let mut scope = scope.borrow_mut();
+        let scope = scope
+            .as_mut()
+            .ok_or_else(|| Error::msg("`command` is not available here"))?;
+        let command_id = *scope.commands.get(&name.to_lowercase()).ok_or_else(|| {
+            Error::msg(format!("`command` received an unknown command: {}", name))
+        })?;
+        if scope.depth >= MAX_COMMAND_DEPTH {
+            return Err(Error::msg(format!(
+                "`command` calls are limited to a depth of {}",
+                MAX_COMMAND_DEPTH
+            )));
+        }
+        scope.depth += 1;
+        Ok((scope.tera.clone(), scope.context.clone(), command_id))
+    })?;
+    if let Some(command_args) = command_args {
+        context.insert("args", &command_args);
+    }
+    let result = tera.render(&format!("{}", command_id), &context);
+    SCOPE.with(|scope| {
+        if let Some(scope) = scope.borrow_mut().as_mut() {
+            scope.depth -= 1;
+        }
+    });
+    let output = result?;
+    sandbox::charge(0, output.len())?;
+    Ok(Value::String(output))
+}
+
+/// `http_get_json(url="https://...")`: fetch and parse a JSON document from an allowed host
+fn http_get_json(allowed_hosts: &[String], args: &Args) -> tera::Result<Value> {
+    let url: String = arg(args, "http_get_json", "url")?;
+    let url = Url::parse(&url)
+        .map_err(|_| Error::msg(format!("`http_get_json` received an invalid url: {}", url)))?;
+    let host = url.host_str().unwrap_or_default();
+    let allowed = (url.scheme() == "https" || url.scheme() == "http")
+        && allowed_hosts
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(host));
+    if !allowed {
+        return Err(Error::msg(format!(
+            "`http_get_json` is not allowed to access {}",
+            host
+        )));
+    }
+
+    sandbox::charge(0, 0)?;
+    let response = HTTP_CLIENT
+        .get(url)
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| Error::msg(format!("`http_get_json` request failed: {}", err)))?;
+    let mut body = Vec::new();
+    response
+        .take(MAX_RESPONSE_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|err| Error::msg(format!("`http_get_json` request failed: {}", err)))?;
+    if body.len() as u64 > MAX_RESPONSE_BYTES {
+        return Err(Error::msg(format!(
+            "`http_get_json` responses are limited to {} bytes",
+            MAX_RESPONSE_BYTES
+        )));
+    }
+    sandbox::charge(0, body.len())?;
+    serde_json::from_slice(&body).map_err(|_| Error::msg("`http_get_json` received invalid JSON"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn tera() -> Arc<Tera> {
+        let mut tera = Tera::default();
+        register(&mut tera, vec!["api.example.com".to_string()]);
+        tera.add_raw_templates(vec![
+            ("1", r#"Follow me on {{ command(name="Socials") }}"#),
+            ("2", "twitter, {{ args }}"),
+            ("3", r#"{{ command(name="socials", args="twitch") }}"#),
+            ("4", r#"again {{ command(name="loop") }}"#),
+        ])
+        .unwrap();
+        Arc::new(tera)
+    }
+
+    fn commands() -> FnvHashMap<String, i32> {
+        vec![("socials".to_string(), 2), ("loop".to_string(), 4)]
+            .into_iter()
+            .collect()
+    }
+
+    #[test]
+    fn test_command_calls() {
+        assert_eq!(
+            command_calls(
+                r#"{{ command(name="Socials") }} {{ command( name = "dice", args="2") }}"#
+            ),
+            vec!["socials", "dice"]
+        );
+        assert!(command_calls(r#"{{ command(name=alias) }}"#).is_empty());
+    }
+
+    #[test]
+    fn test_command() {
+        let tera = tera();
+        let mut context = tera::Context::new();
+        context.insert("args", "youtube");
+
+        let output = render_scoped(tera.clone(), "1", &context, commands()).unwrap();
+        assert_eq!(output, "Follow me on twitter, youtube");
+        let output = render_scoped(tera.clone(), "3", &context, commands()).unwrap();
+        assert_eq!(output, "twitter, twitch");
+        assert!(render_scoped(tera.clone(), "4", &context, commands()).is_err());
+        assert!(render_scoped(tera.clone(), "1", &context, Default::default()).is_err());
+        // the scope is gone after rendering
+        assert!(tera.render("1", &context).is_err());
+    }
+
+    #[test]
+    fn test_http_get_json_allowed_hosts() {
+        let allowed = vec!["api.example.com".to_string()];
+        let args = |url: &str| serde_json::from_value(json!({ "url": url })).unwrap();
+        assert!(http_get_json(&allowed, &args("https://example.com/data.json")).is_err());
+        assert!(http_get_json(&allowed, &args("ftp://api.example.com/data.json")).is_err());
+        assert!(http_get_json(&allowed, &args("not a url")).is_err());
+        assert!(http_get_json(&[], &args("https://api.example.com/data.json")).is_err());
+    }
+}
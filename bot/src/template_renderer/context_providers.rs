@@ -1,20 +1,28 @@
 use serde_json::{to_value, Value as JsonValue};
 
 use async_trait::async_trait;
+use persistence::stats::user_chat_stats;
+use persistence::user::User;
 
 use crate::event::CbEvent;
 use crate::state::BotContext;
 use crate::util::split_args;
 use crate::Result;
 
+use super::args;
+use super::RenderError;
+
 /// Trait for command context data providers. Implementing this trait is the main way to make
 /// additional data available to command templates.
 #[async_trait]
 pub trait ContextProvider: Send + Sync {
+    /// `command` is the command call without prefix, e.g. `dice 3`. In a pipeline it differs from
+    /// the message of the event.
     async fn run(
         &self,
         request: &JsonValue,
         event: &CbEvent,
+        command: &str,
         bot: &BotContext,
     ) -> Result<Option<(String, JsonValue)>>;
 }
@@ -28,6 +36,7 @@ impl ContextProvider for UserProvider {
         &self,
         request: &JsonValue,
         event: &CbEvent,
+        _command: &str,
         bot: &BotContext,
     ) -> Result<Option<(String, JsonValue)>> {
         if let JsonValue::Bool(true) = request["sender"] {
@@ -50,6 +59,7 @@ impl ContextProvider for ChannelInfoProvider {
         &self,
         request: &JsonValue,
         event: &CbEvent,
+        _command: &str,
         bot: &BotContext,
     ) -> Result<Option<(String, JsonValue)>> {
         if let JsonValue::Bool(true) = request["channel"] {
@@ -63,7 +73,9 @@ impl ContextProvider for ChannelInfoProvider {
     }
 }
 
-/// Provides command arguments given by the user
+/// Provides command arguments given by the user. `"complete"` provides the argument string,
+/// `"array"` the list of arguments and a list of argument declarations the parsed arguments, see
+/// `args`. Declared arguments are only parsed for chat messages.
 pub struct ArgsProvider;
 
 #[async_trait]
@@ -72,37 +84,126 @@ impl ContextProvider for ArgsProvider {
         &self,
         request: &JsonValue,
         event: &CbEvent,
+        command: &str,
         bot: &BotContext,
     ) -> Result<Option<(String, JsonValue)>> {
-        if let JsonValue::String(s) = &request["args"] {
-            let message = event.message();
-            let channel_info = event.channel_info(bot).await?;
-            let prefix = channel_info
-                .as_deref()
-                .and_then(|channel_info| channel_info.data.command_prefix.as_ref());
-            let args_str = message.map(|msg| {
-                let msg_without_prefix = if let Some(prefix) = prefix {
-                    msg.split_at(prefix.len()).1
-                } else {
-                    msg
+        let request = &request["args"];
+        if request.is_null() {
+            return Ok(None);
+        }
+        let args_str = command_args(command);
+        let value = match request {
+            JsonValue::String(s) if s == "complete" => to_value(args_str).unwrap(),
+            JsonValue::String(s) if s == "array" => match split_args(args_str) {
+                Ok(args) => to_value(args).unwrap(),
+                Err(err) => return Err(RenderError::Args(err.to_string()).into()),
+            },
+            // only chat messages call commands with arguments, other events (e.g. alerts) would
+            // fail the required arguments
+            JsonValue::Array(_) if event.message().is_some() => {
+                let specs = args::parse_specs(request).map_err(RenderError::Invalid)?;
+                let values = match args::parse_args(&specs, args_str) {
+                    Ok(values) => values,
+                    Err(err) => {
+                        let channel_info = event.channel_info(bot).await?;
+                        let prefix = channel_info
+                            .as_deref()
+                            .and_then(|channel_info| channel_info.data.command_prefix.as_deref());
+                        return Err(RenderError::Args(format!(
+                            "{} - usage: {}{} {}",
+                            err,
+                            prefix.unwrap_or_default(),
+                            command_name(command),
+                            args::usage(&specs)
+                        ))
+                        .into());
+                    }
                 };
-                // remove the command itself
-                if let Some(index) = msg_without_prefix.find(char::is_whitespace) {
-                    msg_without_prefix.split_at(index).1.trim()
-                } else {
-                    ""
-                }
-            });
-            if s == "complete" {
-                Ok(Some(("args".to_string(), to_value(args_str).unwrap())))
-            } else if s == "array" {
-                let value = to_value(args_str.map(|args| split_args(args).unwrap())).unwrap(); // get rid of unwrap
-                Ok(Some(("args".to_string(), value)))
-            } else {
-                Ok(None)
+                JsonValue::Object(values)
             }
+            _ => return Ok(None),
+        };
+        Ok(Some(("args".to_string(), value)))
+    }
+}
+
+/// Provides chat statistics of the user calling the command in the current channel
+pub struct SenderStatsProvider;
+
+#[async_trait]
+impl ContextProvider for SenderStatsProvider {
+    async fn run(
+        &self,
+        request: &JsonValue,
+        event: &CbEvent,
+        _command: &str,
+        bot: &BotContext,
+    ) -> Result<Option<(String, JsonValue)>> {
+        if let JsonValue::Bool(true) = request["sender_stats"] {
+            let channel_info = event.channel_info(bot).await?;
+            let user = event.user(bot).await?;
+            let stats = match (channel_info, user) {
+                (Some(channel_info), Some(user)) => {
+                    user_chat_stats(&bot.db_context.db_pool, channel_info.data.id, user.id).await?
+                }
+                _ => None,
+            };
+            Ok(Some(("sender_stats".to_string(), to_value(stats).unwrap())))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Provides chat statistics of the user named in the first command argument, for example
+/// `!lastseen <user>`. The value is null if no user is given or the user is unknown.
+pub struct TargetStatsProvider;
+
+#[async_trait]
+impl ContextProvider for TargetStatsProvider {
+    async fn run(
+        &self,
+        request: &JsonValue,
+        event: &CbEvent,
+        command: &str,
+        bot: &BotContext,
+    ) -> Result<Option<(String, JsonValue)>> {
+        if let JsonValue::Bool(true) = request["target_stats"] {
+            let channel_info = event.channel_info(bot).await?;
+            let target_name = command_args(command)
+                .split_whitespace()
+                .next()
+                .map(|name| name.trim_start_matches('@'));
+            let stats = match (channel_info.as_deref(), target_name) {
+                (Some(channel_info), Some(name)) if !name.is_empty() => {
+                    match User::get_by_name(&bot.db_context.db_pool, name).await {
+                        Ok(user) => {
+                            user_chat_stats(&bot.db_context.db_pool, channel_info.data.id, user.id)
+                                .await?
+                        }
+                        Err(persistence::Error::NotFound) => None,
+                        Err(err) => return Err(err.into()),
+                    }
+                }
+                _ => None,
+            };
+            Ok(Some(("target_stats".to_string(), to_value(stats).unwrap())))
         } else {
             Ok(None)
         }
     }
 }
+
+/// Get the name of a command call, e.g. `dice` for `dice 3`
+fn command_name(command: &str) -> &str {
+    command.split_whitespace().next().unwrap_or_default()
+}
+
+/// Get the arguments of a command call, e.g. `3` for `dice 3`
+fn command_args(command: &str) -> &str {
+    if let Some(index) = command.find(char::is_whitespace) {
+        command.split_at(index).1.trim()
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,325 @@
+//! Bot specific Tera functions and filters available in all command templates
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use rand::distributions::Uniform;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use tera::{from_value, to_value, Error, Tera, Value};
+
+use super::sandbox::{budgeted_filter, budgeted_function};
+
+/// Start of the bot process, used by `uptime()`
+static STARTED_AT: Lazy<DateTime<Utc>> = Lazy::new(Utc::now);
+
+type Args = HashMap<String, Value>;
+
+/// Register all functions and filters with a Tera instance. Calls are charged to the render
+/// budget, see `sandbox`.
+pub fn register(tera: &mut Tera) {
+    Lazy::force(&STARTED_AT);
+
+    tera.register_function("random_choice", budgeted_function(random_choice));
+    tera.register_function("random_int", budgeted_function(random_int));
+    tera.register_function("now", budgeted_function(now));
+    tera.register_function("duration_since", budgeted_function(duration_since));
+    tera.register_function("uptime", budgeted_function(uptime));
+    tera.register_filter("pluralize", budgeted_filter(pluralize));
+    tera.register_filter("user_mention", budgeted_filter(user_mention));
+}
+
+/// Get a required argument of a function or filter
+pub(super) fn arg<T: serde::de::DeserializeOwned>(
+    args: &Args,
+    fn_name: &str,
+    name: &str,
+) -> tera::Result<T> {
+    let value = args
+        .get(name)
+        .ok_or_else(|| Error::msg(format!("`{}` requires the `{}` argument", fn_name, name)))?;
+    from_value(value.clone()).map_err(|_| {
+        Error::msg(format!(
+            "`{}` received an invalid `{}` argument: {}",
+            fn_name, name, value
+        ))
+    })
+}
+
+/// Get an optional argument of a function or filter
+pub(super) fn opt_arg<T: serde::de::DeserializeOwned>(
+    args: &Args,
+    fn_name: &str,
+    name: &str,
+) -> tera::Result<Option<T>> {
+    if args.contains_key(name) {
+        arg(args, fn_name, name).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// `random_choice(list=["yes", "no"])`: a random element of a list
+fn random_choice(args: &Args) -> tera::Result<Value> {
+    let list: Vec<Value> = arg(args, "random_choice", "list")?;
+    list.choose(&mut rand::thread_rng())
+        .cloned()
+        .ok_or_else(|| Error::msg("`random_choice` requires a non-empty list"))
+}
+
+/// `random_int(min=1, max=6)`: a random integer between min and max (inclusive)
+fn random_int(args: &Args) -> tera::Result<Value> {
+    let min: i64 = arg(args, "random_int", "min")?;
+    let max: i64 = arg(args, "random_int", "max")?;
+    if min > max {
+        return Err(Error::msg("`random_int` requires min <= max"));
+    }
+    Ok(to_value(
+        rand::thread_rng().sample(Uniform::new_inclusive(min, max)),
+    )?)
+}
+
+/// `now(tz="Europe/Berlin")`: the current time as RFC 3339 string in a time zone, UTC by default.
+/// Use the `date` filter to format it.
+fn now(args: &Args) -> tera::Result<Value> {
+    let now = Utc::now();
+    match opt_arg::<String>(args, "now", "tz")? {
+        Some(tz) => {
+            let tz: Tz = tz
+                .parse()
+                .map_err(|_| Error::msg(format!("`now` received an unknown time zone: {}", tz)))?;
+            Ok(to_value(now.with_timezone(&tz).to_rfc3339())?)
+        }
+        None => Ok(to_value(now.to_rfc3339())?),
+    }
+}
+
+/// `duration_since(date=sender_stats.first_seen)`: readable time passed since a date, e.g.
+/// "1 year, 2 months". Accepts RFC 3339 strings, dates (YYYY-MM-DD) and unix timestamps.
+fn duration_since(args: &Args) -> tera::Result<Value> {
+    let date = match args.get("date") {
+        Some(Value::Number(timestamp)) => timestamp
+            .as_i64()
+            .and_then(|timestamp| Utc.timestamp_opt(timestamp, 0).single()),
+        Some(Value::String(date)) => DateTime::parse_from_rfc3339(date)
+            .map(|date| date.with_timezone(&Utc))
+            .ok()
+            .or_else(|| {
+                NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                    .ok()
+                    .map(|date| Utc.from_utc_date(&date).and_hms(0, 0, 0))
+            }),
+        Some(_) => None,
+        None => return Err(Error::msg("`duration_since` requires the `date` argument")),
+    }
+    .ok_or_else(|| Error::msg("`duration_since` received an invalid date"))?;
+    Ok(to_value(format_duration(Utc::now() - date))?)
+}
+
+/// `uptime()`: readable time since the bot was started
+fn uptime(_args: &Args) -> tera::Result<Value> {
+    Ok(to_value(format_duration(Utc::now() - *STARTED_AT))?)
+}
+
+/// `{{ count }} {{ count | pluralize(singular="person", plural="people") }}`: the singular or plural
+/// word for a number. Defaults to an empty singular and "s" as plural suffix.
+fn pluralize(value: &Value, args: &Args) -> tera::Result<Value> {
+    let count = value
+        .as_f64()
+        .ok_or_else(|| Error::msg("`pluralize` can only be applied to numbers"))?;
+    let singular = opt_arg(args, "pluralize", "singular")?.unwrap_or_else(String::new);
+    let plural = opt_arg(args, "pluralize", "plural")?.unwrap_or_else(|| "s".to_string());
+    if (count.abs() - 1.0).abs() < std::f64::EPSILON {
+        Ok(to_value(singular)?)
+    } else {
+        Ok(to_value(plural)?)
+    }
+}
+
+/// `{{ name | user_mention }}`: mention a user by name, "@name"
+fn user_mention(value: &Value, _args: &Args) -> tera::Result<Value> {
+    let name = value
+        .as_str()
+        .ok_or_else(|| Error::msg("`user_mention` can only be applied to strings"))?;
+    Ok(to_value(format!(
+        "@{}",
+        name.trim().trim_start_matches('@')
+    ))?)
+}
+
+/// Format a duration with its two largest units, e.g. "3 days, 4 hours"
+fn format_duration(duration: Duration) -> String {
+    const UNITS: [(&str, i64); 6] = [
+        ("year", 365 * 24 * 3600),
+        ("month", 30 * 24 * 3600),
+        ("day", 24 * 3600),
+        ("hour", 3600),
+        ("minute", 60),
+        ("second", 1),
+    ];
+    let mut remaining = duration.num_seconds().max(0);
+    let parts: Vec<String> = UNITS
+        .iter()
+        .filter_map(|(unit, seconds)| {
+            let count = remaining / seconds;
+            remaining %= seconds;
+            match count {
+                0 => None,
+                1 => Some(format!("1 {}", unit)),
+                count => Some(format!("{} {}s", count, unit)),
+            }
+        })
+        .take(2)
+        .collect();
+    if parts.is_empty() {
+        "0 seconds".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    fn args(value: Value) -> Args {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_random_choice() {
+        let list = json!(["a", "b", "c"]);
+        for _ in 0..20 {
+            let choice = random_choice(&args(json!({ "list": list }))).unwrap();
+            assert!(list.as_array().unwrap().contains(&choice));
+        }
+        assert!(random_choice(&args(json!({ "list": [] }))).is_err());
+        assert!(random_choice(&args(json!({}))).is_err());
+    }
+
+    #[test]
+    fn test_random_int() {
+        for _ in 0..20 {
+            let value = random_int(&args(json!({ "min": 1, "max": 6 }))).unwrap();
+            let value = value.as_i64().unwrap();
+            assert!((1..=6).contains(&value));
+        }
+        assert_eq!(
+            random_int(&args(json!({ "min": 3, "max": 3 }))).unwrap(),
+            json!(3)
+        );
+        assert_eq!(
+            random_int(&args(json!({ "min": i64::MAX, "max": i64::MAX }))).unwrap(),
+            json!(i64::MAX)
+        );
+        assert!(random_int(&args(json!({ "min": i64::MIN, "max": i64::MAX }))).is_ok());
+        assert!(random_int(&args(json!({ "min": 6, "max": 1 }))).is_err());
+        assert!(random_int(&args(json!({ "min": "a", "max": 1 }))).is_err());
+    }
+
+    #[test]
+    fn test_now() {
+        let utc = now(&args(json!({}))).unwrap();
+        let utc = DateTime::parse_from_rfc3339(utc.as_str().unwrap()).unwrap();
+        assert_eq!(utc.offset().local_minus_utc(), 0);
+        assert!((Utc::now() - utc.with_timezone(&Utc)).num_seconds().abs() < 5);
+
+        let tokyo = now(&args(json!({ "tz": "Asia/Tokyo" }))).unwrap();
+        let tokyo = DateTime::parse_from_rfc3339(tokyo.as_str().unwrap()).unwrap();
+        assert_eq!(tokyo.offset().local_minus_utc(), 9 * 3600);
+
+        assert!(now(&args(json!({ "tz": "Nowhere/Invalid" }))).is_err());
+    }
+
+    #[test]
+    fn test_duration_since() {
+        let date = (Utc::now() - Duration::days(3) - Duration::hours(4)).to_rfc3339();
+        assert_eq!(
+            duration_since(&args(json!({ "date": date }))).unwrap(),
+            json!("3 days, 4 hours")
+        );
+        let timestamp = (Utc::now() - Duration::minutes(90)).timestamp();
+        assert_eq!(
+            duration_since(&args(json!({ "date": timestamp }))).unwrap(),
+            json!("1 hour, 30 minutes")
+        );
+        let day = (Utc::now() - Duration::days(400))
+            .format("%Y-%m-%d")
+            .to_string();
+        assert!(duration_since(&args(json!({ "date": day })))
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .starts_with("1 year, 1 month"));
+        assert!(duration_since(&args(json!({ "date": "yesterday" }))).is_err());
+        // out of range timestamps are errors instead of panics
+        assert!(duration_since(&args(json!({ "date": 99_999_999_999_999_999i64 }))).is_err());
+        assert!(duration_since(&args(json!({}))).is_err());
+    }
+
+    #[test]
+    fn test_uptime() {
+        Lazy::force(&STARTED_AT);
+        let value = uptime(&args(json!({}))).unwrap();
+        assert!(value.as_str().unwrap().ends_with("seconds") || value == json!("1 second"));
+    }
+
+    #[test]
+    fn test_pluralize() {
+        assert_eq!(pluralize(&json!(1), &args(json!({}))).unwrap(), json!(""));
+        assert_eq!(pluralize(&json!(2), &args(json!({}))).unwrap(), json!("s"));
+        assert_eq!(pluralize(&json!(0), &args(json!({}))).unwrap(), json!("s"));
+        let words = args(json!({ "singular": "person", "plural": "people" }));
+        assert_eq!(pluralize(&json!(-1), &words).unwrap(), json!("person"));
+        assert_eq!(pluralize(&json!(5), &words).unwrap(), json!("people"));
+        assert!(pluralize(&json!("many"), &words).is_err());
+    }
+
+    #[test]
+    fn test_user_mention() {
+        let no_args = args(json!({}));
+        assert_eq!(
+            user_mention(&json!("cere"), &no_args).unwrap(),
+            json!("@cere")
+        );
+        assert_eq!(
+            user_mention(&json!("@cere"), &no_args).unwrap(),
+            json!("@cere")
+        );
+        assert!(user_mention(&json!(42), &no_args).is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::seconds(0)), "0 seconds");
+        assert_eq!(format_duration(Duration::seconds(-5)), "0 seconds");
+        assert_eq!(format_duration(Duration::seconds(61)), "1 minute, 1 second");
+        assert_eq!(format_duration(Duration::hours(25)), "1 day, 1 hour");
+        assert_eq!(
+            format_duration(Duration::days(365) + Duration::hours(5)),
+            "1 year, 5 hours"
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let mut tera = Tera::default();
+        register(&mut tera);
+        let mut context = tera::Context::new();
+        context.insert("count", &3);
+        context.insert("name", "cere");
+        tera.add_raw_template(
+            "dice",
+            "{{ name | user_mention }} rolled {{ random_int(min=4, max=4) }} with \
+             {{ count }} dice{{ count | pluralize(singular=\"\", plural=\"\") }}, \
+             {{ random_choice(list=[\"yes\"]) }}",
+        )
+        .unwrap();
+        let output = tera.render("dice", &context).unwrap();
+        assert_eq!(output, "@cere rolled 4 with 3 dice, yes");
+    }
+}
@@ -1,36 +1,58 @@
 use std::sync::Arc;
 
-use fnv::FnvHashMap;
+use fnv::{FnvHashMap, FnvHashSet};
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use serde_json::Value as JsonValue;
 use tera::Tera;
 
+use persistence::alerts::ChannelAlert;
+use persistence::commands::channel_config::ChannelCommandConfig;
 use persistence::commands::templates::CommandTemplate;
+use persistence::permissions::UserPermission;
 use persistence::DbContext;
 
+use crate::config::CerebotConfig;
 use crate::event::CbEvent;
 use crate::state::BotContext;
 use crate::Result;
 
 use self::context_providers::*;
+pub use self::sandbox::RenderError;
 
+mod args;
+mod compose;
 mod context_providers;
+mod functions;
+mod sandbox;
 
+#[derive(Clone)]
 pub struct TemplateRenderer {
-    tera: Tera,
+    tera: Arc<Tera>,
     context_requests: FnvHashMap<i32, JsonValue>,
+    /// template name -> aliases of the commands called with `command()`
+    command_calls: FnvHashMap<String, Vec<String>>,
+    /// templates that failed to load with the reason
+    invalid_templates: FnvHashMap<String, String>,
     context_providers: Vec<Arc<dyn ContextProvider>>,
 }
 
 impl TemplateRenderer {
     /// Create a new renderer instance and load the templates
     pub async fn create(db_context: &DbContext) -> Result<Self> {
-        let tera = Tera::default();
+        let mut tera = Tera::default();
+        functions::register(&mut tera);
+        compose::register(
+            &mut tera,
+            CerebotConfig::get()?.template_http_hosts().to_vec(),
+        );
+        sandbox::register(&mut tera);
 
         let mut instance = TemplateRenderer {
-            tera,
+            tera: Arc::new(tera),
             context_requests: Default::default(),
+            command_calls: Default::default(),
+            invalid_templates: Default::default(),
             context_providers: vec![],
         };
         instance.load_templates(db_context).await?;
@@ -38,27 +60,147 @@ impl TemplateRenderer {
         instance.register_context_provider(UserProvider);
         instance.register_context_provider(ChannelInfoProvider);
         instance.register_context_provider(ArgsProvider);
+        instance.register_context_provider(SenderStatsProvider);
+        instance.register_context_provider(TargetStatsProvider);
 
         Ok(instance)
     }
 
-    /// Render a template.
+    /// Render a template. Rendering is limited in time and output length, see `sandbox`.
+    ///
+    /// `command` is the command call without prefix, see `ContextProvider`. Context data requested
+    /// by the commands called with `command()` is included in the context. The sender can only
+    /// call commands they are allowed to use.
     pub async fn render(
         &self,
         command_id: i32,
         event: &CbEvent,
+        command: &str,
         bot: &BotContext,
     ) -> Result<String> {
-        let context_request = self.context_requests.get(&command_id);
+        let name = format!("{}", command_id);
+        let channel_id = event
+            .channel_info(bot)
+            .await?
+            .map(|channel| channel.data.id);
+        let user_permission_ids = match event.user(bot).await? {
+            Some(user) => UserPermission::get_by_user_id(&bot.db_context, user.id).await?,
+            None => vec![],
+        };
+        let commands = self
+            .resolve_command_calls(&name, channel_id, &user_permission_ids, bot)
+            .await?;
+
+        let mut context_request = serde_json::Map::new();
+        for id in std::iter::once(&command_id).chain(commands.values()) {
+            if let Some(JsonValue::Object(request)) = self.context_requests.get(id) {
+                for (key, value) in request {
+                    context_request
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+        }
         let mut context = tera::Context::new();
-        if let Some(context_request) = context_request {
-            self.build_context(&mut context, context_request, event, bot)
+        if !context_request.is_empty() {
+            let context_request = JsonValue::Object(context_request);
+            self.build_context(&mut context, &context_request, event, command, bot)
                 .await?;
         }
         debug!("Built template context: {:?}", context);
-        self.tera
-            .render(&format!("{}", command_id), &context)
-            .map_err(Into::into)
+        self.render_sandboxed(name, context, commands).await
+    }
+
+    /// Render the template of a chat alert in a channel. Alerts are not tied to a command call, so
+    /// the context is built by the caller from the event. Commands called with `command()` are
+    /// rendered with the same context, only commands without permission requirements can be called.
+    pub async fn render_alert(
+        &self,
+        alert_id: i32,
+        channel_id: i32,
+        context: tera::Context,
+        bot: &BotContext,
+    ) -> Result<String> {
+        let name = alert_template_name(alert_id);
+        let commands = self
+            .resolve_command_calls(&name, Some(channel_id), &[], bot)
+            .await?;
+        self.render_sandboxed(name, context, commands).await
+    }
+
+    async fn render_sandboxed(
+        &self,
+        name: String,
+        context: tera::Context,
+        commands: FnvHashMap<String, i32>,
+    ) -> Result<String> {
+        if let Some(reason) = self.invalid_templates.get(&name) {
+            return Err(RenderError::Invalid(reason.clone()).into());
+        }
+        sandbox::render(self.tera.clone(), name, context, commands).await
+    }
+
+    /// Find the template commands a template calls with `command()` and the commands called by
+    /// those, up to the maximum call depth. Like running them directly, commands have to be active
+    /// in the channel (if any) and `user_permission_ids` have to fulfill their permission
+    /// requirements. Returns a map of command alias -> command id.
+    async fn resolve_command_calls(
+        &self,
+        name: &str,
+        channel_id: Option<i32>,
+        user_permission_ids: &[i32],
+        bot: &BotContext,
+    ) -> Result<FnvHashMap<String, i32>> {
+        let store = bot.commands.load();
+        let permission_store = bot.permissions.load();
+        let mut commands = FnvHashMap::default();
+        let mut checked = FnvHashSet::default();
+        let mut names = vec![name.to_string()];
+        for _ in 0..compose::MAX_COMMAND_DEPTH {
+            let mut next_names = vec![];
+            for name in &names {
+                for alias in self.command_calls.get(name).into_iter().flatten() {
+                    if !checked.insert(alias.clone()) {
+                        continue;
+                    }
+                    let attributes = match store.get_by_alias(alias) {
+                        Some(attributes)
+                            if attributes.enabled && attributes.handler_name == "template" =>
+                        {
+                            attributes
+                        }
+                        _ => continue,
+                    };
+                    if let Some(channel_id) = channel_id {
+                        let active =
+                            ChannelCommandConfig::get(&bot.db_context, channel_id, attributes.id)
+                                .await?
+                                .and_then(|config| config.active)
+                                .unwrap_or(attributes.default_active);
+                        if !active {
+                            continue;
+                        }
+                    }
+                    let permissions = permission_store
+                        .get_by_command(&bot.db_context, attributes.id)
+                        .await?;
+                    if !permissions.requirements().check(user_permission_ids) {
+                        continue;
+                    }
+                    commands.insert(alias.clone(), attributes.id);
+                    next_names.push(format!("{}", attributes.id));
+                }
+            }
+            names = next_names;
+        }
+        Ok(commands)
+    }
+
+    /// Usage of a template command from its argument declaration, see `args`
+    pub fn usage(&self, command_id: i32) -> Option<String> {
+        let request = self.context_requests.get(&command_id)?;
+        let specs = args::parse_specs(&request["args"]).ok()?;
+        Some(args::usage(&specs)).filter(|usage| !usage.is_empty())
     }
 
     pub fn register_context_provider(&mut self, provider_fn: impl ContextProvider + 'static) {
@@ -70,12 +212,13 @@ impl TemplateRenderer {
         context: &mut tera::Context,
         request: &JsonValue,
         event: &CbEvent,
+        command: &str,
         bot: &BotContext,
     ) -> Result<()> {
         let mut fut_unordered = self
             .context_providers
             .iter()
-            .map(|provider| provider.run(request, event, bot))
+            .map(|provider| provider.run(request, event, command, bot))
             .collect::<FuturesUnordered<_>>();
         while let Some(result) = fut_unordered.next().await {
             if let Some((key, value)) = result? {
@@ -85,17 +228,74 @@ impl TemplateRenderer {
         Ok(())
     }
 
-    /// Load the command templates from the database
+    /// Copy of the renderer with the template of a command replaced, or removed if `None`. Only
+    /// this template is compiled again.
+    pub fn with_command_template(
+        &self,
+        command_id: i32,
+        template: Option<&CommandTemplate>,
+    ) -> TemplateRenderer {
+        let mut renderer = self.clone();
+        let name = format!("{}", command_id);
+        renderer.context_requests.remove(&command_id);
+        renderer.command_calls.remove(&name);
+        renderer.invalid_templates.remove(&name);
+        match template {
+            Some(CommandTemplate {
+                template: Some(source),
+                template_context,
+                ..
+            }) => {
+                if let Some(request) = template_context {
+                    renderer
+                        .context_requests
+                        .insert(command_id, request.clone());
+                }
+                renderer.add_template(name, source);
+            }
+            // Tera can't remove templates, an empty template renders nothing
+            _ => renderer.add_template(name, ""),
+        }
+        renderer
+    }
+
+    /// Load the command and alert templates from the database
     async fn load_templates(&mut self, db_context: &DbContext) -> Result<()> {
         let templates: Vec<CommandTemplate> = CommandTemplate::all(&db_context.db_pool).await?;
-
         for template in templates {
             if let Some(request) = template.template_context {
                 self.context_requests.insert(template.id, request);
             }
-            self.tera
-                .add_raw_template(&format!("{}", template.id), &template.template.unwrap())?;
+            if let Some(source) = template.template {
+                self.add_template(format!("{}", template.id), &source);
+            }
+        }
+
+        let alerts = ChannelAlert::all(&db_context.db_pool).await?;
+        for alert in alerts {
+            self.add_template(alert_template_name(alert.id), &alert.template);
         }
         Ok(())
     }
+
+    /// Compile and add a template. Templates that fail are remembered as invalid so the others
+    /// stay usable, they are edited outside of the bot and one broken template shouldn't stop the
+    /// rest.
+    fn add_template(&mut self, name: String, source: &str) {
+        let command_calls = compose::command_calls(source);
+        if !command_calls.is_empty() {
+            self.command_calls.insert(name.clone(), command_calls);
+        }
+        let tera = Arc::make_mut(&mut self.tera);
+        let result = sandbox::check_template(source)
+            .and_then(|_| tera.add_raw_template(&name, source).map_err(Into::into));
+        if let Err(err) = result {
+            error!("Template {} is invalid: {}", name, err);
+            self.invalid_templates.insert(name, err.to_string());
+        }
+    }
+}
+
+fn alert_template_name(alert_id: i32) -> String {
+    format!("alert:{}", alert_id)
 }
@@ -0,0 +1,337 @@
+//! Limits for rendering user written templates. Templates are rendered on the blocking thread pool
+//! with a budget, loops are bounded by capping `range()` and the output is cut to the length of a
+//! chat message. Tags that reach into other templates and `get_env()` are not available.
+//!
+//! Tera can't be interrupted, so the budget of a render is kept in a thread local and checked
+//! whenever the template calls `range()` or one of the bot's functions and filters. A render that
+//! used up its time, loop items or output fails at the next of these calls. Loops over literal or
+//! context arrays that only use Tera's builtin filters and tags are not checked, they are only
+//! bounded by the size of the template and the context.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use fnv::FnvHashMap;
+use tera::{from_value, to_value, Filter, Function, Tera, Value};
+use thiserror::Error;
+use tokio::task;
+use tokio::time::timeout;
+
+use util::template;
+
+use crate::Result;
+
+use super::compose;
+
+/// Time a template may render before it fails at the next budget check, and how long callers
+/// wait for the output
+pub const RENDER_TIMEOUT: Duration = Duration::from_millis(500);
+/// Maximum length of the rendered output in characters, the Twitch message length limit
+pub const MAX_OUTPUT_CHARS: usize = 500;
+/// Maximum number of items `range()` may produce
+pub const MAX_RANGE_ITEMS: usize = 1000;
+/// Maximum number of items all `range()` calls of a render may produce together, bounds nested
+/// loops
+pub const MAX_RENDER_ITEMS: usize = 10_000;
+/// Maximum size in bytes of the output of all `command()` calls and `http_get_json()` responses
+/// of a render
+pub const MAX_RENDER_BYTES: usize = 64 * 1024;
+/// Maximum length of error details shown in chat
+const MAX_DETAILS_CHARS: usize = 100;
+
+thread_local! {
+    static BUDGET: RefCell<Option<RenderBudget>> = RefCell::new(None);
+}
+
+/// Budget of the render running on the current thread
+struct RenderBudget {
+    deadline: Instant,
+    items: usize,
+    bytes: usize,
+}
+
+/// Removes the budget when rendering is done, blocking threads are reused
+struct BudgetGuard;
+
+impl Drop for BudgetGuard {
+    fn drop(&mut self) {
+        BUDGET.with(|budget| budget.borrow_mut().take());
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("Rendering took longer than {0:?}")]
+    Timeout(Duration),
+    #[error("`{0}` is not allowed in templates")]
+    ForbiddenTag(String),
+    #[error("Template is invalid: {0}")]
+    Invalid(String),
+    /// Arguments given by the user don't match the command's declaration, the message includes
+    /// the usage
+    #[error("Invalid arguments: {0}")]
+    Args(String),
+    #[error("{0}")]
+    Tera(#[from] tera::Error),
+}
+
+impl RenderError {
+    /// Short description of the error for chat replies
+    pub fn user_message(&self) -> String {
+        match self {
+            RenderError::Timeout(_) => "the template took too long to render".to_string(),
+            RenderError::ForbiddenTag(tag) => format!("{} is not allowed in templates", tag),
+            RenderError::Invalid(_) => "the template is invalid".to_string(),
+            RenderError::Args(message) => message.clone(),
+            RenderError::Tera(err) => {
+                // tera wraps the actual problem in "failed to render" errors
+                let mut source: &dyn std::error::Error = err;
+                while let Some(inner) = source.source() {
+                    source = inner;
+                }
+                let details = source.to_string();
+                let mut message: String = details.chars().take(MAX_DETAILS_CHARS).collect();
+                if message.len() < details.len() {
+                    message.push('…');
+                }
+                message
+            }
+        }
+    }
+}
+
+/// Override builtin Tera functions that are unsafe in user templates
+pub fn register(tera: &mut Tera) {
+    tera.register_function("range", range);
+    tera.register_function("get_env", get_env);
+}
+
+/// Check a template source for tags that are not allowed
+pub fn check_template(source: &str) -> std::result::Result<(), RenderError> {
+    match template::forbidden_tag(source) {
+        Some(tag) => Err(RenderError::ForbiddenTag(tag.to_string())),
+        None => Ok(()),
+    }
+}
+
+/// Render a template on the blocking thread pool, fails with `RenderError::Timeout` if there is no
+/// output after `RENDER_TIMEOUT`. The render itself only stops at the next budget check, one that
+/// doesn't reach one keeps running in the background, see the module docs. `commands` are the
+/// template commands the template can call with `command()`.
+pub async fn render(
+    tera: Arc<Tera>,
+    name: String,
+    context: tera::Context,
+    commands: FnvHashMap<String, i32>,
+) -> Result<String> {
+    let render = task::spawn_blocking(move || {
+        let deadline = Instant::now() + RENDER_TIMEOUT;
+        BUDGET.with(|budget| {
+            budget.borrow_mut().replace(RenderBudget {
+                deadline,
+                items: 0,
+                bytes: 0,
+            })
+        });
+        let _guard = BudgetGuard;
+        match compose::render_scoped(tera, &name, &context, commands) {
+            Err(_) if Instant::now() >= deadline => Err(RenderError::Timeout(RENDER_TIMEOUT)),
+            output => output.map_err(RenderError::from),
+        }
+    });
+    let output = timeout(RENDER_TIMEOUT, render)
+        .await
+        .map_err(|_| RenderError::Timeout(RENDER_TIMEOUT))???;
+    Ok(truncate_output(output))
+}
+
+/// Charge each call of a function to the render budget, so loops calling it stop at the deadline
+pub(super) fn budgeted_function(function: impl Function + 'static) -> impl Function {
+    move |args: &HashMap<String, Value>| {
+        charge(0, 0)?;
+        function.call(args)
+    }
+}
+
+/// Charge each call of a filter to the render budget, see `budgeted_function`
+pub(super) fn budgeted_filter(filter: impl Filter + 'static) -> impl Filter {
+    move |value: &Value, args: &HashMap<String, Value>| {
+        charge(0, 0)?;
+        filter.filter(value, args)
+    }
+}
+
+/// Charge `items` loop items and `bytes` of `command()` output or `http_get_json()` responses to
+/// the budget of the render running on the current thread. Fails if the budget is used up or the time is over.
+pub(super) fn charge(items: usize, bytes: usize) -> tera::Result<()> {
+    BUDGET.with(|budget| {
+        let mut budget = budget.borrow_mut();
+        let budget = match budget.as_mut() {
+            Some(budget) => budget,
+            None => return Ok(()),
+        };
+        if Instant::now() >= budget.deadline {
+            return Err(tera::Error::msg(format!(
+                "rendering took longer than {:?}",
+                RENDER_TIMEOUT
+            )));
+        }
+        budget.items += items;
+        if budget.items > MAX_RENDER_ITEMS {
+            return Err(tera::Error::msg(format!(
+                "loops are limited to {} items in total",
+                MAX_RENDER_ITEMS
+            )));
+        }
+        budget.bytes += bytes;
+        if budget.bytes > MAX_RENDER_BYTES {
+            return Err(tera::Error::msg(format!(
+                "`command` output and `http_get_json` responses are limited to {} bytes in total",
+                MAX_RENDER_BYTES
+            )));
+        }
+        Ok(())
+    })
+}
+
+/// Cut the output to `MAX_OUTPUT_CHARS` characters
+fn truncate_output(mut output: String) -> String {
+    if let Some((index, _)) = output.char_indices().nth(MAX_OUTPUT_CHARS) {
+        output.truncate(index);
+    }
+    output
+}
+
+/// Builtin `range(end, start=0, step_by=1)` limited to `MAX_RANGE_ITEMS` items
+fn range(args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let number = |name: &str, default: Option<usize>| -> tera::Result<usize> {
+        match args.get(name) {
+            Some(value) => from_value(value.clone()).map_err(|_| {
+                tera::Error::msg(format!(
+                    "`range` received an invalid `{}` argument: {}",
+                    name, value
+                ))
+            }),
+            None => default.ok_or_else(|| {
+                tera::Error::msg(format!("`range` requires the `{}` argument", name))
+            }),
+        }
+    };
+    let start = number("start", Some(0))?;
+    let end = number("end", None)?;
+    let step_by = number("step_by", Some(1))?;
+    if step_by == 0 {
+        return Err(tera::Error::msg(
+            "`range` requires a step_by greater than 0",
+        ));
+    }
+    if start > end {
+        return Err(tera::Error::msg("`range` requires start <= end"));
+    }
+    let items = (end - start + step_by - 1) / step_by;
+    if items > MAX_RANGE_ITEMS {
+        return Err(tera::Error::msg(format!(
+            "`range` is limited to {} items",
+            MAX_RANGE_ITEMS
+        )));
+    }
+    charge(items, 0)?;
+    Ok(to_value((start..end).step_by(step_by).collect::<Vec<_>>())?)
+}
+
+/// Replaces the builtin `get_env()`, the bot's environment contains credentials
+fn get_env(_args: &HashMap<String, Value>) -> tera::Result<Value> {
+    Err(tera::Error::msg("`get_env` is not available in templates"))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_range() {
+        let args = |value: Value| serde_json::from_value(value).unwrap();
+        assert_eq!(range(&args(json!({ "end": 3 }))).unwrap(), json!([0, 1, 2]));
+        assert_eq!(
+            range(&args(json!({ "start": 2, "end": 10, "step_by": 3 }))).unwrap(),
+            json!([2, 5, 8])
+        );
+        assert!(range(&args(json!({ "end": MAX_RANGE_ITEMS }))).is_ok());
+        assert!(range(&args(json!({ "end": MAX_RANGE_ITEMS + 1 }))).is_err());
+        assert!(range(&args(json!({ "end": 3, "step_by": 0 }))).is_err());
+        assert!(range(&args(json!({ "start": 3, "end": 1 }))).is_err());
+    }
+
+    #[test]
+    fn test_truncate_output() {
+        assert_eq!(truncate_output("short".to_string()), "short");
+        let long = "ä".repeat(MAX_OUTPUT_CHARS + 10);
+        assert_eq!(truncate_output(long).chars().count(), MAX_OUTPUT_CHARS);
+    }
+
+    #[test]
+    fn test_charge() {
+        // outside of a render
+        assert!(charge(MAX_RENDER_ITEMS + 1, 0).is_ok());
+
+        let set_budget = |deadline: Instant| {
+            BUDGET.with(|budget| {
+                budget.borrow_mut().replace(RenderBudget {
+                    deadline,
+                    items: 0,
+                    bytes: 0,
+                })
+            })
+        };
+        let _guard = BudgetGuard;
+        set_budget(Instant::now() + RENDER_TIMEOUT);
+        assert!(charge(MAX_RENDER_ITEMS, MAX_RENDER_BYTES).is_ok());
+        assert!(charge(1, 0).is_err());
+        set_budget(Instant::now() + RENDER_TIMEOUT);
+        assert!(charge(0, MAX_RENDER_BYTES + 1).is_err());
+        set_budget(Instant::now());
+        assert!(charge(0, 0).is_err());
+
+        let function = budgeted_function(|_: &HashMap<String, Value>| Ok(Value::Null));
+        let filter = budgeted_filter(|value: &Value, _: &HashMap<String, Value>| Ok(value.clone()));
+        assert!(function.call(&HashMap::new()).is_err());
+        assert!(filter.filter(&Value::Null, &HashMap::new()).is_err());
+        set_budget(Instant::now() + RENDER_TIMEOUT);
+        assert!(function.call(&HashMap::new()).is_ok());
+        assert!(filter.filter(&Value::Null, &HashMap::new()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_render_limits() {
+        let mut tera = Tera::default();
+        register(&mut tera);
+        tera.add_raw_template("env", r#"{{ get_env(name="HOME") }}"#)
+            .unwrap();
+        tera.add_raw_template("missing", "{{ nope }}").unwrap();
+        tera.add_raw_template("ok", "{{ 1 + 1 }}").unwrap();
+        tera.add_raw_template(
+            "nested",
+            "{% for i in range(end=1000) %}{% for j in range(end=1000) %}.{% endfor %}{% endfor %}",
+        )
+        .unwrap();
+        let tera = Arc::new(tera);
+        let context = tera::Context::new();
+
+        let render_template = |name: &str| {
+            render(
+                tera.clone(),
+                name.to_string(),
+                context.clone(),
+                Default::default(),
+            )
+        };
+        assert_eq!(render_template("ok").await.unwrap(), "2");
+        assert!(render_template("env").await.is_err());
+        assert!(render_template("missing").await.is_err());
+        assert!(render_template("nested").await.is_err());
+    }
+}
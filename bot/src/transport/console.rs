@@ -0,0 +1,318 @@
+//! Local chat in the terminal, for development without a Twitch account. Lines typed on stdin are
+//! sent to the bot as chat messages of a chosen user in a chosen channel, messages sent by the bot
+//! are printed to stdout.
+//!
+//! Input lines starting with `/` are console commands:
+//!
+//! - `/user <name>` chat as another user
+//! - `/channel <name>` switch to another channel
+//! - `/w <message>` whisper the bot
+//! - `/quit` stop the bot, same as closing stdin
+//!
+//! Users and channels get IDs derived from their names, see `local_id`.
+
+use std::hash::Hasher;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use fnv::FnvHasher;
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::channel::oneshot;
+use futures::lock::Mutex;
+use futures::{future, stream, FutureExt, StreamExt};
+use tmi_rs::event::Event;
+use tmi_rs::irc::IrcMessage;
+use tmi_rs::ClientMessage;
+use tokio::task;
+
+use async_trait::async_trait;
+
+use crate::event::CbEvent;
+use crate::transport::{ChatConnection, ChatTransport};
+use crate::Result;
+
+/// Name of the bot receiving whispers if no username is configured
+pub const DEFAULT_BOT_NAME: &str = "cerebot";
+/// Lowest ID of local users and channels, above the IDs assigned by Twitch so far
+const LOCAL_ID_OFFSET: i32 = 2_000_000_000;
+/// Number of different local IDs
+const LOCAL_ID_RANGE: u64 = 100_000_000;
+
+const CONSOLE_HELP: &str =
+    "Console commands: /user <name>, /channel <name>, /w <message> (whisper the bot), /quit";
+
+/// Used to create unique message IDs
+static MESSAGE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub struct ConsoleTransport {
+    input: Arc<Mutex<ConsoleInput>>,
+}
+
+/// Stdin lines and the current user and channel, kept when the bot restarts
+struct ConsoleInput {
+    lines: UnboundedReceiver<String>,
+    bot_name: String,
+    user: String,
+    channel: String,
+}
+
+impl ConsoleTransport {
+    /// Start reading from stdin. `bot_name` is the user receiving whispers.
+    pub fn new(bot_name: &str, user: &str, channel: &str) -> Self {
+        println!(
+            "Chatting as {} in #{}. {}",
+            user.to_lowercase(),
+            channel.to_lowercase(),
+            CONSOLE_HELP
+        );
+        ConsoleTransport {
+            input: Arc::new(Mutex::new(ConsoleInput {
+                lines: read_stdin(),
+                bot_name: bot_name.to_lowercase(),
+                user: user.to_lowercase(),
+                channel: channel.to_lowercase(),
+            })),
+        }
+    }
+}
+
+#[async_trait]
+impl ChatTransport for ConsoleTransport {
+    async fn connect(&mut self) -> Result<ChatConnection> {
+        let (sender, mut outgoing) = mpsc::unbounded::<ClientMessage<String>>();
+        let (closed_sender, closed) = oneshot::channel::<()>();
+        task::spawn(async move {
+            while let Some(message) = outgoing.next().await {
+                if let ClientMessage::Close = message {
+                    break;
+                }
+                println!("{}", message);
+            }
+            // ends the incoming events
+            drop(closed_sender);
+        });
+
+        // load the current channel into the bot state, like after joining it on Twitch
+        let channel = self.input.lock().await.channel.clone();
+        let joined = stream::iter(parse_event(&roomstate(&channel, local_id(&channel))));
+
+        let typed = stream::unfold(self.input.clone(), |input| async move {
+            let events = input.lock().await.next_events().await?;
+            Some((stream::iter(events), input))
+        })
+        .flatten();
+
+        let events = joined
+            .chain(typed)
+            .map(Some)
+            // end of input
+            .chain(stream::once(future::ready(None)));
+        let receiver = stream::select(events, closed.into_stream().map(|_| None))
+            .take_while(|event| future::ready(event.is_some()))
+            .filter_map(future::ready)
+            .map(CbEvent::from)
+            .boxed();
+
+        Ok(ChatConnection {
+            sender,
+            receiver,
+            error_receiver: stream::empty().boxed(),
+        })
+    }
+}
+
+impl ConsoleInput {
+    /// Wait for the next line and turn it into chat events. Returns `None` when the input ends.
+    async fn next_events(&mut self) -> Option<Vec<Arc<Event<String>>>> {
+        let line = self.lines.next().await?;
+        let raw_messages = self.handle_line(&line)?;
+        Some(
+            raw_messages
+                .iter()
+                .filter_map(|raw| parse_event(raw))
+                .collect(),
+        )
+    }
+
+    /// Apply a line typed into the console, returns the raw IRC messages it results in
+    fn handle_line(&mut self, line: &str) -> Option<Vec<String>> {
+        let line = line.trim();
+        let (command, arg) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+            None => (line, ""),
+        };
+        let messages = match command {
+            "" => vec![],
+            "/quit" => return None,
+            "/user" | "/channel" if !is_valid_name(arg) => {
+                println!("Invalid name {:?}", arg);
+                vec![]
+            }
+            "/user" => {
+                self.user = arg.to_lowercase();
+                println!("Chatting as {}", self.user);
+                vec![]
+            }
+            "/channel" => {
+                self.channel = arg.to_lowercase();
+                println!("Chatting in #{}", self.channel);
+                vec![roomstate(&self.channel, local_id(&self.channel))]
+            }
+            "/w" if !arg.is_empty() => vec![whisper(
+                &self.bot_name,
+                &self.user,
+                local_id(&self.user),
+                arg,
+            )],
+            _ if command.starts_with('/') => {
+                println!("{}", CONSOLE_HELP);
+                vec![]
+            }
+            _ => vec![privmsg(
+                &self.channel,
+                local_id(&self.channel),
+                &self.user,
+                local_id(&self.user),
+                line,
+            )],
+        };
+        Some(messages)
+    }
+}
+
+/// Read stdin lines on a separate thread, the receiver ends at the end of the input
+fn read_stdin() -> UnboundedReceiver<String> {
+    let (sender, receiver) = mpsc::unbounded();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => {
+                    if sender.unbounded_send(line).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    error!("Reading from stdin failed: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+    receiver
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse_event(raw: &str) -> Option<Arc<Event<String>>> {
+    match IrcMessage::parse(raw) {
+        Ok(message) => Some(Arc::new(Event::from(message))),
+        Err(err) => {
+            error!("Invalid chat message {:?}: {}", raw, err);
+            None
+        }
+    }
+}
+
+/// Stable ID for the name of a local user or channel. IDs are used to look up users and channels
+/// in the database, so local names don't clash with the ones from Twitch.
+pub fn local_id(name: &str) -> i32 {
+    let mut hasher = FnvHasher::default();
+    hasher.write(name.as_bytes());
+    LOCAL_ID_OFFSET + (hasher.finish() % LOCAL_ID_RANGE) as i32
+}
+
+fn next_message_id() -> usize {
+    MESSAGE_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Raw ROOMSTATE message, sent by Twitch after joining a channel
+pub fn roomstate(channel: &str, room_id: i32) -> String {
+    format!(
+        "@emote-only=0;followers-only=-1;r9k=0;rituals=0;room-id={};slow=0;subs-only=0 \
+         :tmi.twitch.tv ROOMSTATE #{}",
+        room_id, channel
+    )
+}
+
+/// Raw channel message
+pub fn privmsg(channel: &str, room_id: i32, user: &str, user_id: i32, message: &str) -> String {
+    format!(
+        "@badge-info=;badges=;color=;display-name={name};emotes=;flags=;\
+         id=00000000-0000-0000-0000-{id:012};mod=0;room-id={room_id};subscriber=0;tmi-sent-ts=0;\
+         turbo=0;user-id={user_id};user-type= :{name}!{name}@{name}.tmi.twitch.tv PRIVMSG \
+         #{channel} :{message}",
+        name = user,
+        id = next_message_id(),
+        room_id = room_id,
+        user_id = user_id,
+        channel = channel,
+        message = message
+    )
+}
+
+/// Raw whisper to `bot_name`
+pub fn whisper(bot_name: &str, user: &str, user_id: i32, message: &str) -> String {
+    format!(
+        "@badges=;color=;display-name={name};emotes=;message-id={id};thread-id={user_id}_1;\
+         turbo=0;user-id={user_id};user-type= :{name}!{name}@{name}.tmi.twitch.tv WHISPER {bot} \
+         :{message}",
+        name = user,
+        id = next_message_id(),
+        user_id = user_id,
+        bot = bot_name,
+        message = message
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn input() -> ConsoleInput {
+        ConsoleInput {
+            lines: mpsc::unbounded().1,
+            bot_name: "cerebot".into(),
+            user: "someone".into(),
+            channel: "somechannel".into(),
+        }
+    }
+
+    #[test]
+    fn test_local_id() {
+        assert_eq!(local_id("someone"), local_id("someone"));
+        assert_ne!(local_id("someone"), local_id("someone_else"));
+        assert!(local_id("someone") >= LOCAL_ID_OFFSET);
+    }
+
+    #[test]
+    fn test_handle_line() {
+        let mut input = input();
+        let messages = input.handle_line("!say hi").unwrap();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains(":someone!someone@someone.tmi.twitch.tv PRIVMSG"));
+        assert!(messages[0].ends_with("#somechannel :!say hi"));
+        assert!(messages[0].contains(&format!("room-id={};", local_id("somechannel"))));
+
+        assert!(input.handle_line("/user Other_User").unwrap().is_empty());
+        assert_eq!(input.user, "other_user");
+        assert!(input.handle_line("/user not valid").unwrap().is_empty());
+        assert_eq!(input.user, "other_user");
+
+        let messages = input.handle_line("/channel elsewhere").unwrap();
+        assert_eq!(
+            messages,
+            vec![roomstate("elsewhere", local_id("elsewhere"))]
+        );
+
+        let messages = input.handle_line("/w say psst").unwrap();
+        assert!(messages[0].ends_with("WHISPER cerebot :say psst"));
+
+        assert!(input.handle_line("/unknown").unwrap().is_empty());
+        assert!(input.handle_line("/quit").is_none());
+    }
+}
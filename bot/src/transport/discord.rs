@@ -0,0 +1,218 @@
+//! Discord connection. Messages in guild channels are handled like Twitch channel messages, direct
+//! messages like whispers. Guild channels are added to the `channels` table as
+//! `discord:<guild id>/<channel id>` with the command prefix `!` when the first message is received,
+//! see `CbEvent::channel_info`. The IDs keep the names unique and stable when guilds or channels
+//! are renamed. Discord users can link their account to a Twitch user with the `link`
+//! command.
+
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::channel::oneshot;
+use futures::future::{self, abortable, AbortHandle};
+use futures::{stream, FutureExt, StreamExt};
+use serenity::client::{Client, Context, EventHandler};
+use serenity::model::channel::Message;
+use serenity::model::gateway::Ready;
+use serenity::model::id::ChannelId;
+use tmi_rs::ClientMessage;
+use tokio::task;
+
+use async_trait::async_trait;
+
+use crate::config::CerebotConfig;
+use crate::error::Error;
+use crate::event::CbEvent;
+use crate::handlers::error::CommandError;
+use crate::transport::{ChatConnection, ChatTransport};
+use crate::Result;
+
+/// A message received on Discord
+#[derive(Debug)]
+pub struct DiscordMessage {
+    pub message_id: u64,
+    pub channel_id: u64,
+    /// name of the channel in the `channels` table, `None` for direct messages
+    pub channel: Option<String>,
+    /// readable guild and channel name for logs, e.g. `Some Guild/#general`
+    pub channel_display: Option<String>,
+    pub author_id: u64,
+    /// user name with discriminator, e.g. `someone#1234`. Can't clash with Twitch names, which
+    /// don't contain `#`.
+    pub author: String,
+    /// nickname on the server
+    pub author_nick: Option<String>,
+    pub content: String,
+    replies: UnboundedSender<DiscordReply>,
+}
+
+#[derive(Debug)]
+struct DiscordReply {
+    channel_id: u64,
+    message: String,
+}
+
+impl DiscordMessage {
+    /// Send a message to the channel or direct message conversation this message was sent in
+    pub fn reply(&self, message: &str) -> Result<()> {
+        self.replies
+            .unbounded_send(DiscordReply {
+                channel_id: self.channel_id,
+                message: message.to_string(),
+            })
+            .map_err(|_| CommandError::ReplyError("Discord connection is closed").into())
+    }
+}
+
+/// Discord connection with the token from the bot config. Like `TwitchTransport`, the config is
+/// read on every connect.
+pub struct DiscordTransport;
+
+#[async_trait]
+impl ChatTransport for DiscordTransport {
+    async fn connect(&mut self) -> Result<ChatConnection> {
+        let (message_sender, messages) = mpsc::unbounded();
+        let (reply_sender, mut replies) = mpsc::unbounded::<DiscordReply>();
+        let config = CerebotConfig::get()?;
+        let token = config.discord_token().ok_or_else(|| {
+            Error::Config("discord_token is required to connect to Discord".to_string())
+        })?;
+        let mut client = Client::builder(token)
+            .event_handler(Handler {
+                messages: message_sender,
+                replies: reply_sender,
+            })
+            .await?;
+        let http = client.cache_and_http.http.clone();
+
+        let (error_sender, errors) = mpsc::unbounded();
+        let (run_client, client_handle) = abortable(async move { client.start().await });
+        task::spawn(async move {
+            if let Ok(Err(err)) = run_client.await {
+                error_sender.unbounded_send(err.into()).ok();
+            }
+        });
+
+        let (send_replies, replies_handle) = abortable(async move {
+            while let Some(reply) = replies.next().await {
+                if let Err(err) = ChannelId(reply.channel_id).say(&http, &reply.message).await {
+                    error!("Sending Discord message failed: {}", err);
+                }
+            }
+        });
+        task::spawn(send_replies);
+
+        // messages for Twitch don't apply here, only closing the connection is handled
+        let (sender, mut outgoing) = mpsc::unbounded::<ClientMessage<String>>();
+        let (closed_sender, closed) = oneshot::channel::<()>();
+        task::spawn(async move {
+            while let Some(message) = outgoing.next().await {
+                if let ClientMessage::Close = message {
+                    closed_sender.send(()).ok();
+                    break;
+                }
+            }
+        });
+
+        // stops the client when the connection is dropped
+        let connection_guard = AbortOnDrop(vec![client_handle, replies_handle]);
+        let closed = closed
+            .into_stream()
+            .filter_map(|closed| future::ready(closed.ok()));
+        let receiver = stream::select(messages.map(Some), closed.map(|_| None))
+            .take_while(|message| future::ready(message.is_some()))
+            .filter_map(future::ready)
+            .map(move |message| {
+                let _ = &connection_guard;
+                CbEvent::from(message)
+            })
+            .boxed();
+
+        Ok(ChatConnection {
+            sender,
+            receiver,
+            error_receiver: errors.boxed(),
+        })
+    }
+}
+
+struct AbortOnDrop(Vec<AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for handle in &self.0 {
+            handle.abort();
+        }
+    }
+}
+
+struct Handler {
+    messages: UnboundedSender<DiscordMessage>,
+    replies: UnboundedSender<DiscordReply>,
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn message(&self, ctx: Context, message: Message) {
+        // ignore other bots and the bot's own messages
+        if message.author.bot {
+            return;
+        }
+        let (channel, channel_display) = match message.guild_id {
+            Some(guild_id) => {
+                let guild_name = guild_id
+                    .name(&ctx.cache)
+                    .await
+                    .unwrap_or_else(|| guild_id.to_string());
+                let channel_name = message
+                    .channel_id
+                    .name(&ctx.cache)
+                    .await
+                    .unwrap_or_else(|| message.channel_id.to_string());
+                (
+                    Some(channel_name_for(guild_id.0, message.channel_id.0)),
+                    Some(format!("{}/#{}", guild_name, channel_name)),
+                )
+            }
+            None => (None, None),
+        };
+        let discord_message = DiscordMessage {
+            message_id: message.id.0,
+            channel_id: message.channel_id.0,
+            channel,
+            channel_display,
+            author_id: message.author.id.0,
+            author: format!(
+                "{}#{:04}",
+                message.author.name.to_lowercase(),
+                message.author.discriminator
+            ),
+            author_nick: message.member.and_then(|member| member.nick),
+            content: message.content,
+            replies: self.replies.clone(),
+        };
+        if self.messages.unbounded_send(discord_message).is_err() {
+            debug!("Discord message received after the connection was closed");
+        }
+    }
+
+    async fn ready(&self, _ctx: Context, ready: Ready) {
+        info!("Discord connected as {}.", ready.user.name);
+    }
+}
+
+/// Name of a Discord guild channel in the `channels` table
+fn channel_name_for(guild_id: u64, channel_id: u64) -> String {
+    format!("discord:{}/{}", guild_id, channel_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_channel_name() {
+        assert_eq!(
+            channel_name_for(81384788765712384, 381886868708655104),
+            "discord:81384788765712384/381886868708655104"
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! Chat transports connect the bot to a chat. Incoming events are dispatched to the event
+//! handlers, outgoing messages are sent through the `ChatSender` of the connection. Replies to
+//! messages from other platforms than Twitch are sent by the event, see `send_reply`.
+
+use futures::stream::BoxStream;
+use tmi_rs::ChatSender;
+
+use async_trait::async_trait;
+
+use crate::error::Error;
+use crate::event::CbEvent;
+use crate::Result;
+
+pub use self::console::ConsoleTransport;
+pub use self::discord::DiscordTransport;
+pub use self::twitch::TwitchTransport;
+
+pub mod console;
+pub mod discord;
+mod twitch;
+
+/// An open chat connection
+pub struct ChatConnection {
+    /// Sends messages to chat. Sending `ClientMessage::Close` closes the connection.
+    pub sender: ChatSender,
+    /// Incoming chat events, ends when the connection is closed
+    pub receiver: BoxStream<'static, CbEvent>,
+    /// Errors of the connection that don't close it
+    pub error_receiver: BoxStream<'static, Error>,
+}
+
+#[async_trait]
+pub trait ChatTransport: Send {
+    /// Open a new connection. Called again after the previous connection was closed when the bot
+    /// restarts.
+    async fn connect(&mut self) -> Result<ChatConnection>;
+}
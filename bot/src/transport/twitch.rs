@@ -0,0 +1,44 @@
+use futures::StreamExt;
+use tmi_rs::{TwitchChatConnection, TwitchClient, TwitchClientConfigBuilder};
+
+use async_trait::async_trait;
+
+use crate::config::CerebotConfig;
+use crate::error::Error;
+use crate::event::CbEvent;
+use crate::transport::{ChatConnection, ChatTransport};
+use crate::Result;
+
+/// Twitch chat connection with the credentials from the bot config. The config is read on every
+/// connect, so changed credentials are used after a restart.
+pub struct TwitchTransport;
+
+fn create_client(config: &CerebotConfig) -> Result<TwitchClient> {
+    let (username, auth_token) = config.twitch_credentials()?;
+    Ok(TwitchClientConfigBuilder::default()
+        .username(username.to_string())
+        .token(auth_token.to_string())
+        .build()
+        .map_err(Error::TmiConfig)?
+        .into())
+}
+
+#[async_trait]
+impl ChatTransport for TwitchTransport {
+    async fn connect(&mut self) -> Result<ChatConnection> {
+        let chat_client = create_client(&*CerebotConfig::get()?)?;
+        debug!("Connecting to Twitch chat...");
+        let TwitchChatConnection {
+            sender,
+            receiver,
+            error_receiver,
+        } = chat_client.connect().await?;
+        info!("Twitch chat connected.");
+
+        Ok(ChatConnection {
+            sender,
+            receiver: receiver.map(CbEvent::from).boxed(),
+            error_receiver: error_receiver.map(Error::from).boxed(),
+        })
+    }
+}
@@ -0,0 +1,85 @@
+//! Client for the Twitch Helix API, used where chat messages don't tell enough about a user
+
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::Deserialize;
+
+use crate::config::CerebotConfig;
+use crate::error::Error;
+use crate::Result;
+
+const USERS_URL: &str = "https://api.twitch.tv/helix/users";
+
+/// Maximum number of logins in one users request
+const MAX_LOGINS: usize = 100;
+
+#[derive(Debug)]
+pub struct TwitchApi {
+    client: reqwest::Client,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HelixUser {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HelixResponse<T> {
+    data: Vec<T>,
+}
+
+impl TwitchApi {
+    /// Create a client authenticated with the bot's chat token. Fails if the client ID or the
+    /// token are not configured, the token has to be created for the client ID.
+    pub fn new(config: &CerebotConfig) -> Result<Self> {
+        let (client_id, auth_token) = match (config.twitch_client_id(), config.auth_token()) {
+            (Some(client_id), Some(auth_token)) => (client_id, auth_token),
+            _ => {
+                return Err(Error::Config(
+                    "twitch_client_id and auth_token are required for the Twitch API".to_string(),
+                ))
+            }
+        };
+        let header = |value: &str| {
+            HeaderValue::from_str(value)
+                .map_err(|err| Error::Config(format!("Invalid Twitch API header value: {}", err)))
+        };
+        let mut default_headers = HeaderMap::new();
+        default_headers.insert("Client-ID", header(client_id)?);
+        default_headers.insert(
+            "Authorization",
+            header(&format!(
+                "Bearer {}",
+                auth_token.trim_start_matches("oauth:")
+            ))?,
+        );
+        Ok(TwitchApi {
+            client: reqwest::ClientBuilder::new()
+                .default_headers(default_headers)
+                .build()?,
+        })
+    }
+
+    /// Look up users by their login names, unknown names are missing in the result
+    pub async fn users_by_login(&self, logins: &[String]) -> Result<Vec<HelixUser>> {
+        let mut users = vec![];
+        for logins in logins.chunks(MAX_LOGINS) {
+            let query = logins
+                .iter()
+                .map(|login| ("login", login.as_str()))
+                .collect::<Vec<_>>();
+            let response = self
+                .client
+                .get(USERS_URL)
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<HelixResponse<HelixUser>>()
+                .await?;
+            users.extend(response.data);
+        }
+        Ok(users)
+    }
+}
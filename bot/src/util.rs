@@ -15,7 +15,8 @@ pub fn disallowed_input_chars(c: char) -> bool {
         && !is_quote(c)
 }
 
-fn is_quote(c: char) -> bool {
+/// Match the quote characters of quoted command arguments
+pub fn is_quote(c: char) -> bool {
     c == '\'' || c == '"'
 }
 
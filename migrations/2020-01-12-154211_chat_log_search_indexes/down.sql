@@ -0,0 +1,2 @@
+drop index chat_events_sender_idx;
+drop index chat_events_message_fts_idx;
@@ -0,0 +1,5 @@
+create index chat_events_message_fts_idx
+    on chat_events using gin (to_tsvector('simple', coalesce(message, '')));
+
+create index chat_events_sender_idx
+    on chat_events (sender_user_id, received_at);
@@ -0,0 +1,6 @@
+drop function refresh_chat_stats();
+drop table chat_stats_refresh;
+drop table command_usage_daily;
+drop table chat_stats_first_seen;
+drop table chat_stats_user_daily;
+drop table chat_stats_hourly;
@@ -0,0 +1,90 @@
+create table chat_stats_hourly
+(
+    channel_id    integer references channels on delete cascade not null,
+    hour          timestamp with time zone                      not null,
+    message_count integer                                       not null,
+    chatter_count integer                                       not null,
+    primary key (channel_id, hour)
+);
+
+create table chat_stats_user_daily
+(
+    channel_id    integer references channels on delete cascade not null,
+    day           date                                          not null,
+    user_id       integer references users on delete cascade    not null,
+    message_count integer                                       not null,
+    primary key (channel_id, day, user_id)
+);
+
+create table chat_stats_first_seen
+(
+    channel_id integer references channels on delete cascade not null,
+    user_id    integer references users on delete cascade    not null,
+    first_day  date                                          not null,
+    primary key (channel_id, user_id)
+);
+
+create table command_usage_daily
+(
+    channel_id integer references channels on delete cascade           not null,
+    day        date                                                    not null,
+    command_id integer references command_attributes on delete cascade not null,
+    use_count  integer                                                 not null,
+    primary key (channel_id, day, command_id)
+);
+
+-- single row table holding the time up to which the rollups have been refreshed
+create table chat_stats_refresh
+(
+    id              boolean primary key default true check (id),
+    refreshed_until timestamp with time zone not null
+);
+insert into chat_stats_refresh (refreshed_until)
+values ('-infinity');
+
+-- Incrementally refresh the chat statistic rollup tables. The last hour before the previous refresh is
+-- recomputed to include events that were persisted late, daily rollups recompute the whole day.
+create function refresh_chat_stats() returns void as
+$$
+declare
+    since timestamp with time zone;
+    until timestamp with time zone := now();
+begin
+    if not pg_try_advisory_xact_lock(hashtext('refresh_chat_stats')) then
+        raise notice 'Chat stats refresh is already running';
+        return;
+    end if;
+
+    select date_trunc('hour', refreshed_until - interval '1 hour') into since from chat_stats_refresh;
+
+    insert into chat_stats_hourly (channel_id, hour, message_count, chatter_count)
+    select channel_id, date_trunc('hour', received_at), count(*), count(distinct sender_user_id)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and received_at >= since
+    group by 1, 2
+    on conflict (channel_id, hour) do update set message_count = excluded.message_count,
+                                                 chatter_count = excluded.chatter_count;
+
+    insert into chat_stats_user_daily (channel_id, day, user_id, message_count)
+    select channel_id, (received_at at time zone 'UTC')::date, sender_user_id, count(*)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and sender_user_id is not null
+      and received_at >= date_trunc('day', since at time zone 'UTC') at time zone 'UTC'
+    group by 1, 2, 3
+    on conflict (channel_id, day, user_id) do update set message_count = excluded.message_count;
+
+    insert into chat_stats_first_seen (channel_id, user_id, first_day)
+    select channel_id, user_id, min(day)
+    from chat_stats_user_daily
+    where day >= (since at time zone 'UTC')::date
+    group by 1, 2
+    on conflict (channel_id, user_id) do update set first_day = least(chat_stats_first_seen.first_day,
+                                                                      excluded.first_day);
+
+    update chat_stats_refresh set refreshed_until = until;
+end
+$$ language plpgsql;
@@ -0,0 +1,30 @@
+-- Merge all subpartitions of each channel back into a single subpartition covering all times
+do
+$$
+    declare
+        r     record;
+        child text;
+    begin
+        for r in select p.relname parent
+                 from pg_class p
+                 where p.relname ~ '^chat_events_ch\d+$'
+                   and p.relkind = 'p'
+            loop
+                execute format('create table %I (like chat_events)', r.parent || '_merged');
+                for child in select c.relname
+                             from pg_inherits i
+                                      join pg_class c on c.oid = i.inhrelid
+                             where i.inhparent = r.parent::regclass
+                    loop
+                        execute format('alter table %I detach partition %I', r.parent, child);
+                        execute format('insert into %I select * from %I', r.parent || '_merged', child);
+                        execute format('drop table %I', child);
+                    end loop;
+                execute format('alter table %I rename to %I', r.parent || '_merged', r.parent || '_default');
+                execute format('alter table %I attach partition %I for values from (minvalue) to (maxvalue)',
+                               r.parent, r.parent || '_default');
+            end loop;
+    end
+$$;
+
+drop function create_chat_events_partitions(int, int);
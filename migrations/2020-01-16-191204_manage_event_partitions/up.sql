@@ -0,0 +1,75 @@
+-- Create the list partition of a channel if it doesn't exist, with a default range subpartition and
+-- monthly range subpartitions from the current month to `months_ahead` months in the future.
+-- A null channel ID manages the partition for events without a channel.
+create function create_chat_events_partitions(cid int, months_ahead int) returns void as
+$$
+declare
+    partition_name     text := format('chat_events_ch%s', coalesce(cid, 0));
+    month_start        timestamp;
+    month_end          timestamp;
+    subpartition_name  text;
+begin
+    if to_regclass(partition_name) is null then
+        raise notice 'Creating partition table for channel % (%)', cid, partition_name;
+        execute format(
+                'create table %I partition of chat_events for values in (%s) partition by range(received_at)',
+                partition_name, coalesce(cid::text, 'null'));
+    end if;
+
+    if to_regclass(partition_name || '_default') is null then
+        execute format('create table %I partition of %I default', partition_name || '_default', partition_name);
+    end if;
+
+    for i in 0..months_ahead
+        loop
+            month_start := date_trunc('month', now() at time zone 'UTC') + make_interval(months => i);
+            month_end := month_start + interval '1 month';
+            subpartition_name := format('%s_y%sm%s', partition_name, to_char(month_start, 'YYYY'),
+                                        to_char(month_start, 'MM'));
+            if to_regclass(subpartition_name) is null then
+                begin
+                    execute format(
+                            'create table %I partition of %I for values from (%L) to (%L)',
+                            subpartition_name, partition_name,
+                            month_start at time zone 'UTC', month_end at time zone 'UTC');
+                exception
+                    -- the month is covered by the legacy partition
+                    when invalid_object_definition then
+                        raise notice 'Skipping partition %, overlaps existing partition', subpartition_name;
+                    -- events for the month were already written to the default partition
+                    when check_violation then
+                        raise notice 'Skipping partition %, default partition contains rows', subpartition_name;
+                end;
+            end if;
+        end loop;
+end
+$$ language plpgsql;
+
+-- Existing channel partitions have a single subpartition covering all times. Limit it to the current
+-- month so monthly partitions can be added after it.
+do
+$$
+    declare
+        r          record;
+        legacy_end timestamp with time zone :=
+            (date_trunc('month', now() at time zone 'UTC') + interval '1 month') at time zone 'UTC';
+    begin
+        for r in select c.relname child, p.relname parent
+                 from pg_inherits i
+                          join pg_class c on c.oid = i.inhrelid
+                          join pg_class p on p.oid = i.inhparent
+                 where p.relname ~ '^chat_events_ch\d+$'
+                   and c.relname = p.relname || '_default'
+            loop
+                raise notice 'Converting % to legacy partition', r.child;
+                execute format('alter table %I detach partition %I', r.parent, r.child);
+                execute format('alter table %I rename to %I', r.child, r.parent || '_legacy');
+                execute format('alter table %I attach partition %I for values from (minvalue) to (%L)',
+                               r.parent, r.parent || '_legacy', legacy_end);
+            end loop;
+    end
+$$;
+
+select create_chat_events_partitions(null, 3);
+select create_chat_events_partitions(id, 3)
+from channels;
@@ -0,0 +1,4 @@
+alter table channels
+    drop column archive_logs;
+alter table channels
+    drop column log_retention_days;
@@ -0,0 +1,6 @@
+-- days to keep chat logs, null keeps logs forever
+alter table channels
+    add column log_retention_days integer check (log_retention_days > 0);
+-- export expired logs to files before dropping them
+alter table channels
+    add column archive_logs boolean not null default false;
@@ -0,0 +1,23 @@
+drop index chat_events_notice_kind_idx;
+
+alter table chat_events
+    drop column notice_kind,
+    drop column sub_plan,
+    drop column months,
+    drop column recipient_name,
+    drop column gift_count,
+    drop column viewer_count,
+    drop column bits_threshold,
+    drop column ritual_name;
+
+drop type user_notice_kind;
+
+-- enum values can't be removed, recreate the type without the added values
+delete
+from chat_events
+where event_type in ('join', 'part', 'mode', 'userstate', 'globaluserstate', 'reconnect');
+alter type event_type rename to event_type_old;
+create type event_type as enum ('privmsg', 'whisper', 'notice', 'usernotice', 'host', 'clearchat', 'clearmsg', 'roomstate', 'connect');
+alter table chat_events
+    alter column event_type type event_type using event_type::text::event_type;
+drop type event_type_old;
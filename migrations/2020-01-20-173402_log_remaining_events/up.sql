@@ -0,0 +1,34 @@
+-- recreate the type with the new values, `alter type ... add value` can't run in the migration's
+-- transaction before PostgreSQL 12
+alter type event_type rename to event_type_old;
+create type event_type as enum ('privmsg', 'whisper', 'notice', 'usernotice', 'host', 'clearchat', 'clearmsg', 'roomstate', 'connect',
+    'join', 'part', 'mode', 'userstate', 'globaluserstate', 'reconnect');
+alter table chat_events
+    alter column event_type type event_type using event_type::text::event_type;
+drop type event_type_old;
+
+create type user_notice_kind as enum (
+    'sub', 'resub', 'subgift', 'anonsubgift', 'submysterygift', 'giftpaidupgrade', 'anongiftpaidupgrade',
+    'raid', 'unraid', 'ritual', 'bitsbadgetier', 'other'
+    );
+
+-- structured values of usernotice events, parsed from the msg-id and msg-param-* tags
+alter table chat_events
+    add column notice_kind    user_notice_kind,
+    -- Prime, 1000, 2000 or 3000
+    add column sub_plan       text,
+    -- cumulative months of a resub
+    add column months         integer,
+    -- receiver of a gifted sub
+    add column recipient_name text,
+    -- number of gifted subs of a mystery gift
+    add column gift_count     integer,
+    -- number of raiding viewers
+    add column viewer_count   integer,
+    -- bits badge tier
+    add column bits_threshold integer,
+    add column ritual_name    text;
+
+create index chat_events_notice_kind_idx
+    on chat_events (channel_id, notice_kind, received_at)
+    where notice_kind is not null;
@@ -0,0 +1,2 @@
+drop table channel_alerts;
+drop type alert_kind;
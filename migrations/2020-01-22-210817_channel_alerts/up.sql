@@ -0,0 +1,21 @@
+create type alert_kind as enum ('sub', 'resub', 'subgift', 'giftbomb', 'raid', 'cheer');
+
+create table channel_alerts
+(
+    id           serial primary key,
+    channel_id   integer references channels on delete cascade not null,
+    alert_kind   alert_kind                                    not null,
+    -- the alert applies to events with at least this value: sub tier (prime is 0), resub months, number of
+    -- gifted subs, raiding viewers or cheered bits. The matching alert with the highest threshold is used.
+    min_value    integer,
+    template     text                                          not null,
+    -- minimum time between two messages of this alert in milliseconds
+    min_interval integer,
+    enabled      boolean                                       not null default true,
+    updated_at   timestamp with time zone,
+    created_at   timestamp with time zone                      not null default now()
+);
+
+create index channel_alerts_channel_idx on channel_alerts (channel_id, alert_kind);
+
+select diesel_manage_updated_at('channel_alerts');
@@ -0,0 +1,8 @@
+drop index channels_discord_id_index;
+alter table channels drop column discord_channel_id;
+
+delete from users where twitch_user_id is null;
+alter table users drop constraint users_platform_id_check;
+drop index users_discord_id_index;
+alter table users drop column discord_user_id;
+alter table users alter column twitch_user_id set not null;
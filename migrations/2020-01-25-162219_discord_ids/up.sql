@@ -0,0 +1,9 @@
+-- users and channels can come from Discord, which doesn't have Twitch IDs
+alter table users alter column twitch_user_id drop not null;
+alter table users add column discord_user_id bigint;
+create unique index users_discord_id_index on users (discord_user_id);
+alter table users add constraint users_platform_id_check
+    check (twitch_user_id is not null or discord_user_id is not null);
+
+alter table channels add column discord_channel_id bigint;
+create unique index channels_discord_id_index on channels (discord_channel_id);
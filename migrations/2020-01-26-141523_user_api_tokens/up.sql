@@ -0,0 +1,7 @@
+-- personal API tokens, identify the user of a web backend request. Only SHA-256 hashes are stored.
+create table user_api_tokens (
+    token_hash text primary key,
+    user_id integer not null references users (id) on delete cascade,
+    created_at timestamptz not null default now()
+);
+create index user_api_tokens_user_id_index on user_api_tokens (user_id);
@@ -0,0 +1,48 @@
+create or replace function refresh_chat_stats() returns void as
+$$
+declare
+    since timestamp with time zone;
+    until timestamp with time zone := now();
+begin
+    if not pg_try_advisory_xact_lock(hashtext('refresh_chat_stats')) then
+        raise notice 'Chat stats refresh is already running';
+        return;
+    end if;
+
+    select date_trunc('hour', refreshed_until - interval '1 hour') into since from chat_stats_refresh;
+
+    insert into chat_stats_hourly (channel_id, hour, message_count, chatter_count)
+    select channel_id, date_trunc('hour', received_at), count(*), count(distinct sender_user_id)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and received_at >= since
+    group by 1, 2
+    on conflict (channel_id, hour) do update set message_count = excluded.message_count,
+                                                 chatter_count = excluded.chatter_count;
+
+    insert into chat_stats_user_daily (channel_id, day, user_id, message_count)
+    select channel_id, (received_at at time zone 'UTC')::date, sender_user_id, count(*)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and sender_user_id is not null
+      and received_at >= date_trunc('day', since at time zone 'UTC') at time zone 'UTC'
+    group by 1, 2, 3
+    on conflict (channel_id, day, user_id) do update set message_count = excluded.message_count;
+
+    insert into chat_stats_first_seen (channel_id, user_id, first_day)
+    select channel_id, user_id, min(day)
+    from chat_stats_user_daily
+    where day >= (since at time zone 'UTC')::date
+    group by 1, 2
+    on conflict (channel_id, user_id) do update set first_day = least(chat_stats_first_seen.first_day,
+                                                                      excluded.first_day);
+
+    update chat_stats_refresh set refreshed_until = until;
+end
+$$ language plpgsql;
+
+drop trigger chat_events_mark_late on chat_events;
+drop function mark_late_chat_event();
+drop table chat_stats_late_hours;
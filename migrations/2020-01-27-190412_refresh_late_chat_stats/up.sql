@@ -0,0 +1,110 @@
+-- hours with events that were persisted after the rollups of the hour had been refreshed, e.g. when the
+-- persistence queue was backed up
+create table chat_stats_late_hours
+(
+    channel_id integer references channels on delete cascade not null,
+    hour       timestamp with time zone                      not null,
+    primary key (channel_id, hour)
+);
+
+-- Events received less than half an hour before they are inserted are covered by the hour the refresh
+-- looks back, older ones are marked for the next refresh.
+create function mark_late_chat_event() returns trigger as
+$$
+begin
+    insert into chat_stats_late_hours (channel_id, hour)
+    values (new.channel_id, date_trunc('hour', new.received_at))
+    on conflict do nothing;
+    return null;
+end
+$$ language plpgsql;
+
+create trigger chat_events_mark_late
+    after insert
+    on chat_events
+    for each row
+    when (new.event_type = 'privmsg' and new.channel_id is not null
+        and new.received_at < now() - interval '30 minutes')
+execute procedure mark_late_chat_event();
+
+-- Incrementally refresh the chat statistic rollup tables. The last hour before the previous refresh is
+-- recomputed to include events that were persisted late, daily rollups recompute the whole day. Older
+-- hours that received late events are recomputed as well.
+create or replace function refresh_chat_stats() returns void as
+$$
+declare
+    since timestamp with time zone;
+    until timestamp with time zone := now();
+begin
+    if not pg_try_advisory_xact_lock(hashtext('refresh_chat_stats')) then
+        raise notice 'Chat stats refresh is already running';
+        return;
+    end if;
+
+    select date_trunc('hour', refreshed_until - interval '1 hour') into since from chat_stats_refresh;
+
+    -- hours marked after this point stay for the next refresh
+    create temporary table late_hours on commit drop as
+    with marked as (delete from chat_stats_late_hours returning channel_id, hour)
+    select channel_id, hour
+    from marked
+    where hour < since;
+
+    create temporary table late_days on commit drop as
+    select distinct channel_id, (hour at time zone 'UTC')::date as day
+    from late_hours
+    where hour < date_trunc('day', since at time zone 'UTC') at time zone 'UTC';
+
+    insert into chat_stats_hourly (channel_id, hour, message_count, chatter_count)
+    select channel_id, date_trunc('hour', received_at), count(*), count(distinct sender_user_id)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and received_at >= since
+    group by 1, 2
+    on conflict (channel_id, hour) do update set message_count = excluded.message_count,
+                                                 chatter_count = excluded.chatter_count;
+
+    insert into chat_stats_hourly (channel_id, hour, message_count, chatter_count)
+    select l.channel_id, l.hour, count(*), count(distinct e.sender_user_id)
+    from late_hours l
+             join chat_events e on e.channel_id = l.channel_id
+        and e.received_at >= l.hour and e.received_at < l.hour + interval '1 hour'
+    where e.event_type = 'privmsg'
+    group by 1, 2
+    on conflict (channel_id, hour) do update set message_count = excluded.message_count,
+                                                 chatter_count = excluded.chatter_count;
+
+    insert into chat_stats_user_daily (channel_id, day, user_id, message_count)
+    select channel_id, (received_at at time zone 'UTC')::date, sender_user_id, count(*)
+    from chat_events
+    where event_type = 'privmsg'
+      and channel_id is not null
+      and sender_user_id is not null
+      and received_at >= date_trunc('day', since at time zone 'UTC') at time zone 'UTC'
+    group by 1, 2, 3
+    on conflict (channel_id, day, user_id) do update set message_count = excluded.message_count;
+
+    insert into chat_stats_user_daily (channel_id, day, user_id, message_count)
+    select l.channel_id, l.day, e.sender_user_id, count(*)
+    from late_days l
+             join chat_events e on e.channel_id = l.channel_id
+        and e.received_at >= l.day::timestamp at time zone 'UTC'
+        and e.received_at < (l.day + 1)::timestamp at time zone 'UTC'
+    where e.event_type = 'privmsg'
+      and e.sender_user_id is not null
+    group by 1, 2, 3
+    on conflict (channel_id, day, user_id) do update set message_count = excluded.message_count;
+
+    insert into chat_stats_first_seen (channel_id, user_id, first_day)
+    select d.channel_id, d.user_id, min(d.day)
+    from chat_stats_user_daily d
+    where d.day >= (since at time zone 'UTC')::date
+       or exists(select 1 from late_days l where l.channel_id = d.channel_id and l.day = d.day)
+    group by 1, 2
+    on conflict (channel_id, user_id) do update set first_day = least(chat_stats_first_seen.first_day,
+                                                                      excluded.first_day);
+
+    update chat_stats_refresh set refreshed_until = until;
+end
+$$ language plpgsql;
@@ -0,0 +1,11 @@
+-- changes that weren't made with chat commands, e.g. permissions granted to the admins from the bot config
+create table audit_log (
+    id serial primary key,
+    created_at timestamptz not null default now(),
+    -- user the change was made for
+    user_id integer references users (id) on delete set null,
+    action text not null,
+    -- where the change came from, e.g. "config"
+    source text not null
+);
+create index audit_log_user_id_index on audit_log (user_id);
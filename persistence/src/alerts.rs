@@ -0,0 +1,208 @@
+//! Chat alerts, messages the bot posts in response to subscriptions, gift subs, raids and cheers.
+
+use std::convert::TryInto;
+
+use chrono::{DateTime, Utc};
+use darkredis::{CommandList, Value as RedisValue};
+use diesel::prelude::*;
+use diesel_derive_enum::DbEnum;
+use serde::{Deserialize, Serialize};
+use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
+
+use crate::commands::attributes::DurationMillis;
+use crate::schema::channel_alerts;
+use crate::Result;
+use crate::{DbPool, Error, RedisPool};
+
+#[derive(DbEnum, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertKind {
+    /// New subscription, the value is the sub tier (0 for prime)
+    Sub,
+    /// Resubscription, the value is the cumulative months
+    Resub,
+    /// Single gifted subscription, the value is the sub tier
+    Subgift,
+    /// Multiple gifted subscriptions, the value is the number of gifts
+    Giftbomb,
+    /// Incoming raid, the value is the number of viewers
+    Raid,
+    /// Bits cheered in a message, the value is the number of bits
+    Cheer,
+}
+
+#[derive(Queryable, Serialize, Deserialize, Debug, Clone)]
+pub struct ChannelAlert {
+    pub id: i32,
+    pub channel_id: i32,
+    pub alert_kind: AlertKind,
+    /// the alert applies to events with at least this value
+    pub min_value: Option<i32>,
+    pub template: String,
+    /// minimum time between two messages of this alert
+    pub min_interval: Option<DurationMillis>,
+    pub enabled: bool,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Insertable, Debug, Clone)]
+#[table_name = "channel_alerts"]
+pub struct InsertChannelAlert {
+    pub channel_id: i32,
+    pub alert_kind: AlertKind,
+    pub min_value: Option<i32>,
+    pub template: String,
+    /// minimum time between two messages of this alert in milliseconds
+    pub min_interval: Option<i32>,
+    pub enabled: bool,
+}
+
+fn interval_cache_key(alert_id: i32) -> String {
+    format!("cb:cooldowns:alert:{}", alert_id)
+}
+
+fn gift_bomb_key(channel_id: i32, gifter: &str) -> String {
+    format!("cb:alerts:giftbomb:{}:{}", channel_id, gifter)
+}
+
+/// Time the individual gifts of a gift bomb are expected to arrive in
+const GIFT_BOMB_SECONDS: u32 = 60;
+
+/// Counts down the remaining gifts of a gift bomb, returns 1 if a gift was part of one
+const TAKE_GIFT_SCRIPT: &str = r#"
+local remaining = redis.call('DECR', KEYS[1])
+if remaining < 0 then
+    redis.call('DEL', KEYS[1])
+    return 0
+end
+return 1
+"#;
+
+/// Remember that a user gifted multiple subs at once. Twitch sends a separate gift event for each
+/// of the subs following the gift bomb, those don't get their own alert.
+pub async fn start_gift_bomb(
+    pool: &RedisPool,
+    channel_id: i32,
+    gifter: &str,
+    count: i32,
+) -> Result<()> {
+    pool.get()
+        .await
+        .set_and_expire_seconds(
+            gift_bomb_key(channel_id, gifter),
+            count.to_string(),
+            GIFT_BOMB_SECONDS,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Check whether a single gifted sub belongs to a previous gift bomb of the same user
+pub async fn take_gift_bomb_gift(pool: &RedisPool, channel_id: i32, gifter: &str) -> Result<bool> {
+    let key = gift_bomb_key(channel_id, gifter);
+    let command = CommandList::new("EVAL")
+        .arg(&TAKE_GIFT_SCRIPT)
+        .arg(b"1")
+        .arg(&key);
+    let result = pool.get().await.run_commands(command).await?;
+    if let Some(RedisValue::Integer(1)) = result.into_iter().next() {
+        Ok(true)
+    } else {
+        Ok(false)
+    }
+}
+
+impl ChannelAlert {
+    /// All alerts of all channels, used to load the templates
+    pub async fn all(pool: &DbPool) -> Result<Vec<ChannelAlert>> {
+        channel_alerts::table
+            .order(channel_alerts::id.asc())
+            .load_async(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn list_for_channel(pool: &DbPool, channel_id: i32) -> Result<Vec<ChannelAlert>> {
+        channel_alerts::table
+            .filter(channel_alerts::channel_id.eq(channel_id))
+            .order((
+                channel_alerts::alert_kind.asc(),
+                channel_alerts::min_value.asc(),
+            ))
+            .load_async(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Find the enabled alert of a kind with the highest threshold the event value reaches
+    pub async fn get_matching(
+        pool: &DbPool,
+        channel_id: i32,
+        kind: AlertKind,
+        value: i32,
+    ) -> Result<Option<ChannelAlert>> {
+        channel_alerts::table
+            .filter(channel_alerts::channel_id.eq(channel_id))
+            .filter(channel_alerts::alert_kind.eq(kind))
+            .filter(channel_alerts::enabled.eq(true))
+            .filter(
+                channel_alerts::min_value
+                    .is_null()
+                    .or(channel_alerts::min_value.le(value)),
+            )
+            .order(channel_alerts::min_value.desc().nulls_last())
+            .first_async(pool)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    pub async fn create(pool: &DbPool, alert: InsertChannelAlert) -> Result<ChannelAlert> {
+        diesel::insert_into(channel_alerts::table)
+            .values(alert)
+            .get_result_async(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Delete an alert of a channel. Returns false if it doesn't exist.
+    pub async fn delete(pool: &DbPool, channel_id: i32, alert_id: i32) -> Result<bool> {
+        let deleted = diesel::delete(
+            channel_alerts::table
+                .filter(channel_alerts::channel_id.eq(channel_id))
+                .filter(channel_alerts::id.eq(alert_id)),
+        )
+        .execute_async(pool)
+        .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Check whether the alert may be posted and start its minimum interval if it may.
+    /// Check and set happen atomically so concurrent events don't both post the alert.
+    pub async fn claim_interval(&self, pool: &RedisPool) -> Result<bool> {
+        if let Some(interval) = &self.min_interval {
+            let millis: u32 = interval
+                .as_millis()
+                .try_into()
+                .map_err(Error::InvalidRedisExpiry)?;
+            let key = interval_cache_key(self.id);
+            let millis = millis.to_string();
+            let command = CommandList::new("SET")
+                .arg(&key)
+                .arg(b"1")
+                .arg(b"NX")
+                .arg(b"PX")
+                .arg(&millis);
+            let result = pool.get().await.run_commands(command).await?;
+            // SET NX replies nil if the key already exists
+            if let Some(RedisValue::Ok) = result.into_iter().next() {
+                Ok(true)
+            } else {
+                Ok(false)
+            }
+        } else {
+            Ok(true)
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Personal API tokens. The web backend identifies the user of a request by their token, so
+//! permission checks don't depend on IDs sent by the client. Only SHA-256 hashes of the tokens
+//! are stored.
+
+use diesel::sql_query;
+use diesel::sql_types::{Integer, Text};
+use tokio_diesel::AsyncRunQueryDsl;
+
+use crate::{DbPool, Result};
+
+/// Hash of the token in the first bind parameter, as stored in `user_api_tokens.token_hash`
+const TOKEN_HASH: &str = "encode(sha256(convert_to($1, 'UTF8')), 'hex')";
+
+#[derive(QueryableByName, Debug)]
+struct TokenOwner {
+    #[sql_type = "Integer"]
+    user_id: i32,
+}
+
+pub struct UserApiToken;
+
+impl UserApiToken {
+    /// Store a new token for a user. The token itself has to be random and is not stored.
+    pub async fn create(pool: &DbPool, user_id: i32, token: &str) -> Result<()> {
+        sql_query(format!(
+            "insert into user_api_tokens (token_hash, user_id) values ({}, $2)",
+            TOKEN_HASH
+        ))
+        .bind::<Text, _>(token.to_string())
+        .bind::<Integer, _>(user_id)
+        .execute_async(pool)
+        .await?;
+        Ok(())
+    }
+
+    /// ID of the user owning a token, `None` for unknown tokens
+    pub async fn user_id(pool: &DbPool, token: &str) -> Result<Option<i32>> {
+        let owners = sql_query(format!(
+            "select user_id from user_api_tokens where token_hash = {}",
+            TOKEN_HASH
+        ))
+        .bind::<Text, _>(token.to_string())
+        .load_async::<TokenOwner>(pool)
+        .await?;
+        Ok(owners.into_iter().next().map(|owner| owner.user_id))
+    }
+
+    /// Delete all tokens of a user, returns the number of deleted tokens
+    pub async fn revoke_all(pool: &DbPool, user_id: i32) -> Result<usize> {
+        sql_query("delete from user_api_tokens where user_id = $1")
+            .bind::<Integer, _>(user_id)
+            .execute_async(pool)
+            .await
+            .map_err(Into::into)
+    }
+}
@@ -0,0 +1,25 @@
+//! Audit log of changes that weren't made with chat commands, e.g. permissions granted to the
+//! admins from the bot config.
+
+use diesel::prelude::*;
+use tokio_diesel::AsyncRunQueryDsl;
+
+use crate::schema::audit_log;
+use crate::{DbPool, Result};
+
+pub struct AuditLog;
+
+impl AuditLog {
+    /// Add an entry for a change made for a user. `source` is where the change came from.
+    pub async fn add(pool: &DbPool, user_id: i32, action: &str, source: &str) -> Result<()> {
+        diesel::insert_into(audit_log::table)
+            .values((
+                audit_log::user_id.eq(user_id),
+                audit_log::action.eq(action.to_owned()),
+                audit_log::source.eq(source.to_owned()),
+            ))
+            .execute_async(pool)
+            .await?;
+        Ok(())
+    }
+}
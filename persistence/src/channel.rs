@@ -5,6 +5,7 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
 
+use crate::partitions::create_channel_partitions;
 use crate::schema::channels;
 use crate::DbContext;
 use crate::DbPool;
@@ -20,6 +21,12 @@ pub struct Channel {
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub silent: bool,
+    /// days to keep chat logs, `None` keeps logs forever
+    pub log_retention_days: Option<i32>,
+    /// whether expired chat logs are exported to files before they are dropped
+    pub archive_logs: bool,
+    /// set for channels on Discord instead of Twitch
+    pub discord_channel_id: Option<i64>,
 }
 
 #[derive(Insertable, AsChangeset, Clone, Debug)]
@@ -36,6 +43,9 @@ pub struct UpdateChannelSettings {
     #[allow(clippy::option_option)]
     pub command_prefix: Option<Option<String>>,
     pub silent: Option<bool>,
+    #[allow(clippy::option_option)]
+    pub log_retention_days: Option<Option<i32>>,
+    pub archive_logs: Option<bool>,
 }
 
 #[derive(Insertable, Debug)]
@@ -46,6 +56,8 @@ pub struct InsertChannel {
     pub join_on_start: Option<bool>,
     pub command_prefix: Option<String>,
     pub silent: Option<bool>,
+    pub log_retention_days: Option<i32>,
+    pub archive_logs: Option<bool>,
 }
 
 impl Channel {
@@ -60,6 +72,24 @@ impl Channel {
             .map_err(Into::into)
     }
 
+    /// Get all channels ordered by name
+    pub async fn all(pool: &DbPool) -> Result<Vec<Channel>> {
+        channels::table
+            .order(channels::name)
+            .load_async::<Channel>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get a channel by its ID
+    pub async fn get_by_id(pool: &DbPool, channel_id: i32) -> Result<Channel> {
+        channels::table
+            .find(channel_id)
+            .first_async::<Channel>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
     /// Get a channel by the information received with the roomstate event or update the channel in
     /// the database. Inserts if not found, updates the Twitch room ID if not set in the database.
     pub async fn get_or_persist_roomstate(
@@ -84,10 +114,47 @@ impl Channel {
                 .values(channel_values)
                 .get_result_async::<Channel>(&ctx.db_pool)
                 .await?;
+            create_channel_partitions(&ctx.db_pool, Some(inserted_channel.id)).await?;
             Ok(inserted_channel)
         }
     }
 
+    /// Get the channel for a Discord guild channel by its ID, inserts it with the given command
+    /// prefix if not found. Updates the name if it changed, e.g. for channels added before the
+    /// names were based on IDs.
+    pub async fn get_or_persist_discord(
+        ctx: &DbContext,
+        discord_channel_id: i64,
+        name: &str,
+        command_prefix: &str,
+    ) -> Result<Channel> {
+        let channel = channels::table
+            .filter(channels::discord_channel_id.eq(discord_channel_id))
+            .first_async::<Channel>(&ctx.db_pool)
+            .await
+            .optional()?;
+        match channel {
+            Some(channel) if channel.name == name => Ok(channel),
+            Some(channel) => diesel::update(channels::table.find(channel.id))
+                .set(channels::name.eq(name.to_owned()))
+                .get_result_async(&ctx.db_pool)
+                .await
+                .map_err(Into::into),
+            None => {
+                let inserted_channel = diesel::insert_into(channels::table)
+                    .values((
+                        channels::discord_channel_id.eq(discord_channel_id),
+                        channels::name.eq(name.to_owned()),
+                        channels::command_prefix.eq(command_prefix.to_owned()),
+                    ))
+                    .get_result_async::<Channel>(&ctx.db_pool)
+                    .await?;
+                create_channel_partitions(&ctx.db_pool, Some(inserted_channel.id)).await?;
+                Ok(inserted_channel)
+            }
+        }
+    }
+
     /// Update a channel's settings
     pub async fn update_settings(
         ctx: &DbContext,
@@ -111,6 +178,7 @@ impl Channel {
             .values(values)
             .get_result_async::<Channel>(&ctx.db_pool)
             .await?;
+        create_channel_partitions(&ctx.db_pool, Some(inserted_channel.id)).await?;
 
         Ok(inserted_channel)
     }
@@ -118,6 +186,8 @@ impl Channel {
     pub async fn get_startup_channels(pool: &DbPool) -> Result<Vec<Channel>> {
         channels::table
             .filter(channels::join_on_start.eq(true))
+            // Discord channels are always joined
+            .filter(channels::discord_channel_id.is_null())
             .load_async::<Channel>(pool)
             .await
             .map_err(Into::into)
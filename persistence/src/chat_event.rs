@@ -4,21 +4,23 @@ use std::ops::Deref;
 use chrono::{DateTime, FixedOffset, Utc};
 use darkredis::{CommandList, Value as RedisValue};
 use diesel::deserialize::FromSql;
+use diesel::dsl::sql;
 use diesel::pg::Pg;
+use diesel::prelude::*;
 use diesel::serialize::{Output, ToSql};
-use diesel::sql_types::Jsonb;
+use diesel::sql_types::{BigInt, Bool, Jsonb, Text, Timestamptz};
 use diesel_derive_enum::DbEnum;
 use fnv::FnvHashMap;
 use serde::{Deserialize, Serialize};
-use tokio_diesel::AsyncRunQueryDsl;
+use tokio_diesel::{AsyncConnection, AsyncRunQueryDsl};
 
-use crate::impl_redis_bincode_int;
 use crate::redis_values::*;
 use crate::schema::chat_events;
-use crate::DbContext;
 use crate::Result;
+use crate::{DbContext, DbPool};
 
 #[derive(DbEnum, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
 pub enum ChatEventType {
     Privmsg,
     Whisper,
@@ -29,9 +31,61 @@ pub enum ChatEventType {
     Clearmsg,
     Roomstate,
     Connect,
+    Join,
+    Part,
+    Mode,
+    Userstate,
+    Globaluserstate,
+    Reconnect,
 }
 
-#[derive(Queryable)]
+impl ChatEventType {
+    /// Name of the event type as used in the database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatEventType::Privmsg => "privmsg",
+            ChatEventType::Whisper => "whisper",
+            ChatEventType::Notice => "notice",
+            ChatEventType::Usernotice => "usernotice",
+            ChatEventType::Host => "host",
+            ChatEventType::Clearchat => "clearchat",
+            ChatEventType::Clearmsg => "clearmsg",
+            ChatEventType::Roomstate => "roomstate",
+            ChatEventType::Connect => "connect",
+            ChatEventType::Join => "join",
+            ChatEventType::Part => "part",
+            ChatEventType::Mode => "mode",
+            ChatEventType::Userstate => "userstate",
+            ChatEventType::Globaluserstate => "globaluserstate",
+            ChatEventType::Reconnect => "reconnect",
+        }
+    }
+
+    /// Get an event type by its database name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "privmsg" => ChatEventType::Privmsg,
+            "whisper" => ChatEventType::Whisper,
+            "notice" => ChatEventType::Notice,
+            "usernotice" => ChatEventType::Usernotice,
+            "host" => ChatEventType::Host,
+            "clearchat" => ChatEventType::Clearchat,
+            "clearmsg" => ChatEventType::Clearmsg,
+            "roomstate" => ChatEventType::Roomstate,
+            "connect" => ChatEventType::Connect,
+            "join" => ChatEventType::Join,
+            "part" => ChatEventType::Part,
+            "mode" => ChatEventType::Mode,
+            "userstate" => ChatEventType::Userstate,
+            "globaluserstate" => ChatEventType::Globaluserstate,
+            "reconnect" => ChatEventType::Reconnect,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Queryable, QueryableByName, Serialize, Debug)]
+#[table_name = "chat_events"]
 pub struct ChatEvent {
     pub id: i64,
     pub event_type: ChatEventType,
@@ -41,6 +95,14 @@ pub struct ChatEvent {
     pub sender_user_id: Option<i32>,
     pub tags: Option<Tags>,
     pub received_at: DateTime<Utc>,
+    pub notice_kind: Option<UserNoticeKind>,
+    pub sub_plan: Option<String>,
+    pub months: Option<i32>,
+    pub recipient_name: Option<String>,
+    pub gift_count: Option<i32>,
+    pub viewer_count: Option<i32>,
+    pub bits_threshold: Option<i32>,
+    pub ritual_name: Option<String>,
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug, PartialEq)]
@@ -53,9 +115,218 @@ pub struct NewChatEvent {
     pub sender_user_id: Option<i32>,
     pub tags: Option<Tags>,
     pub received_at: DateTime<FixedOffset>,
+    #[diesel(embed)]
+    pub user_notice: UserNotice,
 }
 
-impl_redis_bincode_int!(NewChatEvent);
+/// Kind of a usernotice event, from the `msg-id` tag
+#[derive(DbEnum, Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum UserNoticeKind {
+    Sub,
+    Resub,
+    Subgift,
+    Anonsubgift,
+    Submysterygift,
+    Giftpaidupgrade,
+    Anongiftpaidupgrade,
+    Raid,
+    Unraid,
+    Ritual,
+    Bitsbadgetier,
+    Other,
+}
+
+/// Structured values of usernotice events. Empty for all other events.
+#[derive(Insertable, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[table_name = "chat_events"]
+pub struct UserNotice {
+    pub notice_kind: Option<UserNoticeKind>,
+    pub sub_plan: Option<String>,
+    pub months: Option<i32>,
+    pub recipient_name: Option<String>,
+    pub gift_count: Option<i32>,
+    pub viewer_count: Option<i32>,
+    pub bits_threshold: Option<i32>,
+    pub ritual_name: Option<String>,
+}
+
+impl UserNotice {
+    /// Parse the `msg-id` and `msg-param-*` tags of a usernotice event
+    pub fn from_tags(tags: &FnvHashMap<String, String>) -> UserNotice {
+        let text = |name: &str| tags.get(name).filter(|value| !value.is_empty()).cloned();
+        let number = |name: &str| tags.get(name).and_then(|value| value.parse().ok());
+
+        let notice_kind = tags.get("msg-id").map(|id| match id.as_str() {
+            "sub" => UserNoticeKind::Sub,
+            "resub" => UserNoticeKind::Resub,
+            "subgift" => UserNoticeKind::Subgift,
+            "anonsubgift" => UserNoticeKind::Anonsubgift,
+            "submysterygift" => UserNoticeKind::Submysterygift,
+            "giftpaidupgrade" => UserNoticeKind::Giftpaidupgrade,
+            "anongiftpaidupgrade" => UserNoticeKind::Anongiftpaidupgrade,
+            "raid" => UserNoticeKind::Raid,
+            "unraid" => UserNoticeKind::Unraid,
+            "ritual" => UserNoticeKind::Ritual,
+            "bitsbadgetier" => UserNoticeKind::Bitsbadgetier,
+            _ => UserNoticeKind::Other,
+        });
+
+        UserNotice {
+            notice_kind,
+            sub_plan: text("msg-param-sub-plan"),
+            months: number("msg-param-cumulative-months").or_else(|| number("msg-param-months")),
+            recipient_name: text("msg-param-recipient-display-name")
+                .or_else(|| text("msg-param-recipient-user-name")),
+            gift_count: number("msg-param-mass-gift-count"),
+            viewer_count: number("msg-param-viewerCount"),
+            bits_threshold: number("msg-param-threshold"),
+            ritual_name: text("msg-param-ritual-name"),
+        }
+    }
+}
+
+/// Prefix of queued events in the current format. Events queued by earlier versions of the bot
+/// have no prefix, they start with the index of the event type followed by zero bytes.
+const QUEUE_FORMAT_PREFIX: &[u8] = b"cbe2";
+
+/// Queued event format before usernotice values were added, still decoded to persist events
+/// queued before an update
+#[derive(Serialize, Deserialize)]
+struct NewChatEventV1 {
+    event_type: ChatEventType,
+    twitch_message_id: Option<uuid::Uuid>,
+    message: Option<String>,
+    channel_id: Option<i32>,
+    sender_user_id: Option<i32>,
+    tags: Option<Tags>,
+    received_at: DateTime<FixedOffset>,
+}
+
+impl ToRedisValue for NewChatEvent {
+    fn to_redis(&self) -> Result<Vec<u8>> {
+        let mut value = QUEUE_FORMAT_PREFIX.to_vec();
+        bincode::serialize_into(&mut value, self)?;
+        Ok(value)
+    }
+}
+
+impl FromRedisValue for NewChatEvent {
+    fn from_redis(value: &[u8]) -> Result<Self> {
+        if value.starts_with(QUEUE_FORMAT_PREFIX) {
+            return bincode::deserialize(&value[QUEUE_FORMAT_PREFIX.len()..]).map_err(Into::into);
+        }
+        let event: NewChatEventV1 = bincode::deserialize(value)?;
+        let user_notice = match (event.event_type, &event.tags) {
+            (ChatEventType::Usernotice, Some(tags)) => UserNotice::from_tags(tags),
+            _ => UserNotice::default(),
+        };
+        Ok(NewChatEvent {
+            event_type: event.event_type,
+            twitch_message_id: event.twitch_message_id,
+            message: event.message,
+            channel_id: event.channel_id,
+            sender_user_id: event.sender_user_id,
+            tags: event.tags,
+            received_at: event.received_at,
+            user_notice,
+        })
+    }
+}
+
+/// Filters for searching the chat log of a channel
+#[derive(Debug, Clone, Default)]
+pub struct ChatLogFilter {
+    /// only include events received at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// only include events received before this time
+    pub to: Option<DateTime<Utc>>,
+    pub sender_user_id: Option<i32>,
+    /// only include these event types, all types if empty
+    pub event_types: Vec<ChatEventType>,
+    /// full text search query matched against the message text
+    pub search: Option<String>,
+}
+
+/// Position in a chat log, follows the `(received_at, id)` order of the partitions' unique index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChatLogCursor {
+    pub received_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl From<&ChatEvent> for ChatLogCursor {
+    fn from(event: &ChatEvent) -> Self {
+        ChatLogCursor {
+            received_at: event.received_at,
+            id: event.id,
+        }
+    }
+}
+
+impl ChatEvent {
+    /// Search the chat log of a channel in chronological order. Returns at most `limit` events
+    /// following the `after` cursor.
+    pub async fn search(
+        pool: &DbPool,
+        channel_id: i32,
+        filter: ChatLogFilter,
+        after: Option<ChatLogCursor>,
+        limit: u32,
+    ) -> Result<Vec<ChatEvent>> {
+        pool.run(move |conn| search_query(channel_id, filter, after, limit).load::<ChatEvent>(conn))
+            .await
+            .map_err(Into::into)
+    }
+}
+
+/// Query of `ChatEvent::search`, ordered by `(received_at, id)` to page with the cursor
+fn search_query(
+    channel_id: i32,
+    filter: ChatLogFilter,
+    after: Option<ChatLogCursor>,
+    limit: u32,
+) -> chat_events::BoxedQuery<'static, Pg> {
+    let mut query = chat_events::table
+        .filter(chat_events::channel_id.eq(channel_id))
+        .order((chat_events::received_at.asc(), chat_events::id.asc()))
+        .limit(i64::from(limit))
+        .into_boxed();
+
+    if let Some(from) = filter.from {
+        query = query.filter(chat_events::received_at.ge(from));
+    }
+    if let Some(to) = filter.to {
+        query = query.filter(chat_events::received_at.lt(to));
+    }
+    if let Some(sender_user_id) = filter.sender_user_id {
+        query = query.filter(chat_events::sender_user_id.eq(sender_user_id));
+    }
+    if !filter.event_types.is_empty() {
+        query = query.filter(chat_events::event_type.eq_any(filter.event_types));
+    }
+    if let Some(search) = filter.search {
+        // uses the same expression as the full text index on chat_events
+        query = query.filter(
+            sql::<Bool>(
+                "to_tsvector('simple', coalesce(message, '')) @@ plainto_tsquery('simple', ",
+            )
+            .bind::<Text, _>(search)
+            .sql(")"),
+        );
+    }
+    if let Some(cursor) = after {
+        query = query.filter(
+            sql::<Bool>("(received_at, id) > (")
+                .bind::<Timestamptz, _>(cursor.received_at)
+                .sql(", ")
+                .bind::<BigInt, _>(cursor.id)
+                .sql(")"),
+        );
+    }
+
+    query
+}
 
 /// Convert any chat event into a db entry and save the db entry in the log queue, to
 /// be persisted into the database at a later time
@@ -69,39 +340,255 @@ pub async fn log_event(ctx: &DbContext, event: NewChatEvent) -> Result<()> {
         .map_err(Into::into)
 }
 
-/// Get all queued log events from redis and save them to the database in a batch
-pub async fn persist_event_queue(ctx: &DbContext) -> Result<()> {
-    let queued_events: Vec<NewChatEvent> = {
-        let commands = CommandList::new("LRANGE")
-            .arg(b"cb:persist_event_queue")
-            .arg(b"0")
-            .arg(b"-1")
-            .command("DEL")
-            .arg(b"cb:persist_event_queue");
+/// Events that were taken from the queue but not yet persisted. Retried until the insert succeeds.
+const PROCESSING_QUEUE_KEY: &[u8] = b"cb:persist_event_queue:processing";
+/// Queued values that could not be decoded or were rejected by the database
+const DEAD_LETTER_KEY: &[u8] = b"cb:persist_event_queue:dead";
+/// Hash of persistence counters
+const QUEUE_STATS_KEY: &[u8] = b"cb:persist_event_queue:stats";
 
-        let response = ctx.redis_pool.get().await.run_commands(commands).await?;
-        if let Some(RedisValue::Array(arr)) = response.get(0) {
-            let mut events = vec![];
-            for value in arr.iter() {
-                if let RedisValue::String(bytes) = value {
-                    events.push(NewChatEvent::from_redis(bytes)?)
+/// Maximum number of events inserted at once
+pub const PERSIST_BATCH_SIZE: usize = 1000;
+
+/// Failed inserts of a batch after which its events are inserted one by one, so that events
+/// rejected by the database can't block the queue
+const MAX_BATCH_ATTEMPTS: i64 = 5;
+
+/// Returns the events left in the processing list by a failed attempt. If there are none, moves
+/// a batch from the queue to the processing list and returns it.
+const TAKE_BATCH_SCRIPT: &str = r#"
+local pending = redis.call('LRANGE', KEYS[2], 0, -1)
+if #pending > 0 then
+    return pending
+end
+local batch = redis.call('LRANGE', KEYS[1], 0, tonumber(ARGV[1]) - 1)
+if #batch > 0 then
+    redis.call('RPUSH', KEYS[2], unpack(batch))
+    redis.call('LTRIM', KEYS[1], #batch, -1)
+end
+return batch
+"#;
+
+/// Save a batch of queued log events to the database. Events are moved to a processing list first
+/// and only removed from it after they were inserted, failed batches are retried on the next call.
+/// Failed attempts are counted in the stats hash. Once a batch failed `MAX_BATCH_ATTEMPTS` times,
+/// its events are inserted one by one and events rejected by the database are moved to the dead
+/// letter list. Events may be inserted twice if acknowledging the batch fails after the insert.
+///
+/// Returns the number of persisted events.
+pub async fn persist_event_queue(ctx: &DbContext) -> Result<usize> {
+    let batch_size = PERSIST_BATCH_SIZE.to_string();
+    let commands = CommandList::new("EVAL")
+        .arg(&TAKE_BATCH_SCRIPT)
+        .arg(b"2")
+        .arg(b"cb:persist_event_queue")
+        .arg(&PROCESSING_QUEUE_KEY)
+        .arg(&batch_size)
+        .command("HGET")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"batch_attempts");
+    let mut response = ctx
+        .redis_pool
+        .get()
+        .await
+        .run_commands(commands)
+        .await?
+        .into_iter();
+    let values = match response.next() {
+        Some(RedisValue::Array(values)) if !values.is_empty() => values,
+        _ => return Ok(0),
+    };
+    let attempts = match response.next() {
+        Some(RedisValue::String(bytes)) => std::str::from_utf8(&bytes)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut events = Vec::with_capacity(values.len());
+    let mut encoded = Vec::with_capacity(values.len());
+    let mut dead_letters = vec![];
+    for value in values {
+        if let RedisValue::String(bytes) = value {
+            match NewChatEvent::from_redis(&bytes) {
+                Ok(event) => {
+                    events.push(event);
+                    encoded.push(bytes);
+                }
+                Err(err) => {
+                    error!("Moving undecodable queued event to dead letter list: {}", err);
+                    dead_letters.push(bytes);
+                }
+            }
+        }
+    }
+
+    let persisted = if attempts < MAX_BATCH_ATTEMPTS {
+        let persisted = events.len();
+        if let Err(err) = diesel::insert_into(chat_events::table)
+            .values(events)
+            .execute_async(&ctx.db_pool)
+            .await
+        {
+            return Err(record_failed_attempt(ctx, err.into()).await);
+        }
+        persisted
+    } else {
+        warn!(
+            "Persisting a batch of chat events failed {} times, inserting them one by one",
+            attempts
+        );
+        let mut persisted = 0;
+        for (event, bytes) in events.into_iter().zip(encoded) {
+            match diesel::insert_into(chat_events::table)
+                .values(event)
+                .execute_async(&ctx.db_pool)
+                .await
+                .map_err(crate::Error::from)
+            {
+                Ok(_) => persisted += 1,
+                Err(err) if is_rejected(&err) => {
+                    error!(
+                        "Moving chat event rejected by the database to dead letter list: {}",
+                        err
+                    );
+                    dead_letters.push(bytes);
                 }
+                Err(err) => return Err(record_failed_attempt(ctx, err).await),
             }
-            events
-        } else {
-            vec![]
         }
+        persisted
     };
 
-    diesel::insert_into(chat_events::table)
-        .values(queued_events)
-        .execute_async(&ctx.db_pool)
-        .await?;
+    // acknowledge the batch
+    let persisted_count = persisted.to_string();
+    let dead_lettered_count = dead_letters.len().to_string();
+    let mut commands = CommandList::new("DEL")
+        .arg(&PROCESSING_QUEUE_KEY)
+        .command("HDEL")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"batch_attempts")
+        .command("HINCRBY")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"persisted")
+        .arg(&persisted_count);
+    if !dead_letters.is_empty() {
+        commands = commands
+            .command("RPUSH")
+            .arg(&DEAD_LETTER_KEY)
+            .args(&dead_letters)
+            .command("HINCRBY")
+            .arg(&QUEUE_STATS_KEY)
+            .arg(b"dead_lettered")
+            .arg(&dead_lettered_count);
+    }
+    ctx.redis_pool.get().await.run_commands(commands).await?;
+
+    Ok(persisted)
+}
+
+/// Count a failed insert of the current batch. Returns the insert error, or the redis error if
+/// counting failed.
+async fn record_failed_attempt(ctx: &DbContext, err: crate::Error) -> crate::Error {
+    let error_message = err.to_string();
+    let commands = CommandList::new("HINCRBY")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"failed_batches")
+        .arg(b"1")
+        .command("HINCRBY")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"batch_attempts")
+        .arg(b"1")
+        .command("HSET")
+        .arg(&QUEUE_STATS_KEY)
+        .arg(b"last_error")
+        .arg(&error_message);
+    match ctx.redis_pool.get().await.run_commands(commands).await {
+        Ok(_) => err,
+        Err(redis_err) => redis_err.into(),
+    }
+}
+
+/// Whether an insert failed because the database rejected the event, as opposed to connection
+/// problems that affect all events
+fn is_rejected(err: &crate::Error) -> bool {
+    match err {
+        crate::Error::Conflict => true,
+        crate::Error::AsyncDiesel(tokio_diesel::AsyncError::Error(
+            diesel::result::Error::DatabaseError(..),
+        )) => true,
+        _ => false,
+    }
+}
+
+/// Depth and counters of the event persistence queue
+#[derive(Debug, Clone, Default)]
+pub struct QueueStats {
+    /// events waiting to be persisted
+    pub queued: isize,
+    /// events of a batch that is being persisted or waiting for a retry
+    pub processing: isize,
+    /// undecodable or rejected events in the dead letter list
+    pub dead_lettered: isize,
+    /// events persisted since the counters were created
+    pub persisted_total: i64,
+    /// failed batch inserts since the counters were created
+    pub failed_batches: i64,
+    /// failed attempts to insert the batch that is currently processed
+    pub batch_attempts: i64,
+    /// error of the last failed batch insert
+    pub last_error: Option<String>,
+}
+
+impl QueueStats {
+    pub async fn get(ctx: &DbContext) -> Result<QueueStats> {
+        let commands = CommandList::new("LLEN")
+            .arg(b"cb:persist_event_queue")
+            .command("LLEN")
+            .arg(&PROCESSING_QUEUE_KEY)
+            .command("LLEN")
+            .arg(&DEAD_LETTER_KEY)
+            .command("HMGET")
+            .arg(&QUEUE_STATS_KEY)
+            .arg(b"persisted")
+            .arg(b"failed_batches")
+            .arg(b"batch_attempts")
+            .arg(b"last_error");
+        let response = ctx.redis_pool.get().await.run_commands(commands).await?;
 
-    Ok(())
+        let length = |index: usize| match response.get(index) {
+            Some(RedisValue::Integer(len)) => *len,
+            _ => 0,
+        };
+        let counters = match response.get(3) {
+            Some(RedisValue::Array(values)) => values.as_slice(),
+            _ => &[],
+        };
+        let counter = |index: usize| match counters.get(index) {
+            Some(RedisValue::String(bytes)) => std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0),
+            _ => 0,
+        };
+
+        Ok(QueueStats {
+            queued: length(0),
+            processing: length(1),
+            dead_lettered: length(2),
+            persisted_total: counter(0),
+            failed_batches: counter(1),
+            batch_attempts: counter(2),
+            last_error: match counters.get(3) {
+                Some(RedisValue::String(bytes)) => Some(String::from_utf8_lossy(bytes).into_owned()),
+                _ => None,
+            },
+        })
+    }
 }
 
-#[derive(FromSqlRow, AsExpression, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(FromSqlRow, AsExpression, Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[sql_type = "Jsonb"]
 pub struct Tags(FnvHashMap<String, String>);
 
@@ -140,3 +627,112 @@ impl ToSql<Jsonb, Pg> for Tags {
         <serde_json::Value as ToSql<Jsonb, Pg>>::to_sql(&value, out)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+    use diesel::debug_query;
+
+    use super::*;
+
+    fn usernotice_tags() -> FnvHashMap<String, String> {
+        let mut tags = FnvHashMap::default();
+        tags.insert("msg-id".to_string(), "resub".to_string());
+        tags.insert("msg-param-cumulative-months".to_string(), "7".to_string());
+        tags.insert("msg-param-sub-plan".to_string(), "1000".to_string());
+        tags
+    }
+
+    fn new_usernotice() -> NewChatEvent {
+        let tags = usernotice_tags();
+        NewChatEvent {
+            event_type: ChatEventType::Usernotice,
+            twitch_message_id: None,
+            message: Some("hello".to_string()),
+            channel_id: Some(1),
+            sender_user_id: Some(2),
+            user_notice: UserNotice::from_tags(&tags),
+            tags: Some(tags.into()),
+            received_at: FixedOffset::east(0).ymd(2020, 1, 20).and_hms(17, 34, 2),
+        }
+    }
+
+    #[test]
+    fn test_queue_format_roundtrip() {
+        let event = new_usernotice();
+        let value = event.to_redis().unwrap();
+        assert!(value.starts_with(QUEUE_FORMAT_PREFIX));
+        assert_eq!(NewChatEvent::from_redis(&value).unwrap(), event);
+    }
+
+    #[test]
+    fn test_queue_format_v1() {
+        let event = new_usernotice();
+        let legacy = NewChatEventV1 {
+            event_type: ChatEventType::Usernotice,
+            twitch_message_id: None,
+            message: Some("hello".to_string()),
+            channel_id: Some(1),
+            sender_user_id: Some(2),
+            tags: Some(usernotice_tags().into()),
+            received_at: event.received_at,
+        };
+        let value = bincode::serialize(&legacy).unwrap();
+        let decoded = NewChatEvent::from_redis(&value).unwrap();
+        assert_eq!(decoded.user_notice.months, Some(7));
+        assert_eq!(decoded, event);
+    }
+
+    fn query_sql(filter: ChatLogFilter, after: Option<ChatLogCursor>) -> (String, String) {
+        let query = search_query(1, filter, after, 100);
+        let debug = debug_query::<Pg, _>(&query).to_string();
+        let separator = debug.find(" -- binds: ").expect("binds in debug output");
+        let (sql, binds) = debug.split_at(separator);
+        (sql.to_string(), binds.to_string())
+    }
+
+    #[test]
+    fn test_search_query_order() {
+        let (sql, binds) = query_sql(ChatLogFilter::default(), None);
+        assert!(sql.contains(r#"ORDER BY "chat_events"."received_at" ASC, "chat_events"."id" ASC"#));
+        assert!(!sql.contains("(received_at, id) >"));
+        assert!(!sql.contains("to_tsvector"));
+        assert!(binds.contains("[1, 100]"));
+    }
+
+    #[test]
+    fn test_search_query_cursor() {
+        let cursor = ChatLogCursor {
+            received_at: Utc.ymd(2020, 1, 12).and_hms(15, 42, 11),
+            id: 4711,
+        };
+        let (sql, binds) = query_sql(ChatLogFilter::default(), Some(cursor));
+        // row comparison, so events with the same timestamp are paged by ID
+        assert!(sql.contains("(received_at, id) > ($2, $3)"));
+        assert!(binds.contains("2020-01-12T15:42:11Z"));
+        assert!(binds.contains("4711"));
+    }
+
+    #[test]
+    fn test_search_query_filters() {
+        let filter = ChatLogFilter {
+            from: Some(Utc.ymd(2020, 1, 1).and_hms(0, 0, 0)),
+            to: Some(Utc.ymd(2020, 2, 1).and_hms(0, 0, 0)),
+            sender_user_id: Some(42),
+            event_types: vec![ChatEventType::Privmsg, ChatEventType::Clearchat],
+            search: Some("hello world".into()),
+        };
+        let (sql, binds) = query_sql(filter, None);
+        assert!(sql.contains(r#""chat_events"."received_at" >= $2"#));
+        assert!(sql.contains(r#""chat_events"."received_at" < $3"#));
+        assert!(sql.contains(r#""chat_events"."sender_user_id" = $4"#));
+        assert!(sql.contains(r#""chat_events"."event_type" IN ($5, $6)"#));
+        assert!(sql.contains(
+            "to_tsvector('simple', coalesce(message, '')) @@ plainto_tsquery('simple', $7)"
+        ));
+        assert!(binds.contains("42"));
+        assert!(binds.contains("Privmsg"));
+        assert!(binds.contains("Clearchat"));
+        assert!(binds.contains("\"hello world\""));
+    }
+}
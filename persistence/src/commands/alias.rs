@@ -1,5 +1,6 @@
+use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
-use tokio_diesel::AsyncRunQueryDsl;
+use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
 
 use crate::schema::*;
 use crate::DbPool;
@@ -22,6 +23,24 @@ impl CommandAlias {
             .map_err(Into::into)
     }
 
+    pub async fn get(pool: &DbPool, name: &str) -> Result<Option<CommandAlias>> {
+        let name = name.to_lowercase();
+        command_aliases::table
+            .filter(command_aliases::name.eq(name))
+            .first_async(pool)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    pub async fn for_command(pool: &DbPool, command_id: i32) -> Result<Vec<CommandAlias>> {
+        command_aliases::table
+            .filter(command_aliases::command_id.eq(command_id))
+            .load_async(pool)
+            .await
+            .map_err(Into::into)
+    }
+
     /// Get a list of all channel commands active for the given channel
     pub async fn channel_commands(pool: &DbPool, channel_id: i32) -> Result<Vec<CommandAlias>> {
         sql_query(
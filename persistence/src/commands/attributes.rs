@@ -10,7 +10,7 @@ use diesel::prelude::*;
 use diesel::sql_query;
 use diesel::sql_types::{Array, Integer, Text};
 use serde::{Deserialize, Serialize};
-use tokio_diesel::AsyncRunQueryDsl;
+use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
 
 use crate::cache::Cacheable;
 use crate::commands::channel_config::ChannelCommandConfigNamed;
@@ -177,6 +177,16 @@ impl CommandAttributes {
             .map_err(Into::into)
     }
 
+    pub async fn get(pool: &DbPool, command_id: i32) -> Result<Option<CommandAttributes>> {
+        command_attributes::table
+            .find(command_id)
+            .select(CommandAttributes::COLUMNS)
+            .first_async(pool)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
     pub async fn list_with_aliases(
         pool: &DbPool,
         slice: OffsetParameters,
@@ -8,8 +8,8 @@ use crate::cache::Cacheable;
 use crate::commands::attributes::DurationMillis;
 use crate::impl_redis_bincode_int;
 use crate::schema::*;
-use crate::DbContext;
 use crate::Result;
+use crate::{DbContext, DbPool};
 
 /// Channel specific command configuration
 #[derive(Debug, Serialize, Deserialize, Queryable)]
@@ -54,6 +54,15 @@ impl ChannelCommandConfig {
         }
         Ok(config)
     }
+
+    /// All command configs of a channel, bypasses the cache
+    pub async fn list_for_channel(pool: &DbPool, channel_id_value: i32) -> Result<Vec<Self>> {
+        channel_command_config::table
+            .filter(channel_command_config::channel_id.eq(channel_id_value))
+            .load_async::<ChannelCommandConfig>(pool)
+            .await
+            .map_err(Into::into)
+    }
 }
 
 impl_redis_bincode_int!(ChannelCommandConfig);
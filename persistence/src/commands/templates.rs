@@ -1,5 +1,5 @@
 use diesel::{ExpressionMethods, QueryDsl, Queryable};
-use tokio_diesel::AsyncRunQueryDsl;
+use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
 
 use crate::schema::command_attributes;
 use crate::DbPool;
@@ -34,4 +34,16 @@ impl CommandTemplate {
             .await
             .map_err(Into::into)
     }
+
+    /// Get the template of a command, `None` if the command has no template
+    pub async fn get(pool: &DbPool, command_id: i32) -> Result<Option<CommandTemplate>> {
+        command_attributes::table
+            .find(command_id)
+            .filter(command_attributes::template.is_not_null())
+            .select(CommandTemplate::COLUMNS)
+            .first_async(pool)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
 }
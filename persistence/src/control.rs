@@ -0,0 +1,43 @@
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::impl_redis_bincode_int;
+use crate::redis_values::*;
+use crate::DbContext;
+use crate::Result;
+
+/// Redis pub/sub channel used to send actions to running bot processes
+pub const CONTROL_CHANNEL: &str = "cb:control";
+
+/// Actions the bot can be instructed to take through the control channel
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BotAction {
+    ReloadCommands,
+    ReloadPermissions,
+    ReloadTemplates,
+    JoinChannel(String),
+    PartChannel(String),
+    Say { channel: String, message: String },
+}
+
+impl_redis_bincode_int!(BotAction);
+
+impl BotAction {
+    /// Publish the action on the control channel. Returns the number of subscribers that received
+    /// the action.
+    pub async fn publish(&self, ctx: &DbContext) -> Result<isize> {
+        ctx.redis_pool
+            .get()
+            .await
+            .publish(CONTROL_CHANNEL, self.to_redis()?)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Subscribe to the control channel using a dedicated connection
+    pub async fn subscribe(ctx: &DbContext) -> Result<impl Stream<Item = Result<BotAction>>> {
+        let connection = ctx.dedicated_redis_connection().await?;
+        let messages = connection.subscribe(&[CONTROL_CHANNEL]).await?;
+        Ok(messages.map(|message| BotAction::from_redis(&message.message)))
+    }
+}
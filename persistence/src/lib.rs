@@ -8,6 +8,7 @@ extern crate log;
 use std::num::TryFromIntError;
 
 use diesel::r2d2::ConnectionManager;
+use diesel::result::DatabaseErrorKind;
 use diesel::PgConnection;
 use r2d2::Pool;
 use thiserror::Error;
@@ -24,6 +25,7 @@ embed_migrations!("../migrations");
 pub struct DbContext {
     pub db_pool: DbPool,
     pub redis_pool: RedisPool,
+    redis_address: String,
 }
 
 impl DbContext {
@@ -38,22 +40,39 @@ impl DbContext {
         Ok(DbContext {
             db_pool,
             redis_pool,
+            redis_address: redis_address.to_string(),
         })
     }
 
+    /// Open a new redis connection outside of the pool, for uses that block the connection like
+    /// pub/sub subscriptions
+    pub async fn dedicated_redis_connection(&self) -> Result<darkredis::Connection> {
+        darkredis::Connection::connect(self.redis_address.as_str(), None)
+            .await
+            .map_err(Into::into)
+    }
+
     pub fn run_pending_migrations(&self) -> Result<()> {
         embedded_migrations::run(&*self.db_pool.get()?)?;
         Ok(())
     }
 }
 
+pub mod alerts;
+pub mod api_tokens;
+pub mod audit;
 pub mod cache;
 pub mod channel;
 pub mod chat_event;
 pub mod commands;
+pub mod control;
+pub mod live_events;
 mod pagination;
+pub mod partitions;
 pub mod permissions;
+pub mod retention;
 pub mod schema;
+pub mod stats;
 pub mod user;
 
 #[macro_use]
@@ -67,6 +86,9 @@ pub enum Error {
     #[error("Item not found")]
     NotFound,
 
+    #[error("Item already exists")]
+    Conflict,
+
     #[error("Connection pool error: {0}")]
     ConnectionPool(#[from] r2d2::Error),
 
@@ -90,6 +112,10 @@ impl From<tokio_diesel::AsyncError> for Error {
     fn from(err: AsyncError) -> Self {
         match err {
             AsyncError::Error(diesel::result::Error::NotFound) => Error::NotFound,
+            AsyncError::Error(diesel::result::Error::DatabaseError(
+                DatabaseErrorKind::UniqueViolation,
+                _,
+            )) => Error::Conflict,
             err => Error::AsyncDiesel(err),
         }
     }
@@ -0,0 +1,77 @@
+use chrono::{DateTime, FixedOffset};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::chat_event::{ChatEventType, Tags};
+use crate::impl_redis_bincode_int;
+use crate::redis_values::*;
+use crate::DbContext;
+use crate::Result;
+
+/// Events pushed to live feed subscribers as they happen in a channel
+#[derive(Serialize, Deserialize, Debug)]
+pub enum LiveEvent {
+    Chat(LiveChatEvent),
+    CommandExecuted(LiveCommandEvent),
+}
+
+/// A chat event, normalized like the logged `NewChatEvent` but including the sender's name
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LiveChatEvent {
+    pub event_type: ChatEventType,
+    pub channel_id: i32,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: Option<String>,
+    pub message: Option<String>,
+    pub tags: Option<Tags>,
+    pub received_at: DateTime<FixedOffset>,
+}
+
+/// A command that was successfully executed in a channel
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LiveCommandEvent {
+    pub channel_id: i32,
+    pub command_id: i32,
+    /// the alias used to call the command
+    pub command_name: String,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: Option<String>,
+    pub executed_at: DateTime<FixedOffset>,
+}
+
+impl_redis_bincode_int!(LiveEvent);
+
+fn live_channel_key(channel_id: i32) -> String {
+    format!("cb:live_events:{}", channel_id)
+}
+
+impl LiveEvent {
+    pub fn channel_id(&self) -> i32 {
+        match self {
+            LiveEvent::Chat(event) => event.channel_id,
+            LiveEvent::CommandExecuted(event) => event.channel_id,
+        }
+    }
+
+    /// Publish the event to all subscribers of the channel's live feed
+    pub async fn publish(&self, ctx: &DbContext) -> Result<()> {
+        ctx.redis_pool
+            .get()
+            .await
+            .publish(live_channel_key(self.channel_id()), self.to_redis()?)
+            .await?;
+        Ok(())
+    }
+
+    /// Subscribe to the live feed of a channel using a dedicated connection
+    pub async fn subscribe(
+        ctx: &DbContext,
+        channel_id: i32,
+    ) -> Result<impl Stream<Item = Result<LiveEvent>>> {
+        let connection = ctx.dedicated_redis_connection().await?;
+        let messages = connection
+            .subscribe(&[live_channel_key(channel_id)])
+            .await?;
+        Ok(messages.map(|message| LiveEvent::from_redis(&message.message)))
+    }
+}
@@ -20,3 +20,10 @@ impl OffsetParameters {
         self.limit
     }
 }
+
+/// Result row of a `count(*) as count` query, used to get total counts for paginated raw queries
+#[derive(QueryableByName, Debug)]
+pub(crate) struct TotalCount {
+    #[sql_type = "diesel::sql_types::BigInt"]
+    pub count: i64,
+}
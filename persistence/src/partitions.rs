@@ -0,0 +1,34 @@
+//! Management of the `chat_events` partitions. Events are partitioned by channel, each channel
+//! partition is partitioned by month. Partitions are created by the `create_chat_events_partitions`
+//! database function.
+
+use diesel::sql_query;
+use diesel::sql_types::{Integer, Nullable};
+use tokio_diesel::AsyncRunQueryDsl;
+
+use crate::DbPool;
+use crate::Result;
+
+/// Number of monthly partitions created in advance
+pub const MONTHS_AHEAD: i32 = 3;
+
+/// Create the partition of a channel and its monthly partitions up to `MONTHS_AHEAD` months in
+/// the future. Existing partitions are kept. `None` creates partitions for events without channel.
+pub async fn create_channel_partitions(pool: &DbPool, channel_id: Option<i32>) -> Result<()> {
+    sql_query("select create_chat_events_partitions($1, $2);")
+        .bind::<Nullable<Integer>, _>(channel_id)
+        .bind::<Integer, _>(MONTHS_AHEAD)
+        .execute_async(pool)
+        .await?;
+    Ok(())
+}
+
+/// Create missing partitions for all channels
+pub async fn create_all_partitions(pool: &DbPool) -> Result<()> {
+    create_channel_partitions(pool, None).await?;
+    sql_query("select create_chat_events_partitions(id, $1) from channels;")
+        .bind::<Integer, _>(MONTHS_AHEAD)
+        .execute_async(pool)
+        .await?;
+    Ok(())
+}
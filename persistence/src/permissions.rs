@@ -1,12 +1,13 @@
 use std::iter::FromIterator;
 
 use diesel::expression::sql_literal::sql;
-use diesel::sql_types::Text;
+use diesel::sql_query;
+use diesel::sql_types::{Array, Int4, Integer, Nullable, Text};
 use diesel_derive_enum::DbEnum;
 use fnv::FnvHashSet;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
-use tokio_diesel::{AsyncConnection, AsyncRunQueryDsl};
+use tokio_diesel::{AsyncConnection, AsyncRunQueryDsl, OptionalExtension};
 
 use crate::schema::{command_permissions, implied_permissions, permissions, user_permissions};
 use crate::Result;
@@ -22,7 +23,8 @@ pub enum PermissionState {
 
 /// Represents a permission for any feature in the bot, contains a unique name, user-facing description
 /// and default state
-#[derive(Queryable, Debug)]
+#[derive(Queryable, QueryableByName, Debug)]
+#[table_name = "permissions"]
 pub struct Permission {
     pub id: i32,
     pub name: String,
@@ -44,6 +46,25 @@ pub struct AddPermission<'a> {
     pub implied_by: Vec<&'a str>,
 }
 
+/// Owned data used to create a single custom permission
+#[derive(Clone, Debug)]
+pub struct NewPermission {
+    pub name: String,
+    pub description: Option<String>,
+    pub default_state: PermissionState,
+    /// IDs of the permissions that imply the new permission
+    pub implied_by: Vec<i32>,
+}
+
+/// A permission together with the IDs of all permissions that directly imply it
+#[derive(QueryableByName, Debug)]
+pub struct PermissionWithImplied {
+    #[diesel(embed)]
+    pub permission: Permission,
+    #[sql_type = "Array<Int4>"]
+    pub implied_by: Vec<i32>,
+}
+
 #[derive(Queryable, Insertable)]
 pub struct UserPermission {
     pub permission_id: i32,
@@ -51,6 +72,28 @@ pub struct UserPermission {
     pub user_permission_state: PermissionState,
 }
 
+#[derive(QueryableByName, Debug)]
+struct UserPermissionRow {
+    #[diesel(embed)]
+    permission: Permission,
+    #[sql_type = "Nullable<PermissionStateMapping>"]
+    user_permission_state: Option<PermissionState>,
+    #[sql_type = "Array<Int4>"]
+    implied_by: Vec<i32>,
+}
+
+/// A permission as it applies to a single user
+#[derive(Debug)]
+pub struct EffectivePermission {
+    pub permission: Permission,
+    /// state explicitly set for the user, `None` if the default state applies
+    pub user_state: Option<PermissionState>,
+    /// whether the user holds the permission, either directly or through an implying permission
+    pub granted: bool,
+    /// IDs of permissions held by the user that imply this permission
+    pub granted_via: Vec<i32>,
+}
+
 impl Permission {
     pub async fn get_by_command_id(pool: &DbPool, command_id: i32) -> Result<Vec<i32>> {
         permissions::table
@@ -68,9 +111,126 @@ impl Permission {
             .await
             .map_err(Into::into)
     }
+
+    pub async fn get(pool: &DbPool, permission_id: i32) -> Result<Permission> {
+        permissions::table
+            .find(permission_id)
+            .first_async::<Permission>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_by_name(pool: &DbPool, name: &str) -> Result<Option<Permission>> {
+        let name = name.to_string();
+        permissions::table
+            .filter(permissions::name.eq(name))
+            .first_async::<Permission>(pool)
+            .await
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Get all permissions including the IDs of the permissions implying them
+    pub async fn all_with_implied(pool: &DbPool) -> Result<Vec<PermissionWithImplied>> {
+        sql_query(
+            "select p.id, p.name, p.description, p.default_state, \
+             array_remove(array_agg(ip.implied_by_id), null) implied_by \
+             from permissions p \
+             left join implied_permissions ip on ip.permission_id = p.id \
+             group by p.id \
+             order by p.name;",
+        )
+        .load_async::<PermissionWithImplied>(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Create a single permission. Fails with `Error::Conflict` if the name is already taken.
+    pub async fn create(pool: &DbPool, new_permission: NewPermission) -> Result<Permission> {
+        pool.transaction(move |pg| {
+            let inserted = diesel::insert_into(permissions::table)
+                .values(&NewPermissionAttributes {
+                    name: &new_permission.name,
+                    description: new_permission.description.as_deref(),
+                    default_state: new_permission.default_state,
+                })
+                .get_result::<Permission>(pg)?;
+
+            let implied_values = new_permission
+                .implied_by
+                .iter()
+                .map(|&implied_by_id| {
+                    (
+                        implied_permissions::implied_by_id.eq(implied_by_id),
+                        implied_permissions::permission_id.eq(inserted.id),
+                    )
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(implied_permissions::table)
+                .values(implied_values)
+                .execute(pg)?;
+
+            Ok(inserted)
+        })
+        .await
+        .map_err(Into::into)
+    }
 }
 
 impl UserPermission {
+    /// Explicitly allow or deny a permission for a user, overriding the permission's default state
+    pub async fn set_state(
+        pool: &DbPool,
+        user_id: i32,
+        permission_id: i32,
+        state: PermissionState,
+    ) -> Result<()> {
+        diesel::insert_into(user_permissions::table)
+            .values(UserPermission {
+                permission_id,
+                user_id,
+                user_permission_state: state,
+            })
+            .on_conflict((user_permissions::permission_id, user_permissions::user_id))
+            .do_update()
+            .set(user_permissions::user_permission_state.eq(state))
+            .execute_async(pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a user's explicit permission state so the permission's default applies again.
+    /// Returns false if no state was set.
+    pub async fn reset_state(pool: &DbPool, user_id: i32, permission_id: i32) -> Result<bool> {
+        let deleted = diesel::delete(
+            user_permissions::table
+                .filter(user_permissions::user_id.eq(user_id))
+                .filter(user_permissions::permission_id.eq(permission_id)),
+        )
+        .execute_async(pool)
+        .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Resolve the state of every permission for a user, following the implied permissions
+    /// graph to find permissions that are granted indirectly
+    pub async fn get_effective(pool: &DbPool, user_id: i32) -> Result<Vec<EffectivePermission>> {
+        let rows = sql_query(
+            "select p.id, p.name, p.description, p.default_state, up.user_permission_state, \
+             array_remove(array_agg(ip.implied_by_id), null) implied_by \
+             from permissions p \
+             left join user_permissions up on up.permission_id = p.id and up.user_id = $1 \
+             left join implied_permissions ip on ip.permission_id = p.id \
+             group by p.id, up.user_permission_state \
+             order by p.name;",
+        )
+        .bind::<Integer, _>(user_id)
+        .load_async::<UserPermissionRow>(pool)
+        .await?;
+
+        Ok(resolve_effective(rows))
+    }
+
     pub async fn get_by_user_id(ctx: &DbContext, user_id: i32) -> Result<Vec<i32>> {
         permissions::table
             .select(permissions::id)
@@ -126,6 +286,46 @@ impl UserPermission {
     }
 }
 
+/// Resolve the effective permissions of a user from the permission rows of `get_effective`
+fn resolve_effective(rows: Vec<UserPermissionRow>) -> Vec<EffectivePermission> {
+    let mut granted: FnvHashSet<i32> = rows
+        .iter()
+        .filter(|row| {
+            row.user_permission_state
+                .unwrap_or(row.permission.default_state)
+                == PermissionState::Allow
+        })
+        .map(|row| row.permission.id)
+        .collect();
+
+    // propagate grants along the implied permissions until nothing changes anymore
+    loop {
+        let newly_granted = rows
+            .iter()
+            .filter(|row| !granted.contains(&row.permission.id))
+            .filter(|row| row.implied_by.iter().any(|id| granted.contains(id)))
+            .map(|row| row.permission.id)
+            .collect::<Vec<_>>();
+        if newly_granted.is_empty() {
+            break;
+        }
+        granted.extend(newly_granted);
+    }
+
+    rows.into_iter()
+        .map(|row| EffectivePermission {
+            granted: granted.contains(&row.permission.id),
+            granted_via: row
+                .implied_by
+                .into_iter()
+                .filter(|id| granted.contains(id))
+                .collect(),
+            user_state: row.user_permission_state,
+            permission: row.permission,
+        })
+        .collect()
+}
+
 /// A set of default permissions that should always be available to all commands
 static DEFAULT_PERMISSIONS: OnceCell<Vec<AddPermission<'static>>> = OnceCell::new();
 
@@ -174,14 +374,103 @@ pub async fn create_permissions(
 /// Create the global default permissions
 pub async fn create_default_permissions(ctx: &DbContext) -> Result<usize> {
     let permissions: &'static _ = DEFAULT_PERMISSIONS.get_or_init(|| {
-        vec![AddPermission {
-            attributes: NewPermissionAttributes {
-                name: "root",
-                description: Some("Super admin override"),
-                default_state: PermissionState::Deny,
+        vec![
+            AddPermission {
+                attributes: NewPermissionAttributes {
+                    name: "root",
+                    description: Some("Super admin override"),
+                    default_state: PermissionState::Deny,
+                },
+                implied_by: vec![],
             },
-            implied_by: vec![],
-        }]
+            AddPermission {
+                attributes: NewPermissionAttributes {
+                    name: "livefeed:chat",
+                    description: Some("Watch chat messages in the dashboard live feed"),
+                    default_state: PermissionState::Deny,
+                },
+                implied_by: vec!["root"],
+            },
+            AddPermission {
+                attributes: NewPermissionAttributes {
+                    name: "livefeed:commands",
+                    description: Some("Watch command executions in the dashboard live feed"),
+                    default_state: PermissionState::Deny,
+                },
+                implied_by: vec!["root"],
+            },
+            AddPermission {
+                attributes: NewPermissionAttributes {
+                    name: "livefeed:moderation",
+                    description: Some("Watch moderation actions in the dashboard live feed"),
+                    default_state: PermissionState::Deny,
+                },
+                implied_by: vec!["root"],
+            },
+        ]
     });
     create_permissions(&ctx.db_pool, Cow::Borrowed(permissions)).await
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(
+        id: i32,
+        default_state: PermissionState,
+        user_state: Option<PermissionState>,
+        implied_by: Vec<i32>,
+    ) -> UserPermissionRow {
+        UserPermissionRow {
+            permission: Permission {
+                id,
+                name: format!("permission{}", id),
+                description: None,
+                default_state,
+            },
+            user_permission_state: user_state,
+            implied_by,
+        }
+    }
+
+    fn granted(permissions: &[EffectivePermission]) -> Vec<i32> {
+        permissions
+            .iter()
+            .filter(|permission| permission.granted)
+            .map(|permission| permission.permission.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve_effective_propagates_implied() {
+        use PermissionState::*;
+        // 1 implies 2, 2 implies 3, 4 is implied by nothing the user holds
+        let permissions = resolve_effective(vec![
+            row(1, Deny, Some(Allow), vec![]),
+            row(2, Deny, None, vec![1]),
+            row(3, Deny, None, vec![2]),
+            row(4, Deny, None, vec![5]),
+            row(5, Deny, None, vec![]),
+        ]);
+        assert_eq!(granted(&permissions), vec![1, 2, 3]);
+        assert_eq!(permissions[2].granted_via, vec![2]);
+        assert!(permissions[3].granted_via.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_effective_user_state() {
+        use PermissionState::*;
+        let permissions = resolve_effective(vec![
+            // denied for the user, so it doesn't imply 2
+            row(1, Allow, Some(Deny), vec![]),
+            row(2, Deny, None, vec![1]),
+            // allowed by default
+            row(3, Allow, None, vec![]),
+            row(4, Deny, None, vec![3]),
+        ]);
+        assert_eq!(granted(&permissions), vec![3, 4]);
+        assert_eq!(permissions[0].user_state, Some(Deny));
+        assert_eq!(permissions[3].granted_via, vec![3]);
+    }
+}
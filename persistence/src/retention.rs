@@ -0,0 +1,253 @@
+//! Chat log retention. Expired monthly partitions of `chat_events` are dropped as a whole, expired
+//! events in the default and legacy partitions of a channel are deleted row by row.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Bool, Integer, Nullable, Text, Timestamptz};
+use diesel::RunQueryDsl;
+use tokio_diesel::{AsyncConnection, AsyncRunQueryDsl};
+
+use crate::chat_event::{ChatEvent, ChatLogCursor};
+use crate::DbPool;
+use crate::Result;
+
+/// Partition of a channel containing events older than the channel's retention period
+#[derive(QueryableByName, Debug, Clone)]
+pub struct ExpiredPartition {
+    #[sql_type = "Text"]
+    pub table_name: String,
+    /// name of the channel's list partition
+    #[sql_type = "Text"]
+    pub parent_name: String,
+    #[sql_type = "Integer"]
+    pub channel_id: i32,
+    #[sql_type = "Text"]
+    pub channel_name: String,
+    /// whether expired events should be exported before removing them
+    #[sql_type = "Bool"]
+    pub archive: bool,
+    /// events received before this time are expired
+    #[sql_type = "Timestamptz"]
+    pub cutoff: DateTime<Utc>,
+    /// whether all events in the partition are expired
+    #[sql_type = "Bool"]
+    pub fully_expired: bool,
+}
+
+/// Disk space used by the chat logs of a channel
+#[derive(QueryableByName, Debug)]
+pub struct ChannelStorage {
+    #[sql_type = "Integer"]
+    pub channel_id: i32,
+    #[sql_type = "Text"]
+    pub channel_name: String,
+    #[sql_type = "Nullable<Integer>"]
+    pub log_retention_days: Option<i32>,
+    /// size of all partitions including indexes
+    #[sql_type = "BigInt"]
+    pub total_bytes: i64,
+    #[sql_type = "BigInt"]
+    pub partition_count: i64,
+    /// estimated number of events, based on table statistics
+    #[sql_type = "BigInt"]
+    pub estimated_events: i64,
+}
+
+/// Partition of a channel with a retention period
+#[derive(QueryableByName, Debug)]
+struct ChannelPartition {
+    #[sql_type = "Text"]
+    table_name: String,
+    #[sql_type = "Text"]
+    parent_name: String,
+    #[sql_type = "Integer"]
+    channel_id: i32,
+    #[sql_type = "Text"]
+    channel_name: String,
+    #[sql_type = "Bool"]
+    archive: bool,
+    #[sql_type = "Timestamptz"]
+    cutoff: DateTime<Utc>,
+}
+
+#[derive(QueryableByName, Debug)]
+struct HasExpiredEvents {
+    #[sql_type = "Bool"]
+    expired: bool,
+}
+
+impl ExpiredPartition {
+    /// Find the partitions of all channels with a retention period that contain expired events.
+    /// Monthly partitions are expired once their month ended before the cutoff, the default and
+    /// legacy partitions only if they actually contain events before the cutoff.
+    pub async fn find_all(pool: &DbPool) -> Result<Vec<ExpiredPartition>> {
+        let partitions: Vec<ChannelPartition> = sql_query(
+            "select c.relname::text table_name, p.relname::text parent_name, \
+             ch.id channel_id, ch.name::text channel_name, ch.archive_logs archive, \
+             now() - make_interval(days => ch.log_retention_days) cutoff \
+             from channels ch \
+             join pg_class p on p.relname = 'chat_events_ch' || ch.id \
+             join pg_inherits i on i.inhparent = p.oid \
+             join pg_class c on c.oid = i.inhrelid \
+             where ch.log_retention_days is not null \
+             order by ch.id, c.relname;",
+        )
+        .load_async(pool)
+        .await?;
+
+        let mut expired = vec![];
+        for partition in partitions {
+            let fully_expired = match month_partition_end(&partition.table_name) {
+                Some(end) if end <= partition.cutoff => true,
+                Some(_) => continue,
+                None if has_expired_events(pool, &partition).await? => false,
+                None => continue,
+            };
+            expired.push(ExpiredPartition {
+                table_name: partition.table_name,
+                parent_name: partition.parent_name,
+                channel_id: partition.channel_id,
+                channel_name: partition.channel_name,
+                archive: partition.archive,
+                cutoff: partition.cutoff,
+                fully_expired,
+            });
+        }
+        Ok(expired)
+    }
+
+    /// Load a batch of expired events, ordered by time
+    pub async fn load_events(
+        &self,
+        pool: &DbPool,
+        after: Option<ChatLogCursor>,
+        limit: u32,
+    ) -> Result<Vec<ChatEvent>> {
+        sql_query(format!(
+            "select * from \"{}\" \
+             where received_at < $1 \
+             and ($2::timestamptz is null or (received_at, id) > ($2, $3)) \
+             order by received_at, id \
+             limit $4;",
+            self.table_name
+        ))
+        .bind::<Timestamptz, _>(self.cutoff)
+        .bind::<Nullable<Timestamptz>, _>(after.as_ref().map(|cursor| cursor.received_at))
+        .bind::<Nullable<BigInt>, _>(after.as_ref().map(|cursor| cursor.id))
+        .bind::<BigInt, _>(i64::from(limit))
+        .load_async(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Remove the expired events. Fully expired partitions are detached and dropped.
+    pub async fn remove(&self, pool: &DbPool) -> Result<()> {
+        let partition = self.clone();
+        if self.fully_expired {
+            pool.transaction(move |conn| {
+                sql_query(format!(
+                    "alter table \"{}\" detach partition \"{}\";",
+                    partition.parent_name, partition.table_name
+                ))
+                .execute(conn)?;
+                sql_query(format!("drop table \"{}\";", partition.table_name)).execute(conn)
+            })
+            .await?;
+        } else {
+            sql_query(format!(
+                "delete from \"{}\" where received_at < $1;",
+                partition.table_name
+            ))
+            .bind::<Timestamptz, _>(partition.cutoff)
+            .execute_async(pool)
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// End of the month covered by a monthly partition, `None` for other partitions. Monthly
+/// partitions are named `chat_events_ch<channel id>_y<year>m<month>`.
+fn month_partition_end(table_name: &str) -> Option<DateTime<Utc>> {
+    let suffix = table_name.rsplit('_').next()?;
+    if !suffix.is_ascii() || suffix.len() != 8 || !suffix.starts_with('y') || &suffix[5..6] != "m" {
+        return None;
+    }
+    let year = suffix[1..5].parse().ok()?;
+    let month = suffix[6..8].parse().ok()?;
+    NaiveDate::from_ymd_opt(year, month, 1)?;
+    let end = match month {
+        12 => NaiveDate::from_ymd(year + 1, 1, 1),
+        _ => NaiveDate::from_ymd(year, month + 1, 1),
+    };
+    Some(DateTime::from_utc(end.and_hms(0, 0, 0), Utc))
+}
+
+async fn has_expired_events(pool: &DbPool, partition: &ChannelPartition) -> Result<bool> {
+    let result = sql_query(format!(
+        "select exists(select 1 from \"{}\" where received_at < $1) expired;",
+        partition.table_name
+    ))
+    .bind::<Timestamptz, _>(partition.cutoff)
+    .get_result_async::<HasExpiredEvents>(pool)
+    .await?;
+    Ok(result.expired)
+}
+
+impl ChannelStorage {
+    /// Chat log storage usage of all channels, largest first
+    pub async fn all(pool: &DbPool) -> Result<Vec<ChannelStorage>> {
+        sql_query(
+            "select ch.id channel_id, ch.name::text channel_name, ch.log_retention_days, \
+             coalesce(sum(pg_total_relation_size(c.oid)), 0)::int8 total_bytes, \
+             count(c.oid) partition_count, \
+             coalesce(sum(greatest(c.reltuples, 0)), 0)::int8 estimated_events \
+             from channels ch \
+             left join pg_class p on p.relname = 'chat_events_ch' || ch.id \
+             left join pg_inherits i on i.inhparent = p.oid \
+             left join pg_class c on c.oid = i.inhrelid \
+             group by ch.id \
+             order by total_bytes desc;",
+        )
+        .load_async(pool)
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Chat log storage usage of one channel
+    pub async fn get(pool: &DbPool, channel_id: i32) -> Result<Option<ChannelStorage>> {
+        Ok(Self::all(pool)
+            .await?
+            .into_iter()
+            .find(|storage| storage.channel_id == channel_id))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_month_partition_end() {
+        assert_eq!(
+            month_partition_end("chat_events_ch4_y2020m01"),
+            Some(Utc.ymd(2020, 2, 1).and_hms(0, 0, 0))
+        );
+        assert_eq!(
+            month_partition_end("chat_events_ch12_y2019m12"),
+            Some(Utc.ymd(2020, 1, 1).and_hms(0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_month_partition_end_other_partitions() {
+        assert_eq!(month_partition_end("chat_events_ch4_default"), None);
+        assert_eq!(month_partition_end("chat_events_ch4_1578438000"), None);
+        assert_eq!(month_partition_end("chat_events_ch4"), None);
+        assert_eq!(month_partition_end("chat_events_ch4_y2020m13"), None);
+        assert_eq!(month_partition_end("chat_events_ch4_y2020m00"), None);
+        assert_eq!(month_partition_end("chat_events_ch4_yabcdm01"), None);
+    }
+}
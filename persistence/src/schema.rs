@@ -1,3 +1,30 @@
+table! {
+    audit_log (id) {
+        id -> Int4,
+        created_at -> Timestamptz,
+        user_id -> Nullable<Int4>,
+        action -> Text,
+        source -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use crate::alerts::AlertKindMapping;
+
+    channel_alerts (id) {
+        id -> Int4,
+        channel_id -> Int4,
+        alert_kind -> AlertKindMapping,
+        min_value -> Nullable<Int4>,
+        template -> Text,
+        min_interval -> Nullable<Int4>,
+        enabled -> Bool,
+        updated_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     channel_command_config (channel_id, command_id) {
         channel_id -> Int4,
@@ -17,12 +44,15 @@ table! {
         updated_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
         silent -> Bool,
+        log_retention_days -> Nullable<Int4>,
+        archive_logs -> Bool,
+        discord_channel_id -> Nullable<Int8>,
     }
 }
 
 table! {
     use diesel::sql_types::*;
-    use crate::chat_event::ChatEventTypeMapping;
+    use crate::chat_event::{ChatEventTypeMapping, UserNoticeKindMapping};
 
     chat_events (id) {
         id -> Int8,
@@ -33,6 +63,54 @@ table! {
         sender_user_id -> Nullable<Int4>,
         tags -> Nullable<Jsonb>,
         received_at -> Timestamptz,
+        notice_kind -> Nullable<UserNoticeKindMapping>,
+        sub_plan -> Nullable<Text>,
+        months -> Nullable<Int4>,
+        recipient_name -> Nullable<Text>,
+        gift_count -> Nullable<Int4>,
+        viewer_count -> Nullable<Int4>,
+        bits_threshold -> Nullable<Int4>,
+        ritual_name -> Nullable<Text>,
+    }
+}
+
+table! {
+    chat_stats_first_seen (channel_id, user_id) {
+        channel_id -> Int4,
+        user_id -> Int4,
+        first_day -> Date,
+    }
+}
+
+table! {
+    chat_stats_hourly (channel_id, hour) {
+        channel_id -> Int4,
+        hour -> Timestamptz,
+        message_count -> Int4,
+        chatter_count -> Int4,
+    }
+}
+
+table! {
+    chat_stats_late_hours (channel_id, hour) {
+        channel_id -> Int4,
+        hour -> Timestamptz,
+    }
+}
+
+table! {
+    chat_stats_refresh (id) {
+        id -> Bool,
+        refreshed_until -> Timestamptz,
+    }
+}
+
+table! {
+    chat_stats_user_daily (channel_id, day, user_id) {
+        channel_id -> Int4,
+        day -> Date,
+        user_id -> Int4,
+        message_count -> Int4,
     }
 }
 
@@ -64,6 +142,15 @@ table! {
     }
 }
 
+table! {
+    command_usage_daily (channel_id, day, command_id) {
+        channel_id -> Int4,
+        day -> Date,
+        command_id -> Int4,
+        use_count -> Int4,
+    }
+}
+
 table! {
     implied_permissions (permission_id, implied_by_id) {
         permission_id -> Int4,
@@ -83,6 +170,14 @@ table! {
     }
 }
 
+table! {
+    user_api_tokens (token_hash) {
+        token_hash -> Text,
+        user_id -> Int4,
+        created_at -> Timestamptz,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use crate::permissions::PermissionStateMapping;
@@ -97,35 +192,56 @@ table! {
 table! {
     users (id) {
         id -> Int4,
-        twitch_user_id -> Int4,
+        twitch_user_id -> Nullable<Int4>,
         name -> Varchar,
         display_name -> Nullable<Varchar>,
         previous_names -> Nullable<Array<Text>>,
         previous_display_names -> Nullable<Array<Text>>,
         updated_at -> Nullable<Timestamptz>,
         created_at -> Timestamptz,
+        discord_user_id -> Nullable<Int8>,
     }
 }
 
+joinable!(audit_log -> users (user_id));
+joinable!(channel_alerts -> channels (channel_id));
 joinable!(channel_command_config -> channels (channel_id));
 joinable!(channel_command_config -> command_attributes (command_id));
 joinable!(chat_events -> channels (channel_id));
 joinable!(chat_events -> users (sender_user_id));
+joinable!(chat_stats_first_seen -> channels (channel_id));
+joinable!(chat_stats_first_seen -> users (user_id));
+joinable!(chat_stats_hourly -> channels (channel_id));
+joinable!(chat_stats_late_hours -> channels (channel_id));
+joinable!(chat_stats_user_daily -> channels (channel_id));
+joinable!(chat_stats_user_daily -> users (user_id));
 joinable!(command_aliases -> command_attributes (command_id));
 joinable!(command_permissions -> command_attributes (command_id));
 joinable!(command_permissions -> permissions (permission_id));
+joinable!(command_usage_daily -> channels (channel_id));
+joinable!(command_usage_daily -> command_attributes (command_id));
+joinable!(user_api_tokens -> users (user_id));
 joinable!(user_permissions -> permissions (permission_id));
 joinable!(user_permissions -> users (user_id));
 
 allow_tables_to_appear_in_same_query!(
+    audit_log,
+    channel_alerts,
     channel_command_config,
     channels,
     chat_events,
+    chat_stats_first_seen,
+    chat_stats_hourly,
+    chat_stats_late_hours,
+    chat_stats_refresh,
+    chat_stats_user_daily,
     command_aliases,
     command_attributes,
     command_permissions,
+    command_usage_daily,
     implied_permissions,
     permissions,
+    user_api_tokens,
     user_permissions,
     users,
 );
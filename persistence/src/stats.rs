@@ -0,0 +1,353 @@
+//! Chat statistics, read from rollup tables that are refreshed periodically by `refresh`
+
+use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Date, Integer, Nullable, Text, Timestamptz};
+use serde::Serialize;
+use tokio_diesel::{AsyncRunQueryDsl, OptionalExtension};
+
+use crate::schema::command_usage_daily;
+use crate::DbPool;
+use crate::Result;
+
+/// Resolution of message count time series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Granularity {
+    Hour,
+    Day,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct MessageCount {
+    /// start of the hour or day
+    #[sql_type = "Timestamptz"]
+    pub period: DateTime<Utc>,
+    #[sql_type = "BigInt"]
+    pub message_count: i64,
+    /// number of distinct users that sent messages in the period
+    #[sql_type = "BigInt"]
+    pub chatter_count: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct ChatterCount {
+    #[sql_type = "Integer"]
+    pub user_id: i32,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "BigInt"]
+    pub message_count: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct CommandUsageCount {
+    #[sql_type = "Integer"]
+    pub command_id: i32,
+    #[sql_type = "Text"]
+    pub handler_name: String,
+    #[sql_type = "BigInt"]
+    pub use_count: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+pub struct UserRetention {
+    #[sql_type = "Date"]
+    pub day: NaiveDate,
+    /// users that sent their first message in the channel on this day
+    #[sql_type = "BigInt"]
+    pub new_users: i64,
+    /// users that have sent messages on a previous day
+    #[sql_type = "BigInt"]
+    pub returning_users: i64,
+}
+
+/// Chat activity of a single user in a channel
+#[derive(QueryableByName, Serialize, Debug)]
+pub struct UserChatStats {
+    #[sql_type = "Integer"]
+    pub user_id: i32,
+    #[sql_type = "Text"]
+    pub name: String,
+    #[sql_type = "Nullable<Text>"]
+    pub display_name: Option<String>,
+    #[sql_type = "BigInt"]
+    pub message_count: i64,
+    /// time of the first message in the channel, `None` if the user never chatted there
+    #[sql_type = "Nullable<Timestamptz>"]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// time of the latest logged event of the user in the channel
+    #[sql_type = "Nullable<Timestamptz>"]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// time the bot first saw the user in any channel
+    #[sql_type = "Timestamptz"]
+    pub known_since: DateTime<Utc>,
+    /// whole days since `known_since`. Not the age of the Twitch account, which isn't stored.
+    #[sql_type = "BigInt"]
+    pub known_since_days: i64,
+}
+
+#[derive(QueryableByName, Debug)]
+struct UniqueChatters {
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+/// Update the rollup tables with events received since the last refresh. Does nothing if
+/// another refresh is running.
+pub async fn refresh(pool: &DbPool) -> Result<()> {
+    sql_query("select refresh_chat_stats();")
+        .execute_async(pool)
+        .await?;
+    Ok(())
+}
+
+/// Count a command execution in a channel
+pub async fn record_command_usage(pool: &DbPool, channel_id: i32, command_id: i32) -> Result<()> {
+    use crate::schema::command_usage_daily::dsl;
+    diesel::insert_into(command_usage_daily::table)
+        .values((
+            dsl::channel_id.eq(channel_id),
+            dsl::day.eq(Utc::today().naive_utc()),
+            dsl::command_id.eq(command_id),
+            dsl::use_count.eq(1),
+        ))
+        .on_conflict((dsl::channel_id, dsl::day, dsl::command_id))
+        .do_update()
+        .set(dsl::use_count.eq(dsl::use_count + 1))
+        .execute_async(pool)
+        .await?;
+    Ok(())
+}
+
+/// Message and chatter counts per hour or day in the given time range. Includes the partial
+/// periods containing `from` and `to`.
+pub async fn message_counts(
+    pool: &DbPool,
+    channel_id: i32,
+    granularity: Granularity,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<MessageCount>> {
+    let first = period_start(granularity, from);
+    let last = period_start(granularity, to);
+    match granularity {
+        Granularity::Hour => sql_query(
+            "select hour period, message_count::int8, chatter_count::int8 \
+             from chat_stats_hourly \
+             where channel_id = $1 and hour between $2 and $3 \
+             order by hour;",
+        )
+        .bind::<Integer, _>(channel_id)
+        .bind::<Timestamptz, _>(first)
+        .bind::<Timestamptz, _>(last)
+        .load_async(pool)
+        .await
+        .map_err(Into::into),
+        Granularity::Day => sql_query(
+            "select day::timestamp at time zone 'UTC' period, \
+             sum(message_count)::int8 message_count, count(*) chatter_count \
+             from chat_stats_user_daily \
+             where channel_id = $1 and day between $2 and $3 \
+             group by day \
+             order by day;",
+        )
+        .bind::<Integer, _>(channel_id)
+        .bind::<Date, _>(first.naive_utc().date())
+        .bind::<Date, _>(last.naive_utc().date())
+        .load_async(pool)
+        .await
+        .map_err(Into::into),
+    }
+}
+
+/// Start of the hour or UTC day containing a point in time
+fn period_start(granularity: Granularity, time: DateTime<Utc>) -> DateTime<Utc> {
+    match granularity {
+        Granularity::Hour => time.date().and_hms(time.hour(), 0, 0),
+        Granularity::Day => time.date().and_hms(0, 0, 0),
+    }
+}
+
+/// Number of distinct users that sent messages between two days (inclusive)
+pub async fn unique_chatters(
+    pool: &DbPool,
+    channel_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<i64> {
+    let result = sql_query(
+        "select count(distinct user_id) count from chat_stats_user_daily \
+         where channel_id = $1 and day between $2 and $3;",
+    )
+    .bind::<Integer, _>(channel_id)
+    .bind::<Date, _>(from)
+    .bind::<Date, _>(to)
+    .get_result_async::<UniqueChatters>(pool)
+    .await?;
+    Ok(result.count)
+}
+
+/// Users with the most messages between two days (inclusive)
+pub async fn top_chatters(
+    pool: &DbPool,
+    channel_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+    limit: u32,
+) -> Result<Vec<ChatterCount>> {
+    sql_query(
+        "select s.user_id, coalesce(u.display_name, u.name) as name, \
+         sum(s.message_count)::int8 message_count \
+         from chat_stats_user_daily s \
+         join users u on u.id = s.user_id \
+         where s.channel_id = $1 and s.day between $2 and $3 \
+         group by s.user_id, u.display_name, u.name \
+         order by message_count desc \
+         limit $4;",
+    )
+    .bind::<Integer, _>(channel_id)
+    .bind::<Date, _>(from)
+    .bind::<Date, _>(to)
+    .bind::<BigInt, _>(i64::from(limit))
+    .load_async(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Number of executions per command between two days (inclusive)
+pub async fn command_usage(
+    pool: &DbPool,
+    channel_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<CommandUsageCount>> {
+    sql_query(
+        "select c.command_id, a.handler_name, sum(c.use_count)::int8 use_count \
+         from command_usage_daily c \
+         join command_attributes a on a.id = c.command_id \
+         where c.channel_id = $1 and c.day between $2 and $3 \
+         group by c.command_id, a.handler_name \
+         order by use_count desc;",
+    )
+    .bind::<Integer, _>(channel_id)
+    .bind::<Date, _>(from)
+    .bind::<Date, _>(to)
+    .load_async(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// New and returning chatters per day between two days (inclusive)
+pub async fn user_retention(
+    pool: &DbPool,
+    channel_id: i32,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<UserRetention>> {
+    sql_query(
+        "select s.day, \
+         count(*) filter (where f.first_day = s.day) new_users, \
+         count(*) filter (where f.first_day < s.day) returning_users \
+         from chat_stats_user_daily s \
+         join chat_stats_first_seen f on f.channel_id = s.channel_id and f.user_id = s.user_id \
+         where s.channel_id = $1 and s.day between $2 and $3 \
+         group by s.day \
+         order by s.day;",
+    )
+    .bind::<Integer, _>(channel_id)
+    .bind::<Date, _>(from)
+    .bind::<Date, _>(to)
+    .load_async(pool)
+    .await
+    .map_err(Into::into)
+}
+
+/// Message count, first and last seen time of a user in a channel. Days covered by the rollups
+/// are read from them, later messages are counted in the chat log. The first seen time falls back
+/// to the start of the first day if the first messages were removed from the log already.
+pub async fn user_chat_stats(
+    pool: &DbPool,
+    channel_id: i32,
+    user_id: i32,
+) -> Result<Option<UserChatStats>> {
+    sql_query(
+        "with refresh as (select (refreshed_until at time zone 'UTC')::date as day \
+                   from chat_stats_refresh), \
+         rollup as (select coalesce(sum(d.message_count), 0)::bigint message_count \
+                    from chat_stats_user_daily d, refresh r \
+                    where d.channel_id = $1 and d.user_id = $2 and d.day < r.day), \
+         recent as (select count(*) message_count from chat_events e, refresh r \
+                    where e.channel_id = $1 and e.sender_user_id = $2 and e.event_type = 'privmsg' \
+                    and e.received_at >= r.day::timestamp at time zone 'UTC'), \
+         first_message as (select min(e.received_at) received_at from chat_events e \
+                           where e.channel_id = $1 and e.sender_user_id = $2 \
+                           and e.event_type = 'privmsg'), \
+         last_event as (select max(e.received_at) received_at from chat_events e \
+                        where e.channel_id = $1 and e.sender_user_id = $2) \
+         select u.id user_id, u.name, u.display_name, \
+         rollup.message_count + recent.message_count message_count, \
+         case when f.first_day is not null \
+                   and (fm.received_at is null \
+                        or f.first_day < (fm.received_at at time zone 'UTC')::date) \
+              then f.first_day::timestamp at time zone 'UTC' \
+              else fm.received_at end first_seen, \
+         le.received_at last_seen, \
+         u.created_at known_since, \
+         extract(day from now() - u.created_at)::bigint known_since_days \
+         from users u \
+         cross join rollup cross join recent cross join first_message fm cross join last_event le \
+         left join chat_stats_first_seen f on f.channel_id = $1 and f.user_id = u.id \
+         where u.id = $2;",
+    )
+    .bind::<Integer, _>(channel_id)
+    .bind::<Integer, _>(user_id)
+    .get_result_async(pool)
+    .await
+    .optional()
+    .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::*;
+
+    #[test]
+    fn test_period_start() {
+        let time = Utc.ymd(2020, 1, 14).and_hms_micro(20, 17, 33, 42);
+        assert_eq!(
+            period_start(Granularity::Hour, time),
+            Utc.ymd(2020, 1, 14).and_hms(20, 0, 0)
+        );
+        assert_eq!(
+            period_start(Granularity::Day, time),
+            Utc.ymd(2020, 1, 14).and_hms(0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_period_start_on_boundary() {
+        let midnight = Utc.ymd(2020, 1, 15).and_hms(0, 0, 0);
+        assert_eq!(period_start(Granularity::Hour, midnight), midnight);
+        assert_eq!(period_start(Granularity::Day, midnight), midnight);
+    }
+
+    #[test]
+    fn test_period_start_uses_utc() {
+        // 23:30 in UTC-5 is already the next day in UTC
+        let time = FixedOffset::west(5 * 3600)
+            .ymd(2020, 1, 14)
+            .and_hms(23, 30, 0)
+            .with_timezone(&Utc);
+        assert_eq!(
+            period_start(Granularity::Day, time).naive_utc().date(),
+            NaiveDate::from_ymd(2020, 1, 15)
+        );
+        assert_eq!(
+            period_start(Granularity::Hour, time),
+            Utc.ymd(2020, 1, 15).and_hms(4, 0, 0)
+        );
+    }
+}
@@ -1,35 +1,68 @@
 use std::time::Duration;
 
 use chrono::{DateTime, FixedOffset, Local, Utc};
+use darkredis::{CommandList, Value as RedisValue};
 use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Integer, Nullable, Text};
 use serde::{Deserialize, Serialize};
-use tokio_diesel::AsyncRunQueryDsl;
+use tokio_diesel::{AsyncConnection, AsyncRunQueryDsl};
 
 use crate::cache::Cacheable;
 use crate::impl_redis_bincode_int;
+use crate::pagination::TotalCount;
 use crate::schema::users;
 use crate::DbContext;
 use crate::Result;
-use crate::{DbPool, Error};
+use crate::{DbPool, Error, OffsetParameters, RedisPool};
 
-#[derive(Queryable, Serialize, Deserialize, Debug)]
+/// Seconds a Discord link code can be used
+const DISCORD_LINK_SECONDS: u32 = 600;
+
+/// Get and delete a Discord link code, codes can only be used once
+const TAKE_DISCORD_LINK_SCRIPT: &str = r#"
+local discord_user_id = redis.call('GET', KEYS[1])
+if discord_user_id then
+    redis.call('DEL', KEYS[1])
+end
+return discord_user_id
+"#;
+
+/// Move the chat events and stats of a user (`$1`) to another user (`$2`) before deleting it
+const MERGE_USER_STATEMENTS: &[&str] = &[
+    "update chat_events set sender_user_id = $2 where sender_user_id = $1;",
+    "insert into chat_stats_user_daily (channel_id, day, user_id, message_count) \
+     select channel_id, day, $2, message_count from chat_stats_user_daily where user_id = $1 \
+     on conflict (channel_id, day, user_id) \
+     do update set message_count = chat_stats_user_daily.message_count + excluded.message_count;",
+    "insert into chat_stats_first_seen (channel_id, user_id, first_day) \
+     select channel_id, $2, first_day from chat_stats_first_seen where user_id = $1 \
+     on conflict (channel_id, user_id) \
+     do update set first_day = least(chat_stats_first_seen.first_day, excluded.first_day);",
+];
+
+#[derive(Queryable, QueryableByName, Serialize, Deserialize, Debug)]
+#[table_name = "users"]
 pub struct User {
     pub id: i32,
-    pub twitch_user_id: i32,
+    /// not set for users only known from Discord
+    pub twitch_user_id: Option<i32>,
     pub name: String,
     pub display_name: Option<String>,
     pub previous_names: Option<Vec<String>>,
     pub previous_display_names: Option<Vec<String>>,
     pub updated_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
+    pub discord_user_id: Option<i64>,
 }
 
 impl_redis_bincode_int!(User);
 
 #[derive(Insertable, Debug)]
 #[table_name = "users"]
-pub struct NewTwitchUser {
-    pub twitch_user_id: i32,
+pub struct NewChatUser {
+    pub twitch_user_id: Option<i32>,
+    pub discord_user_id: Option<i64>,
     pub name: String,
     pub display_name: Option<String>,
     pub previous_names: Option<Vec<String>>,
@@ -39,17 +72,39 @@ pub struct NewTwitchUser {
 
 #[derive(AsChangeset, Debug)]
 #[table_name = "users"]
-pub struct UpdateTwitchUser {
-    pub twitch_user_id: i32,
+pub struct UpdateChatUser {
     pub name: String,
     pub display_name: Option<String>,
     pub previous_names: Option<Vec<String>>,
     pub previous_display_names: Option<Vec<String>>,
 }
 
+/// ID of a user on the chat platform a message was received from
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum PlatformUserId {
+    Twitch(i32),
+    Discord(i64),
+}
+
+impl PlatformUserId {
+    pub fn twitch(self) -> Option<i32> {
+        match self {
+            PlatformUserId::Twitch(id) => Some(id),
+            PlatformUserId::Discord(_) => None,
+        }
+    }
+
+    pub fn discord(self) -> Option<i64> {
+        match self {
+            PlatformUserId::Twitch(_) => None,
+            PlatformUserId::Discord(id) => Some(id),
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct ChatUserInfo<'a> {
-    pub twitch_user_id: i32,
+    pub platform_id: PlatformUserId,
     pub name: &'a str,
     pub display_name: Option<&'a str>,
 }
@@ -62,7 +117,7 @@ impl ChatUserInfo<'_> {
 }
 
 pub struct OwnedChatUserInfo {
-    pub twitch_user_id: i32,
+    pub platform_id: PlatformUserId,
     pub name: String,
     pub display_name: Option<String>,
 }
@@ -70,20 +125,23 @@ pub struct OwnedChatUserInfo {
 impl From<&ChatUserInfo<'_>> for OwnedChatUserInfo {
     fn from(source: &ChatUserInfo<'_>) -> Self {
         OwnedChatUserInfo {
-            twitch_user_id: source.twitch_user_id,
+            platform_id: source.platform_id,
             name: source.name.to_string(),
             display_name: source.display_name.map(|s| s.to_string()),
         }
     }
 }
 
-impl Cacheable<i32> for User {
+impl Cacheable<PlatformUserId> for User {
     fn cache_key(&self) -> String {
-        format!("cb:user:{}", self.twitch_user_id)
+        Self::cache_key_from_id(self.platform_id())
     }
 
-    fn cache_key_from_id(id: i32) -> String {
-        format!("cb:user:{}", id)
+    fn cache_key_from_id(id: PlatformUserId) -> String {
+        match id {
+            PlatformUserId::Twitch(id) => format!("cb:user:{}", id),
+            PlatformUserId::Discord(id) => format!("cb:user:discord:{}", id),
+        }
     }
 
     fn cache_life(&self) -> Duration {
@@ -92,10 +150,20 @@ impl Cacheable<i32> for User {
 }
 
 impl User {
+    /// The platform ID used to identify the user, the Twitch ID if the user has one
+    pub fn platform_id(&self) -> PlatformUserId {
+        match (self.twitch_user_id, self.discord_user_id) {
+            (Some(twitch_id), _) => PlatformUserId::Twitch(twitch_id),
+            (None, Some(discord_id)) => PlatformUserId::Discord(discord_id),
+            (None, None) => unreachable!("users_platform_id_check constraint violated"),
+        }
+    }
+
     pub async fn get_or_insert(ctx: &DbContext, user_info: ChatUserInfo<'_>) -> Result<User> {
-        let user = Self::get(ctx, user_info.twitch_user_id).await;
+        let user = Self::get(ctx, user_info.platform_id).await;
         if let Ok(user) = user {
-            if !user_info.data_matches(&user) {
+            // linked users keep the names from Twitch
+            if user.platform_id() == user_info.platform_id && !user_info.data_matches(&user) {
                 let updated = Self::update(ctx, &user_info).await?;
                 Ok(updated)
             } else {
@@ -108,12 +176,12 @@ impl User {
         }
     }
 
-    pub async fn get(ctx: &DbContext, twitch_id: i32) -> Result<User> {
-        if let Some(cached) = User::cache_get(&ctx.redis_pool, twitch_id).await? {
+    pub async fn get(ctx: &DbContext, platform_id: PlatformUserId) -> Result<User> {
+        if let Some(cached) = User::cache_get(&ctx.redis_pool, platform_id).await? {
             trace!("Cache hit for user {}", cached.name);
             Ok(cached)
         } else {
-            let query_result = Self::get_no_cache(&ctx.db_pool, twitch_id).await;
+            let query_result = Self::get_no_cache(&ctx.db_pool, platform_id).await;
             if let Ok(user) = &query_result {
                 user.cache_set(&ctx.redis_pool).await?;
             }
@@ -122,22 +190,138 @@ impl User {
         }
     }
 
-    async fn get_no_cache(pool: &DbPool, twitch_id: i32) -> Result<User> {
+    /// Link a Discord account to a user. A user created for the Discord account before is merged
+    /// into this one: its chat events and stats are moved over, its permissions and API tokens are
+    /// deleted with it.
+    pub async fn link_discord(ctx: &DbContext, user_id: i32, discord_user_id: i64) -> Result<User> {
+        let user = ctx
+            .db_pool
+            .transaction(move |conn| {
+                let discord_user = users::table
+                    .filter(users::discord_user_id.eq(discord_user_id))
+                    .filter(users::id.ne(user_id))
+                    .select(users::id)
+                    .first::<i32>(conn)
+                    .optional()?;
+                if let Some(discord_user) = discord_user {
+                    for statement in MERGE_USER_STATEMENTS {
+                        sql_query(*statement)
+                            .bind::<Integer, _>(discord_user)
+                            .bind::<Integer, _>(user_id)
+                            .execute(conn)?;
+                    }
+                    diesel::delete(users::table.find(discord_user)).execute(conn)?;
+                }
+                diesel::update(users::table.find(user_id))
+                    .set(users::discord_user_id.eq(discord_user_id))
+                    .get_result::<User>(conn)
+            })
+            .await?;
+        ctx.redis_pool
+            .get()
+            .await
+            .del(User::cache_key_from_id(PlatformUserId::Discord(
+                discord_user_id,
+            )))
+            .await?;
+        user.cache_set(&ctx.redis_pool).await?;
+        Ok(user)
+    }
+
+    /// Get a user by the internal user ID
+    pub async fn get_by_id(pool: &DbPool, user_id: i32) -> Result<User> {
+        users::table
+            .find(user_id)
+            .first_async::<User>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get a user by their current name
+    pub async fn get_by_name(pool: &DbPool, name: &str) -> Result<User> {
+        let name = name.to_lowercase();
         users::table
-            .filter(users::twitch_user_id.eq(twitch_id))
+            .filter(users::name.eq(name))
             .first_async::<User>(pool)
             .await
             .map_err(Into::into)
     }
 
+    /// Get all users with a current name. Names are only unique per platform at a time, a renamed
+    /// user can keep a name somebody else has now until they chat again.
+    pub async fn list_by_name(pool: &DbPool, name: &str) -> Result<Vec<User>> {
+        let name = name.to_lowercase();
+        users::table
+            .filter(users::name.eq(name))
+            .order_by(users::id)
+            .load_async::<User>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// Get multiple users by their internal IDs
+    pub async fn get_many_by_id(pool: &DbPool, user_ids: Vec<i32>) -> Result<Vec<User>> {
+        users::table
+            .filter(users::id.eq_any(user_ids))
+            .load_async::<User>(pool)
+            .await
+            .map_err(Into::into)
+    }
+
+    /// List users ordered by name. If a search string is given, only users where the current or
+    /// any of the previous (display) names contain the string are returned.
+    pub async fn list(
+        pool: &DbPool,
+        search: Option<&str>,
+        slice: OffsetParameters,
+    ) -> Result<(u64, Vec<User>)> {
+        let pattern = search.map(|search| format!("%{}%", escape_like_pattern(search)));
+
+        let items = sql_query(format!(
+            "select * from users where {} order by name offset $2 limit $3",
+            USER_SEARCH_CONDITION
+        ))
+        .bind::<Nullable<Text>, _>(pattern.clone())
+        .bind::<Integer, _>(slice.offset() as i32)
+        .bind::<Integer, _>(slice.limit() as i32)
+        .load_async::<User>(pool)
+        .await?;
+
+        let total = sql_query(format!(
+            "select count(*) as count from users where {}",
+            USER_SEARCH_CONDITION
+        ))
+        .bind::<Nullable<Text>, _>(pattern)
+        .get_result_async::<TotalCount>(pool)
+        .await?;
+
+        Ok((total.count as u64, items))
+    }
+
+    async fn get_no_cache(pool: &DbPool, platform_id: PlatformUserId) -> Result<User> {
+        match platform_id {
+            PlatformUserId::Twitch(twitch_id) => users::table
+                .filter(users::twitch_user_id.eq(twitch_id))
+                .first_async::<User>(pool)
+                .await
+                .map_err(Into::into),
+            PlatformUserId::Discord(discord_id) => users::table
+                .filter(users::discord_user_id.eq(discord_id))
+                .first_async::<User>(pool)
+                .await
+                .map_err(Into::into),
+        }
+    }
+
     async fn update(ctx: &DbContext, user_info: &ChatUserInfo<'_>) -> Result<User> {
         let User {
+            id,
             name,
             display_name,
             mut previous_names,
             mut previous_display_names,
             ..
-        } = Self::get_no_cache(&ctx.db_pool, user_info.twitch_user_id).await?;
+        } = Self::get_no_cache(&ctx.db_pool, user_info.platform_id).await?;
 
         if user_info.name != name {
             previous_names
@@ -155,17 +339,15 @@ impl User {
 
         let user_info: OwnedChatUserInfo = user_info.into();
 
-        let updated_user =
-            diesel::update(users::table.filter(users::twitch_user_id.eq(user_info.twitch_user_id)))
-                .set(UpdateTwitchUser {
-                    twitch_user_id: user_info.twitch_user_id,
-                    name: user_info.name,
-                    display_name: user_info.display_name,
-                    previous_names,
-                    previous_display_names,
-                })
-                .get_result_async::<User>(&ctx.db_pool)
-                .await?;
+        let updated_user = diesel::update(users::table.find(id))
+            .set(UpdateChatUser {
+                name: user_info.name,
+                display_name: user_info.display_name,
+                previous_names,
+                previous_display_names,
+            })
+            .get_result_async::<User>(&ctx.db_pool)
+            .await?;
 
         updated_user.cache_set(&ctx.redis_pool).await?;
         Ok(updated_user)
@@ -174,8 +356,9 @@ impl User {
     async fn insert(ctx: &DbContext, user_info: &ChatUserInfo<'_>) -> Result<User> {
         let user_info: OwnedChatUserInfo = user_info.into();
         diesel::insert_into(users::table)
-            .values(NewTwitchUser {
-                twitch_user_id: user_info.twitch_user_id,
+            .values(NewChatUser {
+                twitch_user_id: user_info.platform_id.twitch(),
+                discord_user_id: user_info.platform_id.discord(),
                 name: user_info.name,
                 display_name: user_info.display_name,
                 previous_names: None,
@@ -187,3 +370,54 @@ impl User {
             .map_err(Into::into)
     }
 }
+
+fn discord_link_key(code: &str) -> String {
+    format!("cb:discord_link:{}", code)
+}
+
+/// Remember a code the owner of a Discord account can send on Twitch to link the accounts, see
+/// `User::link_discord`. Codes expire after 10 minutes.
+pub async fn save_discord_link_code(
+    pool: &RedisPool,
+    code: &str,
+    discord_user_id: i64,
+) -> Result<()> {
+    pool.get()
+        .await
+        .set_and_expire_seconds(
+            discord_link_key(code),
+            discord_user_id.to_string(),
+            DISCORD_LINK_SECONDS,
+        )
+        .await?;
+    Ok(())
+}
+
+/// Use a Discord link code, returns the ID of the Discord user it was created for
+pub async fn take_discord_link_code(pool: &RedisPool, code: &str) -> Result<Option<i64>> {
+    let key = discord_link_key(code);
+    let command = CommandList::new("EVAL")
+        .arg(&TAKE_DISCORD_LINK_SCRIPT)
+        .arg(b"1")
+        .arg(&key);
+    let result = pool.get().await.run_commands(command).await?;
+    match result.into_iter().next() {
+        Some(RedisValue::String(value)) => Ok(String::from_utf8_lossy(&value).parse().ok()),
+        _ => Ok(None),
+    }
+}
+
+/// Matches users by current and previous names, with the search pattern bound to `$1`
+const USER_SEARCH_CONDITION: &str = "$1::text is null \
+    or name ilike $1 \
+    or display_name ilike $1 \
+    or exists(select 1 from unnest(previous_names) n where n ilike $1) \
+    or exists(select 1 from unnest(previous_display_names) n where n ilike $1)";
+
+/// Escape the special characters of a `like` pattern
+fn escape_like_pattern(input: &str) -> String {
+    input
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
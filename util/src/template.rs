@@ -0,0 +1,59 @@
+//! Checks for user written Tera templates, shared by the bot and the web backend so templates
+//! are rejected when they are saved and not only when the bot loads them
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tera::Tera;
+use thiserror::Error;
+
+static FORBIDDEN_TAG: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{%-?\s*(include|extends|import)\b").unwrap());
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("`{0}` is not allowed in templates")]
+    ForbiddenTag(String),
+    #[error("{0}")]
+    Tera(#[from] tera::Error),
+}
+
+/// Name of the first tag that reaches into other templates, these are not allowed in user
+/// templates
+pub fn forbidden_tag(source: &str) -> Option<&str> {
+    FORBIDDEN_TAG
+        .captures(source)
+        .and_then(|captures| captures.get(1))
+        .map(|tag| tag.as_str())
+}
+
+/// Check that a template only uses allowed tags and compiles. Functions, filters and variables
+/// are resolved when rendering and not checked.
+pub fn check_template(source: &str) -> Result<(), TemplateError> {
+    if let Some(tag) = forbidden_tag(source) {
+        return Err(TemplateError::ForbiddenTag(tag.to_string()));
+    }
+    Tera::default().add_raw_template("template", source)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_forbidden_tag() {
+        assert_eq!(forbidden_tag("Hello {{ sender.name }}"), None);
+        assert_eq!(forbidden_tag(r#"{% include "12" %}"#), Some("include"));
+        assert_eq!(forbidden_tag(r#"{%- extends "12" %}"#), Some("extends"));
+        assert_eq!(forbidden_tag(r#"{%import "12" as macros %}"#), Some("import"));
+    }
+
+    #[test]
+    fn test_check_template() {
+        assert!(check_template("Thanks for the {{ months }} months, {{ sender.name }}!").is_ok());
+        assert!(check_template("{% for i in range(end=3) %}{{ i }}{% endfor %}").is_ok());
+        assert!(check_template("{% for i in range(end=3) %}{{ i }}").is_err());
+        assert!(check_template("{{ sender.name").is_err());
+        assert!(check_template(r#"{% include "12" %}"#).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::{web, FromRequest, HttpRequest};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use futures::FutureExt;
+
+use persistence::api_tokens::UserApiToken;
+use persistence::DbContext;
+
+use crate::config::Config;
+use crate::error::{ApiError, UserError};
+
+/// Extractor that only succeeds if the request carries the configured API token as bearer token.
+/// Add it as a handler argument to require authentication.
+#[derive(Debug)]
+pub struct ApiToken;
+
+impl FromRequest for ApiToken {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let expected = format!("Bearer {}", Config::get().api_token);
+        let authenticated = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, |value| value == expected);
+
+        if authenticated {
+            ready(Ok(ApiToken))
+        } else {
+            ready(Err(UserError::Unauthenticated.into()))
+        }
+    }
+}
+
+/// Extractor for the user owning the personal API token sent as bearer token, see
+/// `UserApiToken`. Used where permissions of a single user apply.
+#[derive(Debug)]
+pub struct AuthenticatedUser {
+    pub user_id: i32,
+}
+
+impl FromRequest for AuthenticatedUser {
+    type Error = ApiError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                if value.starts_with("Bearer ") {
+                    Some(value["Bearer ".len()..].to_string())
+                } else {
+                    None
+                }
+            });
+        let ctx = req.app_data::<web::Data<DbContext>>().cloned();
+
+        async move {
+            let (token, ctx) = match (token, ctx) {
+                (Some(token), Some(ctx)) => (token, ctx),
+                _ => return Err(UserError::Unauthenticated.into()),
+            };
+            match UserApiToken::user_id(&ctx.db_pool, &token).await {
+                Ok(Some(user_id)) => Ok(AuthenticatedUser { user_id }),
+                Ok(None) => Err(UserError::Unauthenticated.into()),
+                Err(err) => Err(err.into()),
+            }
+        }
+        .boxed_local()
+    }
+}
@@ -8,6 +8,8 @@ pub struct Config {
     pub app_url: String,
     pub twitch_client_id: String,
     pub twitch_client_secret: String,
+    /// bearer token required for endpoints that change data or control the bot
+    pub api_token: String,
 }
 
 static CONFIG: OnceCell<Config> = OnceCell::new();
@@ -24,6 +26,7 @@ impl Config {
             app_url: env::var("APP_URL").expect("redis address"),
             twitch_client_id: env::var("TWITCH_CLIENT_ID").expect("twitch client id"),
             twitch_client_secret: env::var("TWITCH_CLIENT_SECRET").expect("twitch client secret"),
+            api_token: env::var("API_TOKEN").expect("api token"),
         })
     }
 }
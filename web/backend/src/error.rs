@@ -19,6 +19,7 @@ impl From<persistence::Error> for ApiError {
     fn from(source: persistence::Error) -> Self {
         match source {
             persistence::Error::NotFound => ApiError::User(UserError::NotFound),
+            persistence::Error::Conflict => ApiError::User(UserError::Conflict),
             other_err => ApiError::Internal(InternalError::Persistence(other_err)),
         }
     }
@@ -39,6 +40,8 @@ pub enum InternalError {
     Io(#[from] std::io::Error),
     #[error("{0}")]
     Persistence(#[from] persistence::Error),
+    #[error("JSON serialization error: {0}")]
+    Json(#[from] serde_json::Error),
 }
 
 impl ResponseError for InternalError {
@@ -60,6 +63,12 @@ pub enum UserError {
     #[error("Not found")]
     NotFound,
 
+    #[error("Conflict")]
+    Conflict,
+
+    #[error("Bad request: {0}")]
+    BadRequest(&'static str),
+
     #[error("Unauthorized access")]
     Unauthorized,
 
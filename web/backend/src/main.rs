@@ -6,9 +6,11 @@ use crate::config::Config;
 use crate::error::ApiError;
 use actix_cors::Cors;
 
+mod auth;
 mod config;
 mod error;
 mod models;
+mod openapi;
 mod services;
 
 type ApiResult<T> = std::result::Result<T, ApiError>;
@@ -25,7 +27,7 @@ async fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
         App::new()
             .wrap(
                 Cors::new()
-                    .allowed_methods(vec!["GET", "POST", "PUT"])
+                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
                     .allowed_headers(vec![
                         http::header::AUTHORIZATION,
                         http::header::ACCEPT,
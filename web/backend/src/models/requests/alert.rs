@@ -0,0 +1,79 @@
+use std::borrow::Cow;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::{Validate, ValidationError};
+use validator_derive::Validate;
+
+use persistence::alerts::InsertChannelAlert;
+use util::template::check_template;
+
+use crate::models::responses::alert::ApiAlertKind;
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewAlertRequest {
+    pub alert_kind: ApiAlertKind,
+    /// the alert applies to events with at least this value (sub tier, months, gifts, viewers or bits)
+    #[validate(range(min = 0))]
+    pub min_value: Option<i32>,
+    #[validate(length(min = 1, max = 500), custom = "validate_template")]
+    pub template: String,
+    /// minimum time between two messages of the alert in milliseconds
+    #[validate(range(min = 0))]
+    pub min_interval: Option<i32>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+const fn default_enabled() -> bool {
+    true
+}
+
+/// Reject templates the bot would fail to load
+fn validate_template(template: &str) -> Result<(), ValidationError> {
+    check_template(template).map_err(|err| {
+        let mut error = ValidationError::new("template");
+        error.message = Some(Cow::Owned(err.to_string()));
+        error
+    })
+}
+
+impl NewAlertRequest {
+    pub fn into_insert(self, channel_id: i32) -> InsertChannelAlert {
+        InsertChannelAlert {
+            channel_id,
+            alert_kind: self.alert_kind.into(),
+            min_value: self.min_value,
+            template: self.template,
+            min_interval: self.min_interval,
+            enabled: self.enabled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn request(template: &str) -> NewAlertRequest {
+        NewAlertRequest {
+            alert_kind: ApiAlertKind::Sub,
+            min_value: None,
+            template: template.to_string(),
+            min_interval: None,
+            enabled: true,
+        }
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(request("Thanks for subscribing, {{ sender.name }}!")
+            .validate()
+            .is_ok());
+        for template in &["{{ sender.name", r#"{% include "alert:1" %}"#] {
+            let errors = request(template).validate().unwrap_err();
+            assert!(errors.field_errors().contains_key("template"));
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use persistence::control::BotAction;
+
+use crate::error::UserError;
+
+/// Maximum length of a chat message sent through the API
+const MAX_MESSAGE_LENGTH: usize = 500;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum BotActionRequest {
+    ReloadCommands,
+    ReloadPermissions,
+    ReloadTemplates,
+    JoinChannel {
+        channel: String,
+    },
+    PartChannel {
+        channel: String,
+    },
+    Say {
+        channel: String,
+        #[schemars(length(max = 500))]
+        message: String,
+    },
+}
+
+impl BotActionRequest {
+    pub fn validate(&self) -> Result<(), UserError> {
+        match self {
+            BotActionRequest::JoinChannel { channel }
+            | BotActionRequest::PartChannel { channel }
+                if channel.is_empty() =>
+            {
+                Err(UserError::BadRequest("Channel name is required."))
+            }
+            BotActionRequest::Say { channel, message } => {
+                if channel.is_empty() {
+                    Err(UserError::BadRequest("Channel name is required."))
+                } else if message.trim().is_empty() || message.len() > MAX_MESSAGE_LENGTH {
+                    Err(UserError::BadRequest(
+                        "Message must not be empty or longer than 500 bytes.",
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl From<BotActionRequest> for BotAction {
+    fn from(source: BotActionRequest) -> Self {
+        match source {
+            BotActionRequest::ReloadCommands => BotAction::ReloadCommands,
+            BotActionRequest::ReloadPermissions => BotAction::ReloadPermissions,
+            BotActionRequest::ReloadTemplates => BotAction::ReloadTemplates,
+            BotActionRequest::JoinChannel { channel } => BotAction::JoinChannel(channel),
+            BotActionRequest::PartChannel { channel } => BotAction::PartChannel(channel),
+            BotActionRequest::Say { channel, message } => BotAction::Say { channel, message },
+        }
+    }
+}
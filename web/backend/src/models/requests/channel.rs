@@ -0,0 +1,15 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionRequest {
+    /// days to keep chat logs, logs are kept forever if missing
+    #[validate(range(min = 1, max = 36500))]
+    pub log_retention_days: Option<i32>,
+    /// export expired chat logs to files before removing them
+    #[serde(default)]
+    pub archive_logs: bool,
+}
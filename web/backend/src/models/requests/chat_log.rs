@@ -0,0 +1,152 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+use persistence::chat_event::{ChatEventType, ChatLogCursor};
+
+use crate::error::UserError;
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatLogParams {
+    /// only include events received at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// only include events received before this time
+    pub to: Option<DateTime<Utc>>,
+    /// internal ID of the sender
+    pub user_id: Option<i32>,
+    /// current name of the sender
+    #[validate(length(min = 1, max = 200))]
+    pub user: Option<String>,
+    /// comma separated list of event types
+    pub event_type: Option<String>,
+    /// full text search query
+    #[validate(length(min = 1, max = 200))]
+    pub search: Option<String>,
+}
+
+impl ChatLogParams {
+    pub fn event_types(&self) -> Result<Vec<ChatEventType>, UserError> {
+        parse_event_types(self.event_type.as_deref())
+    }
+}
+
+/// Parse a comma separated list of event types, an empty list if no types are given
+pub fn parse_event_types(types: Option<&str>) -> Result<Vec<ChatEventType>, UserError> {
+    types
+        .map(|types| {
+            types
+                .split(',')
+                .map(|name| {
+                    ChatEventType::from_name(name.trim())
+                        .ok_or(UserError::BadRequest("Unknown event type."))
+                })
+                .collect()
+        })
+        .unwrap_or_else(|| Ok(vec![]))
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorParams {
+    /// cursor returned as `nextCursor` by the previous request
+    pub after: Option<String>,
+    /// maximum number of items
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 1000))]
+    pub limit: u32,
+}
+
+const fn default_limit() -> u32 {
+    100
+}
+
+impl CursorParams {
+    pub fn cursor(&self) -> Result<Option<ChatLogCursor>, UserError> {
+        self.after.as_deref().map(decode_cursor).transpose()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// one JSON object per line
+    Jsonl,
+    /// plain text IRC log
+    Text,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        ExportFormat::Jsonl
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportParams {
+    /// export format
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+/// Encode a log position into an opaque cursor string
+pub fn encode_cursor(cursor: &ChatLogCursor) -> String {
+    format!(
+        "{}~{}",
+        cursor
+            .received_at
+            .to_rfc3339_opts(SecondsFormat::Micros, true),
+        cursor.id
+    )
+}
+
+fn decode_cursor(cursor: &str) -> Result<ChatLogCursor, UserError> {
+    let invalid = || UserError::BadRequest("Invalid cursor.");
+    let separator = cursor.rfind('~').ok_or_else(invalid)?;
+    let (received_at, id) = cursor.split_at(separator);
+    Ok(ChatLogCursor {
+        received_at: DateTime::parse_from_rfc3339(received_at)
+            .map_err(|_| invalid())?
+            .with_timezone(&Utc),
+        id: id[1..].parse().map_err(|_| invalid())?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_cursor_roundtrip() {
+        let cursor = ChatLogCursor {
+            received_at: Utc.ymd(2020, 1, 12).and_hms_micro(15, 42, 11, 123_456),
+            id: 4711,
+        };
+        let encoded = encode_cursor(&cursor);
+        assert_eq!(encoded, "2020-01-12T15:42:11.123456Z~4711");
+        assert_eq!(decode_cursor(&encoded).unwrap(), cursor);
+    }
+
+    #[test]
+    fn test_invalid_cursor() {
+        assert!(decode_cursor("").is_err());
+        assert!(decode_cursor("4711").is_err());
+        assert!(decode_cursor("yesterday~4711").is_err());
+        assert!(decode_cursor("2020-01-12T15:42:11Z~").is_err());
+    }
+
+    #[test]
+    fn test_event_types() {
+        assert!(parse_event_types(None).unwrap().is_empty());
+        assert_eq!(
+            parse_event_types(Some("privmsg, clearchat")).unwrap(),
+            vec![ChatEventType::Privmsg, ChatEventType::Clearchat]
+        );
+        assert!(parse_event_types(Some("privmsg,nonsense")).is_err());
+    }
+}
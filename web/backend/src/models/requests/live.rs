@@ -0,0 +1,20 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use persistence::chat_event::ChatEventType;
+
+use crate::error::UserError;
+use crate::models::requests::chat_log::parse_event_types;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveFeedParams {
+    /// comma separated list of chat event types
+    pub event_type: Option<String>,
+}
+
+impl LiveFeedParams {
+    pub fn event_types(&self) -> Result<Vec<ChatEventType>, UserError> {
+        parse_event_types(self.event_type.as_deref())
+    }
+}
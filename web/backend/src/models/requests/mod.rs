@@ -1 +1,9 @@
+pub mod alert;
+pub mod bot;
+pub mod channel;
+pub mod chat_log;
+pub mod live;
 pub mod pagination;
+pub mod permission;
+pub mod stats;
+pub mod user;
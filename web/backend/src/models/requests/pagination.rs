@@ -1,16 +1,19 @@
 use std::cmp::max;
 
+use schemars::JsonSchema;
 use serde::Deserialize;
 use validator::Validate;
 use validator_derive::Validate;
 
 use persistence::OffsetParameters;
 
-#[derive(Debug, Clone, Deserialize, Validate)]
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PaginationParams {
+    /// requested page number, starting at 0
     #[serde(default = "default_page")]
     pub page: u32,
+    /// items per page
     #[serde(default = "default_per_page")]
     pub per_page: u32,
 }
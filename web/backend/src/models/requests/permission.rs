@@ -0,0 +1,38 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+use persistence::permissions::NewPermission;
+
+use crate::models::responses::permission::ApiPermissionState;
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewPermissionRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub default_state: ApiPermissionState,
+    /// IDs of the permissions that should imply the new permission
+    #[serde(default)]
+    pub implied_by: Vec<i32>,
+}
+
+impl From<NewPermissionRequest> for NewPermission {
+    fn from(source: NewPermissionRequest) -> Self {
+        NewPermission {
+            name: source.name,
+            description: source.description,
+            default_state: source.default_state.into(),
+            implied_by: source.implied_by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetPermissionStateRequest {
+    pub state: ApiPermissionState,
+}
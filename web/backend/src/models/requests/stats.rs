@@ -0,0 +1,83 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+use persistence::stats::Granularity;
+
+use crate::error::UserError;
+
+/// Time range covered by statistics if not given in the request
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsRangeParams {
+    /// start of the time range, defaults to 30 days before `to`
+    pub from: Option<DateTime<Utc>>,
+    /// end of the time range, defaults to now
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl StatsRangeParams {
+    pub fn range(&self) -> Result<(DateTime<Utc>, DateTime<Utc>), UserError> {
+        let to = self.to.unwrap_or_else(Utc::now);
+        let from = self
+            .from
+            .unwrap_or_else(|| to - Duration::days(DEFAULT_RANGE_DAYS));
+        if from >= to {
+            return Err(UserError::BadRequest("Start of range must be before its end."));
+        }
+        Ok((from, to))
+    }
+
+    /// The time range as days, including the days of the start and end
+    pub fn day_range(&self) -> Result<(NaiveDate, NaiveDate), UserError> {
+        let (from, to) = self.range()?;
+        Ok((from.naive_utc().date(), to.naive_utc().date()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ApiGranularity {
+    Hour,
+    Day,
+}
+
+impl Default for ApiGranularity {
+    fn default() -> Self {
+        ApiGranularity::Day
+    }
+}
+
+impl From<ApiGranularity> for Granularity {
+    fn from(granularity: ApiGranularity) -> Self {
+        match granularity {
+            ApiGranularity::Hour => Granularity::Hour,
+            ApiGranularity::Day => Granularity::Day,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageStatsParams {
+    /// resolution of the time series
+    #[serde(default)]
+    pub granularity: ApiGranularity,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TopChattersParams {
+    /// number of top chatters to include
+    #[serde(default = "default_limit")]
+    #[validate(range(min = 1, max = 100))]
+    pub limit: u32,
+}
+
+const fn default_limit() -> u32 {
+    10
+}
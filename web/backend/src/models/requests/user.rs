@@ -0,0 +1,12 @@
+use schemars::JsonSchema;
+use serde::Deserialize;
+use validator::Validate;
+use validator_derive::Validate;
+
+#[derive(Debug, Clone, Deserialize, Validate, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserSearchParams {
+    /// matched against current and previous user names
+    #[validate(length(min = 1, max = 200))]
+    pub search: Option<String>,
+}
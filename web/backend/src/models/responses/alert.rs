@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use persistence::alerts::{AlertKind, ChannelAlert};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "alertKind")]
+pub enum ApiAlertKind {
+    Sub,
+    Resub,
+    Subgift,
+    Giftbomb,
+    Raid,
+    Cheer,
+}
+
+impl From<AlertKind> for ApiAlertKind {
+    fn from(kind: AlertKind) -> Self {
+        match kind {
+            AlertKind::Sub => ApiAlertKind::Sub,
+            AlertKind::Resub => ApiAlertKind::Resub,
+            AlertKind::Subgift => ApiAlertKind::Subgift,
+            AlertKind::Giftbomb => ApiAlertKind::Giftbomb,
+            AlertKind::Raid => ApiAlertKind::Raid,
+            AlertKind::Cheer => ApiAlertKind::Cheer,
+        }
+    }
+}
+
+impl From<ApiAlertKind> for AlertKind {
+    fn from(kind: ApiAlertKind) -> Self {
+        match kind {
+            ApiAlertKind::Sub => AlertKind::Sub,
+            ApiAlertKind::Resub => AlertKind::Resub,
+            ApiAlertKind::Subgift => AlertKind::Subgift,
+            ApiAlertKind::Giftbomb => AlertKind::Giftbomb,
+            ApiAlertKind::Raid => AlertKind::Raid,
+            ApiAlertKind::Cheer => AlertKind::Cheer,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "channelAlert")]
+pub struct ApiChannelAlert {
+    pub id: i32,
+    pub channel_id: i32,
+    pub alert_kind: ApiAlertKind,
+    pub min_value: Option<i32>,
+    pub template: String,
+    /// minimum time between two messages of the alert in milliseconds
+    pub min_interval: Option<u64>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<ChannelAlert> for ApiChannelAlert {
+    fn from(source: ChannelAlert) -> Self {
+        ApiChannelAlert {
+            id: source.id,
+            channel_id: source.channel_id,
+            alert_kind: source.alert_kind.into(),
+            min_value: source.min_value,
+            template: source.template,
+            min_interval: source.min_interval.map(|d| d.as_millis() as u64),
+            enabled: source.enabled,
+            created_at: source.created_at,
+        }
+    }
+}
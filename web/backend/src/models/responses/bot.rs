@@ -0,0 +1,44 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use persistence::chat_event::QueueStats;
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "botActionResponse")]
+pub struct BotActionResponse {
+    /// number of bot processes that received the action
+    pub receivers: isize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "queueStats")]
+pub struct ApiQueueStats {
+    /// events waiting to be persisted
+    pub queued: isize,
+    /// events of a batch that is being persisted or waiting for a retry
+    pub processing: isize,
+    /// undecodable or rejected events moved to the dead letter list
+    pub dead_lettered: isize,
+    pub persisted_total: i64,
+    pub failed_batches: i64,
+    /// failed attempts to insert the current batch, its events are inserted one by one after 5
+    pub batch_attempts: i64,
+    /// error of the last failed batch insert
+    pub last_error: Option<String>,
+}
+
+impl From<QueueStats> for ApiQueueStats {
+    fn from(source: QueueStats) -> Self {
+        ApiQueueStats {
+            queued: source.queued,
+            processing: source.processing,
+            dead_lettered: source.dead_lettered,
+            persisted_total: source.persisted_total,
+            failed_batches: source.failed_batches,
+            batch_attempts: source.batch_attempts,
+            last_error: source.last_error,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use persistence::retention::ChannelStorage;
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "channelStorage")]
+pub struct ApiChannelStorage {
+    pub channel_id: i32,
+    pub channel_name: String,
+    pub log_retention_days: Option<i32>,
+    /// size of all chat log partitions including indexes
+    pub total_bytes: i64,
+    pub partition_count: i64,
+    /// estimated number of logged events
+    pub estimated_events: i64,
+}
+
+impl From<ChannelStorage> for ApiChannelStorage {
+    fn from(source: ChannelStorage) -> Self {
+        ApiChannelStorage {
+            channel_id: source.channel_id,
+            channel_name: source.channel_name,
+            log_retention_days: source.log_retention_days,
+            total_bytes: source.total_bytes,
+            partition_count: source.partition_count,
+            estimated_events: source.estimated_events,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use persistence::chat_event::{ChatEvent, Tags, UserNoticeKind};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorListResponse<T> {
+    pub items: Vec<T>,
+    /// cursor to request the following items, missing if there are no more items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "chatEvent")]
+pub struct ApiChatEvent {
+    pub id: i64,
+    pub event_type: &'static str,
+    pub twitch_message_id: Option<String>,
+    pub message: Option<String>,
+    pub channel_id: Option<i32>,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: Option<String>,
+    #[schemars(with = "Option<HashMap<String, String>>")]
+    pub tags: Option<Tags>,
+    pub received_at: DateTime<Utc>,
+    /// structured values of usernotice events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_notice: Option<ApiUserNotice>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "userNotice")]
+pub struct ApiUserNotice {
+    #[schemars(with = "String")]
+    pub kind: UserNoticeKind,
+    pub sub_plan: Option<String>,
+    pub months: Option<i32>,
+    pub recipient_name: Option<String>,
+    pub gift_count: Option<i32>,
+    pub viewer_count: Option<i32>,
+    pub bits_threshold: Option<i32>,
+    pub ritual_name: Option<String>,
+}
+
+impl ApiChatEvent {
+    pub fn new(event: ChatEvent, sender_name: Option<String>) -> Self {
+        // a match instead of `map`, a closure would move the whole event
+        let user_notice = match event.notice_kind {
+            Some(kind) => Some(ApiUserNotice {
+                kind,
+                sub_plan: event.sub_plan,
+                months: event.months,
+                recipient_name: event.recipient_name,
+                gift_count: event.gift_count,
+                viewer_count: event.viewer_count,
+                bits_threshold: event.bits_threshold,
+                ritual_name: event.ritual_name,
+            }),
+            None => None,
+        };
+        ApiChatEvent {
+            id: event.id,
+            event_type: event.event_type.as_str(),
+            twitch_message_id: event.twitch_message_id.map(|id| id.to_string()),
+            message: event.message,
+            channel_id: event.channel_id,
+            sender_user_id: event.sender_user_id,
+            sender_name,
+            tags: event.tags,
+            received_at: event.received_at,
+            user_notice,
+        }
+    }
+
+    /// Format the event as a line in a plain text IRC log
+    pub fn to_irc_line(&self) -> String {
+        let time = self.received_at.format("%Y-%m-%d %H:%M:%S");
+        let sender = self.sender_name.as_deref().unwrap_or("*");
+        let message = self.message.as_deref().unwrap_or("");
+        if self.event_type == "privmsg" {
+            format!("[{}] <{}> {}", time, sender, message)
+        } else {
+            format!("[{}] -!- {} {}: {}", time, self.event_type, sender, message)
+        }
+    }
+}
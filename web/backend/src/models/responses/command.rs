@@ -1,17 +1,20 @@
 use persistence::commands::attributes::{CommandAttributes, CommandDetails, CommandWithAliases};
 use persistence::commands::channel_config::ChannelCommandConfigNamed;
 use persistence::commands::templates::CommandTemplate;
+use schemars::JsonSchema;
 use serde::Serialize;
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "command")]
 pub struct ApiCommand {
     #[serde(flatten)]
     pub attributes: ApiCommandAttributes,
     pub aliases: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(rename = "detailedCommand")]
 pub struct ApiDetailedCommand {
     #[serde(flatten)]
     pub attributes: ApiCommandAttributes,
@@ -21,17 +24,20 @@ pub struct ApiDetailedCommand {
     pub channel_config: Vec<ApiChannelCommandConfig>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(rename = "channelCommandConfig")]
 pub struct ApiChannelCommandConfig {
     pub channel_id: i32,
     pub channel_name: String,
     pub active: Option<bool>,
+    /// overrides the cooldown of the command, in milliseconds
     pub cooldown: Option<u64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(rename = "commandTemplate")]
 pub struct ApiCommandTemplate {
     pub template: Option<String>,
     pub template_context: Option<serde_json::Value>,
@@ -74,8 +80,9 @@ impl From<(CommandDetails, Vec<ChannelCommandConfigNamed>)> for ApiDetailedComma
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(rename = "commandAttributes")]
 pub struct ApiCommandAttributes {
     pub id: i32,
     /// User facing description
@@ -86,7 +93,7 @@ pub struct ApiCommandAttributes {
     pub enabled: bool,
     /// whether the command is active by default in all channels
     pub default_active: bool,
-    /// minimum time between command uses
+    /// minimum time between command uses in milliseconds
     pub cooldown: Option<u64>,
     /// whether the command can be used in whispers
     pub whisper_enabled: bool,
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use persistence::chat_event::Tags;
+use persistence::live_events::{LiveChatEvent, LiveCommandEvent, LiveEvent};
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ApiLiveEvent {
+    Chat(ApiLiveChatEvent),
+    CommandExecuted(ApiLiveCommandEvent),
+}
+
+impl ApiLiveEvent {
+    /// Name of the server-sent event
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            ApiLiveEvent::Chat(_) => "chat",
+            ApiLiveEvent::CommandExecuted(_) => "command",
+        }
+    }
+}
+
+impl From<LiveEvent> for ApiLiveEvent {
+    fn from(event: LiveEvent) -> Self {
+        match event {
+            LiveEvent::Chat(event) => ApiLiveEvent::Chat(event.into()),
+            LiveEvent::CommandExecuted(event) => ApiLiveEvent::CommandExecuted(event.into()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiLiveChatEvent {
+    pub event_type: &'static str,
+    pub channel_id: i32,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: Option<String>,
+    pub message: Option<String>,
+    pub tags: Option<Tags>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl From<LiveChatEvent> for ApiLiveChatEvent {
+    fn from(event: LiveChatEvent) -> Self {
+        ApiLiveChatEvent {
+            event_type: event.event_type.as_str(),
+            channel_id: event.channel_id,
+            sender_user_id: event.sender_user_id,
+            sender_name: event.sender_name,
+            message: event.message,
+            tags: event.tags,
+            received_at: event.received_at.with_timezone(&Utc),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiLiveCommandEvent {
+    pub channel_id: i32,
+    pub command_id: i32,
+    pub command_name: String,
+    pub sender_user_id: Option<i32>,
+    pub sender_name: Option<String>,
+    pub executed_at: DateTime<Utc>,
+}
+
+impl From<LiveCommandEvent> for ApiLiveCommandEvent {
+    fn from(event: LiveCommandEvent) -> Self {
+        ApiLiveCommandEvent {
+            channel_id: event.channel_id,
+            command_id: event.command_id,
+            command_name: event.command_name,
+            sender_user_id: event.sender_user_id,
+            sender_name: event.sender_name,
+            executed_at: event.executed_at.with_timezone(&Utc),
+        }
+    }
+}
@@ -1,3 +1,11 @@
+pub mod alert;
+pub mod bot;
+pub mod channel;
+pub mod chat_log;
 pub mod command;
 pub mod list;
+pub mod live;
+pub mod permission;
 pub mod problem_details;
+pub mod stats;
+pub mod user;
@@ -0,0 +1,83 @@
+use persistence::permissions::{EffectivePermission, PermissionState, PermissionWithImplied};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "permissionState")]
+pub enum ApiPermissionState {
+    Allow,
+    Deny,
+}
+
+impl From<PermissionState> for ApiPermissionState {
+    fn from(state: PermissionState) -> Self {
+        match state {
+            PermissionState::Allow => ApiPermissionState::Allow,
+            PermissionState::Deny => ApiPermissionState::Deny,
+        }
+    }
+}
+
+impl From<ApiPermissionState> for PermissionState {
+    fn from(state: ApiPermissionState) -> Self {
+        match state {
+            ApiPermissionState::Allow => PermissionState::Allow,
+            ApiPermissionState::Deny => PermissionState::Deny,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "permission")]
+pub struct ApiPermission {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub default_state: ApiPermissionState,
+    /// IDs of the permissions that imply this permission
+    pub implied_by: Vec<i32>,
+}
+
+impl From<PermissionWithImplied> for ApiPermission {
+    fn from(source: PermissionWithImplied) -> Self {
+        ApiPermission {
+            id: source.permission.id,
+            name: source.permission.name,
+            description: source.permission.description,
+            default_state: source.permission.default_state.into(),
+            implied_by: source.implied_by,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "userPermission")]
+pub struct ApiUserPermission {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub default_state: ApiPermissionState,
+    /// state explicitly set for the user, overrides the default state
+    pub user_state: Option<ApiPermissionState>,
+    /// whether the user effectively holds this permission
+    pub granted: bool,
+    /// IDs of permissions held by the user that imply this permission
+    pub granted_via: Vec<i32>,
+}
+
+impl From<EffectivePermission> for ApiUserPermission {
+    fn from(source: EffectivePermission) -> Self {
+        ApiUserPermission {
+            id: source.permission.id,
+            name: source.permission.name,
+            description: source.permission.description,
+            default_state: source.permission.default_state.into(),
+            user_state: source.user_state.map(Into::into),
+            granted: source.granted,
+            granted_via: source.granted_via,
+        }
+    }
+}
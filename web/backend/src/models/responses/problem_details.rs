@@ -1,11 +1,13 @@
 use std::borrow::Cow;
 
 use actix_web::error::JsonPayloadError;
+use schemars::JsonSchema;
 use serde::Serialize;
 
 use crate::error::{error_type, UserError};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "problemDetails")]
 pub struct ProblemDetails<'a> {
     #[serde(rename = "type", skip_serializing_if = "str::is_empty")]
     pub error_type: &'static str,
@@ -14,6 +16,7 @@ pub struct ProblemDetails<'a> {
     pub details: Option<Cow<'static, str>>,
     pub status: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<serde_json::Map<String, serde_json::Value>>")]
     pub validation_errors: Option<&'a validator::ValidationErrors>,
 }
 
@@ -48,6 +51,13 @@ impl<'a> From<&'a UserError> for ProblemDetails<'a> {
                 status: 400,
                 validation_errors: Some(err),
             },
+            UserError::BadRequest(details) => ProblemDetails {
+                error_type: "",
+                title: "Bad Request",
+                details: Some((*details).into()),
+                status: 400,
+                validation_errors: None,
+            },
             UserError::Json(err) => err.into(),
             UserError::NotFound => ProblemDetails {
                 error_type: error_type("not_found"),
@@ -56,6 +66,13 @@ impl<'a> From<&'a UserError> for ProblemDetails<'a> {
                 status: 404,
                 validation_errors: None,
             },
+            UserError::Conflict => ProblemDetails {
+                error_type: error_type("conflict"),
+                title: "Conflict",
+                details: Some("An item with the same unique values already exists.".into()),
+                status: 409,
+                validation_errors: None,
+            },
         }
     }
 }
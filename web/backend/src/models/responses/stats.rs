@@ -0,0 +1,90 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use persistence::stats::{ChatterCount, CommandUsageCount, MessageCount, UserRetention};
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "messageCount")]
+pub struct ApiMessageCount {
+    pub period: DateTime<Utc>,
+    pub message_count: i64,
+    pub chatter_count: i64,
+}
+
+impl From<MessageCount> for ApiMessageCount {
+    fn from(source: MessageCount) -> Self {
+        ApiMessageCount {
+            period: source.period,
+            message_count: source.message_count,
+            chatter_count: source.chatter_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "chatterStats")]
+pub struct ApiChatterStats {
+    /// number of distinct users that sent messages
+    pub unique_chatters: i64,
+    pub top_chatters: Vec<ApiChatterCount>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "chatterCount")]
+pub struct ApiChatterCount {
+    pub user_id: i32,
+    pub name: String,
+    pub message_count: i64,
+}
+
+impl From<ChatterCount> for ApiChatterCount {
+    fn from(source: ChatterCount) -> Self {
+        ApiChatterCount {
+            user_id: source.user_id,
+            name: source.name,
+            message_count: source.message_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "commandUsage")]
+pub struct ApiCommandUsage {
+    pub command_id: i32,
+    pub handler_name: String,
+    pub use_count: i64,
+}
+
+impl From<CommandUsageCount> for ApiCommandUsage {
+    fn from(source: CommandUsageCount) -> Self {
+        ApiCommandUsage {
+            command_id: source.command_id,
+            handler_name: source.handler_name,
+            use_count: source.use_count,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "userRetention")]
+pub struct ApiUserRetention {
+    pub day: NaiveDate,
+    pub new_users: i64,
+    pub returning_users: i64,
+}
+
+impl From<UserRetention> for ApiUserRetention {
+    fn from(source: UserRetention) -> Self {
+        ApiUserRetention {
+            day: source.day,
+            new_users: source.new_users,
+            returning_users: source.returning_users,
+        }
+    }
+}
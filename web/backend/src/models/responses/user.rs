@@ -0,0 +1,35 @@
+use chrono::{DateTime, Utc};
+use persistence::user::User;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[schemars(rename = "user")]
+pub struct ApiUser {
+    pub id: i32,
+    pub twitch_user_id: Option<i32>,
+    pub discord_user_id: Option<i64>,
+    pub name: String,
+    pub display_name: Option<String>,
+    pub previous_names: Vec<String>,
+    pub previous_display_names: Vec<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<User> for ApiUser {
+    fn from(source: User) -> Self {
+        ApiUser {
+            id: source.id,
+            twitch_user_id: source.twitch_user_id,
+            discord_user_id: source.discord_user_id,
+            name: source.name,
+            display_name: source.display_name,
+            previous_names: source.previous_names.unwrap_or_default(),
+            previous_display_names: source.previous_display_names.unwrap_or_default(),
+            updated_at: source.updated_at,
+            created_at: source.created_at,
+        }
+    }
+}
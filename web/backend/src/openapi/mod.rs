@@ -0,0 +1,538 @@
+//! OpenAPI document describing the API, generated from the request and response models
+
+use once_cell::sync::Lazy;
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+
+use crate::models::requests::alert::NewAlertRequest;
+use crate::models::requests::bot::BotActionRequest;
+use crate::models::requests::channel::RetentionRequest;
+use crate::models::requests::chat_log::{ChatLogParams, CursorParams, ExportParams};
+use crate::models::requests::live::LiveFeedParams;
+use crate::models::requests::pagination::PaginationParams;
+use crate::models::requests::permission::{NewPermissionRequest, SetPermissionStateRequest};
+use crate::models::requests::stats::{MessageStatsParams, StatsRangeParams, TopChattersParams};
+use crate::models::requests::user::UserSearchParams;
+use crate::models::responses::alert::ApiChannelAlert;
+use crate::models::responses::bot::{ApiQueueStats, BotActionResponse};
+use crate::models::responses::channel::ApiChannelStorage;
+use crate::models::responses::chat_log::{ApiChatEvent, CursorListResponse};
+use crate::models::responses::command::{ApiCommand, ApiDetailedCommand};
+use crate::models::responses::list::ListResponse;
+use crate::models::responses::permission::{ApiPermission, ApiUserPermission};
+use crate::models::responses::problem_details::ProblemDetails;
+use crate::models::responses::stats::{
+    ApiChatterStats, ApiCommandUsage, ApiMessageCount, ApiUserRetention,
+};
+use crate::models::responses::user::ApiUser;
+
+/// The generated document, built on first use
+static SPEC: Lazy<Value> = Lazy::new(build_spec);
+
+pub fn spec() -> &'static Value {
+    &SPEC
+}
+
+fn build_spec() -> Value {
+    // named schemas of all operations end up in the definitions of the generator
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+    for operation in operations() {
+        let method = operation.method;
+        let path = paths
+            .entry(operation.path)
+            .or_insert_with(|| json!({}));
+        path[method] = operation.into_value(&mut generator);
+    }
+
+    json!({
+        "openapi": "3.0.0",
+        "servers": [
+            { "description": "Local testing", "url": "http://localhost:3001/api/1.0" },
+            { "description": "Cerebot API", "url": "https://cere.ws/api/1.0" },
+        ],
+        "info": {
+            "version": "1.0",
+            "title": "Cerebot API",
+            "description": "Cerebot API for use by the web ui or other applications",
+        },
+        "paths": paths,
+        "components": {
+            "schemas": generator.definitions(),
+            "securitySchemes": {
+                "apiToken": { "type": "http", "scheme": "bearer" },
+                "userToken": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Personal API token of a user, created with `cerebot2 token create`",
+                },
+            },
+        },
+    })
+}
+
+/// All operations served under `/api/1.0`. Has to be kept in sync with `services::web_config`.
+fn operations() -> Vec<Operation> {
+    vec![
+        Operation::new("post", "/bot/actions", "createBotAction", "bot")
+            .summary("Send an action to the running bot")
+            .authenticated()
+            .request_body::<BotActionRequest>()
+            .response::<BotActionResponse>(202),
+        Operation::new("get", "/bot/queue", "getQueueStats", "bot")
+            .summary("Depth and counters of the chat event persistence queue")
+            .response::<ApiQueueStats>(200),
+        Operation::new("get", "/channels/storage", "getChannelStorage", "channels")
+            .summary("Disk space used by the chat logs of each channel")
+            .response::<Vec<ApiChannelStorage>>(200),
+        Operation::new("put", "/channels/{id}/retention", "setChannelRetention", "channels")
+            .summary("Set the chat log retention policy of a channel")
+            .authenticated()
+            .path_param("id")
+            .request_body::<RetentionRequest>()
+            .empty_response(204)
+            .not_found(),
+        Operation::new("get", "/channels/{id}/alerts", "getChannelAlerts", "alerts")
+            .summary("List the chat alerts of a channel")
+            .path_param("id")
+            .response::<Vec<ApiChannelAlert>>(200),
+        Operation::new("post", "/channels/{id}/alerts", "createChannelAlert", "alerts")
+            .summary("Create a chat alert for a channel")
+            .authenticated()
+            .path_param("id")
+            .request_body::<NewAlertRequest>()
+            .response::<ApiChannelAlert>(201)
+            .not_found(),
+        Operation::new(
+            "delete",
+            "/channels/{id}/alerts/{alert_id}",
+            "deleteChannelAlert",
+            "alerts",
+        )
+        .summary("Delete a chat alert")
+        .authenticated()
+        .path_param("id")
+        .path_param("alert_id")
+        .empty_response(204)
+        .not_found(),
+        Operation::new("get", "/channels/{id}/logs", "getChatLogs", "chatLogs")
+            .summary("Search the chat log of a channel")
+            .authenticated()
+            .path_param("id")
+            .parameters::<ChatLogParams>()
+            .parameters::<CursorParams>()
+            .response::<CursorListResponse<ApiChatEvent>>(200)
+            .not_found(),
+        Operation::new("get", "/channels/{id}/logs/export", "exportChatLogs", "chatLogs")
+            .summary("Export the chat log of a channel")
+            .authenticated()
+            .path_param("id")
+            .parameters::<ChatLogParams>()
+            .parameters::<ExportParams>()
+            .raw_response(200, "application/x-ndjson", string())
+            .not_found(),
+        Operation::new("get", "/channels/{id}/live", "getLiveFeed", "chatLogs")
+            .summary("Stream live events of a channel as server-sent events")
+            .user_authenticated()
+            .path_param("id")
+            .parameters::<LiveFeedParams>()
+            .raw_response(200, "text/event-stream", string())
+            .not_found(),
+        Operation::new("get", "/channels/{id}/stats/messages", "getMessageStats", "stats")
+            .summary("Message and chatter counts per hour or day")
+            .authenticated()
+            .path_param("id")
+            .parameters::<StatsRangeParams>()
+            .parameters::<MessageStatsParams>()
+            .response::<Vec<ApiMessageCount>>(200)
+            .not_found(),
+        Operation::new("get", "/channels/{id}/stats/chatters", "getChatterStats", "stats")
+            .summary("Unique and top chatters")
+            .authenticated()
+            .path_param("id")
+            .parameters::<StatsRangeParams>()
+            .parameters::<TopChattersParams>()
+            .response::<ApiChatterStats>(200)
+            .not_found(),
+        Operation::new("get", "/channels/{id}/stats/commands", "getCommandStats", "stats")
+            .summary("Command usage counts")
+            .authenticated()
+            .path_param("id")
+            .parameters::<StatsRangeParams>()
+            .response::<Vec<ApiCommandUsage>>(200)
+            .not_found(),
+        Operation::new("get", "/channels/{id}/stats/retention", "getRetentionStats", "stats")
+            .summary("New and returning chatters per day")
+            .authenticated()
+            .path_param("id")
+            .parameters::<StatsRangeParams>()
+            .response::<Vec<ApiUserRetention>>(200)
+            .not_found(),
+        Operation::new("get", "/commands", "getCommands", "commands")
+            .summary("List commands")
+            .parameters::<PaginationParams>()
+            .response::<ListResponse<ApiCommand>>(200),
+        Operation::new("get", "/commands/{id}", "getCommand", "commands")
+            .summary("Get details for a bot command")
+            .path_param("id")
+            .response::<ApiDetailedCommand>(200)
+            .not_found(),
+        Operation::new("get", "/permissions", "getPermissions", "permissions")
+            .summary("List permissions")
+            .response::<Vec<ApiPermission>>(200),
+        Operation::new("post", "/permissions", "createPermission", "permissions")
+            .summary("Create a permission")
+            .authenticated()
+            .request_body::<NewPermissionRequest>()
+            .response::<ApiPermission>(201)
+            .conflict(),
+        Operation::new("get", "/users", "getUsers", "users")
+            .summary("List users")
+            .authenticated()
+            .parameters::<UserSearchParams>()
+            .parameters::<PaginationParams>()
+            .response::<ListResponse<ApiUser>>(200),
+        Operation::new("get", "/users/{id}", "getUser", "users")
+            .summary("Get a user")
+            .authenticated()
+            .path_param("id")
+            .response::<ApiUser>(200)
+            .not_found(),
+        Operation::new("get", "/users/{id}/permissions", "getUserPermissions", "users")
+            .summary("List the effective permissions of a user")
+            .authenticated()
+            .path_param("id")
+            .response::<Vec<ApiUserPermission>>(200)
+            .not_found(),
+        Operation::new(
+            "put",
+            "/users/{id}/permissions/{permission_id}",
+            "setUserPermission",
+            "users",
+        )
+        .summary("Set the state of a permission for a user")
+        .authenticated()
+        .path_param("id")
+        .path_param("permission_id")
+        .request_body::<SetPermissionStateRequest>()
+        .empty_response(204)
+        .not_found(),
+        Operation::new(
+            "delete",
+            "/users/{id}/permissions/{permission_id}",
+            "resetUserPermission",
+            "users",
+        )
+        .summary("Reset a permission of a user to its default state")
+        .authenticated()
+        .path_param("id")
+        .path_param("permission_id")
+        .empty_response(204)
+        .not_found(),
+        Operation::new("get", "/openapi.json", "getSpec", "meta")
+            .summary("This document")
+            .raw_response(200, "application/json", json!({ "type": "object" })),
+    ]
+}
+
+/// Schema of a model, generated when the document is built
+type DeferredSchema = Box<dyn FnOnce(&mut Value, &mut SchemaGenerator)>;
+
+pub struct Operation {
+    method: &'static str,
+    path: &'static str,
+    value: Value,
+    schemas: Vec<DeferredSchema>,
+}
+
+impl Operation {
+    fn new(
+        method: &'static str,
+        path: &'static str,
+        operation_id: &'static str,
+        tag: &'static str,
+    ) -> Self {
+        Operation {
+            method,
+            path,
+            value: json!({
+                "operationId": operation_id,
+                "tags": [tag],
+                "parameters": [],
+                "responses": {},
+            }),
+            schemas: vec![],
+        }
+        .problem(400, "Bad request")
+    }
+
+    fn summary(mut self, summary: &'static str) -> Self {
+        self.value["summary"] = summary.into();
+        self
+    }
+
+    fn authenticated(mut self) -> Self {
+        self.value["security"] = json!([{ "apiToken": [] }]);
+        self.problem(401, "Not authenticated")
+    }
+
+    /// Requires the personal token of a user instead of the shared API token
+    fn user_authenticated(mut self) -> Self {
+        self.value["security"] = json!([{ "userToken": [] }]);
+        self.problem(401, "Not authenticated")
+    }
+
+    fn path_param(mut self, name: &'static str) -> Self {
+        push(
+            &mut self.value["parameters"],
+            json!({ "name": name, "in": "path", "required": true, "schema": integer() }),
+        );
+        self
+    }
+
+    /// Query parameters from the fields of a struct deserialized with `web::Query`
+    fn parameters<P: JsonSchema>(mut self) -> Self {
+        for parameter in query_parameters::<P>() {
+            push(&mut self.value["parameters"], parameter);
+        }
+        self
+    }
+
+    fn request_body<T: JsonSchema>(self) -> Self {
+        self.with_schema::<T>(|value, schema| {
+            value["requestBody"] = json!({
+                "required": true,
+                "content": { "application/json": { "schema": schema } },
+            });
+        })
+    }
+
+    fn response<T: JsonSchema>(self, status: u16) -> Self {
+        self.with_schema::<T>(move |value, schema| {
+            value["responses"][status.to_string()] = content("OK", "application/json", schema);
+        })
+    }
+
+    fn raw_response(mut self, status: u16, content_type: &str, schema: Value) -> Self {
+        self.value["responses"][status.to_string()] = content("OK", content_type, schema);
+        self
+    }
+
+    fn empty_response(mut self, status: u16) -> Self {
+        self.value["responses"][status.to_string()] = json!({ "description": "OK" });
+        self
+    }
+
+    fn not_found(self) -> Self {
+        self.problem(404, "Not found")
+    }
+
+    fn conflict(self) -> Self {
+        self.problem(409, "Conflict")
+    }
+
+    fn problem(self, status: u16, description: &'static str) -> Self {
+        self.with_schema::<ProblemDetails<'static>>(move |value, schema| {
+            value["responses"][status.to_string()] =
+                content(description, "application/problem+json", schema);
+        })
+    }
+
+    fn with_schema<T: JsonSchema>(
+        mut self,
+        place: impl FnOnce(&mut Value, Value) + 'static,
+    ) -> Self {
+        self.schemas.push(Box::new(move |value, generator| {
+            place(value, json!(generator.subschema_for::<T>()))
+        }));
+        self
+    }
+
+    fn into_value(mut self, generator: &mut SchemaGenerator) -> Value {
+        for schema in self.schemas {
+            schema(&mut self.value, generator);
+        }
+        self.value
+    }
+}
+
+/// Turn the fields of a struct into query parameters. Field documentation becomes the
+/// description of the parameter.
+fn query_parameters<P: JsonSchema>() -> Vec<Value> {
+    let schema = SchemaSettings::openapi3()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator()
+        .into_root_schema_for::<P>();
+    let schema = json!(schema.schema);
+    let required = schema["required"].as_array().cloned().unwrap_or_default();
+    schema["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    let mut schema = schema.clone();
+                    let description = schema
+                        .as_object_mut()
+                        .and_then(|schema| schema.remove("description"));
+                    json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&json!(name)),
+                        "description": description,
+                        "schema": schema,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn content(description: &str, content_type: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { content_type: { "schema": schema } },
+    })
+}
+
+fn push(array: &mut Value, item: Value) {
+    if let Value::Array(items) = array {
+        items.push(item);
+    }
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer" })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+    use std::path::Path;
+
+    use actix_web::http::{Method, StatusCode};
+    use actix_web::{test, App};
+
+    use crate::config::Config;
+    use crate::services::web_config;
+
+    use super::*;
+
+    const METHODS: [&str; 4] = ["get", "post", "put", "delete"];
+
+    /// Replace path parameters with a valid value
+    fn concrete_path(path: &str) -> String {
+        path.split('/')
+            .map(|segment| if segment.starts_with('{') { "1" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    fn is_routed(status: StatusCode) -> bool {
+        status != StatusCode::NOT_FOUND && status != StatusCode::METHOD_NOT_ALLOWED
+    }
+
+    /// Method and path of all route attributes like `#[get("/users")]` in the services module
+    fn service_routes() -> Vec<(String, String)> {
+        let services = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/services");
+        let mut routes = vec![];
+        for entry in fs::read_dir(services).unwrap() {
+            let source = fs::read_to_string(entry.unwrap().path()).unwrap();
+            for line in source.lines() {
+                let line = line.trim();
+                for method in &METHODS {
+                    let prefix = format!("#[{}(\"", method);
+                    if line.starts_with(&prefix) {
+                        let path = line[prefix.len()..].split('"').next().unwrap();
+                        routes.push((method.to_string(), path.to_string()));
+                    }
+                }
+            }
+        }
+        routes
+    }
+
+    /// Requests every method on every documented and every declared path without a database.
+    /// Documented operations must reach a handler, which fails without app data, while
+    /// undocumented methods must not be routed.
+    #[actix_rt::test]
+    async fn spec_matches_routes() {
+        for (name, value) in &[
+            ("DATABASE_URL", ""),
+            ("REDIS_URL", ""),
+            ("APP_URL", ""),
+            ("TWITCH_CLIENT_ID", ""),
+            ("TWITCH_CLIENT_SECRET", ""),
+            ("API_TOKEN", "test"),
+        ] {
+            std::env::set_var(name, value);
+        }
+        Config::init();
+
+        let mut app = test::init_service(App::new().configure(web_config)).await;
+        let paths = spec()["paths"].as_object().unwrap();
+        let routes = service_routes();
+        assert!(!routes.is_empty());
+        for (method, path) in &routes {
+            assert!(
+                paths.get(path).and_then(|path| path.get(method)).is_some(),
+                "{} {} is declared in the services but not documented",
+                method,
+                path
+            );
+        }
+
+        let all_paths = paths
+            .keys()
+            .map(String::as_str)
+            .chain(routes.iter().map(|(_, path)| path.as_str()));
+        for path in all_paths {
+            for method in &METHODS {
+                let request = test::TestRequest::with_uri(&format!(
+                    "/api/1.0{}",
+                    concrete_path(path)
+                ))
+                .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+                .header("Authorization", "Bearer test")
+                .to_request();
+                let status = test::call_service(&mut app, request).await.status();
+                let documented = paths
+                    .get(path)
+                    .and_then(|operations| operations.get(*method))
+                    .is_some();
+                assert_eq!(
+                    is_routed(status),
+                    documented,
+                    "{} {} is {} but returned {}",
+                    method,
+                    path,
+                    if documented { "documented" } else { "not documented" },
+                    status
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn schema_references_exist() {
+        let spec = spec();
+        let document = spec.to_string();
+        for reference in document.split("\"$ref\":\"").skip(1) {
+            let name = reference
+                .split('"')
+                .next()
+                .unwrap()
+                .trim_start_matches("#/components/schemas/");
+            assert!(
+                spec["components"]["schemas"].get(name).is_some(),
+                "missing schema {}",
+                name
+            );
+        }
+    }
+}
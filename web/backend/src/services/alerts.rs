@@ -0,0 +1,61 @@
+use actix_web::{delete, get, post, web, HttpResponse};
+use validator::Validate;
+
+use persistence::alerts::ChannelAlert;
+use persistence::channel::Channel;
+use persistence::control::BotAction;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::UserError;
+use crate::models::requests::alert::NewAlertRequest;
+use crate::models::responses::alert::ApiChannelAlert;
+use crate::services::bot::notify_bot;
+use crate::ApiResult;
+
+#[get("/channels/{id}/alerts")]
+pub async fn index(
+    channel_id: web::Path<i32>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let alerts = ChannelAlert::list_for_channel(&ctx.db_pool, *channel_id).await?;
+    Ok(HttpResponse::Ok().json(
+        alerts
+            .into_iter()
+            .map(ApiChannelAlert::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[post("/channels/{id}/alerts")]
+pub async fn create(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    body: web::Json<NewAlertRequest>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    body.validate().map_err(UserError::Validation)?;
+    // make sure the channel exists
+    Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+
+    let created =
+        ChannelAlert::create(&ctx.db_pool, body.into_inner().into_insert(*channel_id)).await?;
+    // alert templates are loaded with the command templates
+    notify_bot(&ctx, BotAction::ReloadTemplates).await;
+    Ok(HttpResponse::Created().json(ApiChannelAlert::from(created)))
+}
+
+#[delete("/channels/{id}/alerts/{alert_id}")]
+pub async fn delete(
+    _auth: ApiToken,
+    path: web::Path<(i32, i32)>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let (channel_id, alert_id) = *path;
+    if ChannelAlert::delete(&ctx.db_pool, channel_id, alert_id).await? {
+        notify_bot(&ctx, BotAction::ReloadTemplates).await;
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(UserError::NotFound.into())
+    }
+}
@@ -0,0 +1,35 @@
+use actix_web::{get, post, web, HttpResponse};
+
+use persistence::chat_event::QueueStats;
+use persistence::control::BotAction;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::models::requests::bot::BotActionRequest;
+use crate::models::responses::bot::{ApiQueueStats, BotActionResponse};
+use crate::ApiResult;
+
+#[post("/bot/actions")]
+pub async fn action(
+    _auth: ApiToken,
+    body: web::Json<BotActionRequest>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    body.validate()?;
+    let receivers = BotAction::from(body.into_inner()).publish(&ctx).await?;
+    Ok(HttpResponse::Accepted().json(BotActionResponse { receivers }))
+}
+
+#[get("/bot/queue")]
+pub async fn queue(ctx: web::Data<DbContext>) -> ApiResult<HttpResponse> {
+    let stats = QueueStats::get(&ctx).await?;
+    Ok(HttpResponse::Ok().json(ApiQueueStats::from(stats)))
+}
+
+/// Tell the bot about changed data after a successful write. Failures are only logged since the
+/// write itself has already happened.
+pub async fn notify_bot(ctx: &DbContext, bot_action: BotAction) {
+    if let Err(err) = bot_action.publish(ctx).await {
+        log::warn!("Failed to publish bot action {:?}: {}", bot_action, err);
+    }
+}
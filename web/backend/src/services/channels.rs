@@ -0,0 +1,47 @@
+use actix_web::{get, put, web, HttpResponse};
+use validator::Validate;
+
+use persistence::channel::{Channel, UpdateChannelSettings};
+use persistence::retention::ChannelStorage;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::UserError;
+use crate::models::requests::channel::RetentionRequest;
+use crate::models::responses::channel::ApiChannelStorage;
+use crate::ApiResult;
+
+#[get("/channels/storage")]
+pub async fn storage(ctx: web::Data<DbContext>) -> ApiResult<HttpResponse> {
+    let storage = ChannelStorage::all(&ctx.db_pool).await?;
+    Ok(HttpResponse::Ok().json(
+        storage
+            .into_iter()
+            .map(ApiChannelStorage::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[put("/channels/{id}/retention")]
+pub async fn set_retention(
+    channel_id: web::Path<i32>,
+    request: web::Json<RetentionRequest>,
+    ctx: web::Data<DbContext>,
+    _auth: ApiToken,
+) -> ApiResult<HttpResponse> {
+    request.validate().map_err(UserError::Validation)?;
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    Channel::update_settings(
+        &ctx,
+        channel.name,
+        UpdateChannelSettings {
+            join_on_start: None,
+            command_prefix: None,
+            silent: None,
+            log_retention_days: Some(request.log_retention_days),
+            archive_logs: Some(request.archive_logs),
+        },
+    )
+    .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpResponse};
+use bytes::Bytes;
+use futures::stream;
+use validator::Validate;
+
+use persistence::channel::Channel;
+use persistence::chat_event::{ChatEvent, ChatLogCursor, ChatLogFilter};
+use persistence::user::User;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::{InternalError, UserError};
+use crate::models::requests::chat_log::{
+    encode_cursor, ChatLogParams, CursorParams, ExportFormat, ExportParams,
+};
+use crate::models::responses::chat_log::{ApiChatEvent, CursorListResponse};
+use crate::ApiResult;
+
+/// Number of events loaded per query while exporting logs
+const EXPORT_BATCH_SIZE: u32 = 1000;
+
+#[get("/channels/{id}/logs")]
+pub async fn index(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    params: web::Query<ChatLogParams>,
+    cursor: web::Query<CursorParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    cursor.validate().map_err(UserError::Validation)?;
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let filter = build_filter(&ctx, &params).await?;
+
+    // load one additional event to find out whether there are more
+    let mut events = ChatEvent::search(
+        &ctx.db_pool,
+        channel.id,
+        filter,
+        cursor.cursor()?,
+        cursor.limit + 1,
+    )
+    .await?;
+    let next_cursor = if events.len() > cursor.limit as usize {
+        events.truncate(cursor.limit as usize);
+        events
+            .last()
+            .map(|event| encode_cursor(&ChatLogCursor::from(event)))
+    } else {
+        None
+    };
+
+    Ok(HttpResponse::Ok().json(CursorListResponse {
+        items: with_sender_names(&ctx, events).await?,
+        next_cursor,
+    }))
+}
+
+#[get("/channels/{id}/logs/export")]
+pub async fn export(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    params: web::Query<ChatLogParams>,
+    export: web::Query<ExportParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let filter = build_filter(&ctx, &params).await?;
+    let format = export.format;
+    let channel_id = channel.id;
+
+    // Page through the log and stream each batch. The state is the cursor of the next batch,
+    // `None` once the log is exhausted.
+    let body = stream::unfold(Some(None), move |state: Option<Option<ChatLogCursor>>| {
+        let ctx = ctx.clone();
+        let filter = filter.clone();
+        async move {
+            let after = state?;
+            match export_batch(&ctx, channel_id, filter, after, format).await {
+                Ok((bytes, next)) => Some((Ok(bytes), next.map(Some))),
+                Err(err) => Some((Err(err), None)),
+            }
+        }
+    });
+
+    let (content_type, extension) = match format {
+        ExportFormat::Jsonl => ("application/x-ndjson", "jsonl"),
+        ExportFormat::Text => ("text/plain; charset=utf-8", "log"),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(content_type)
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"{}.{}\"",
+                channel.name.trim_start_matches('#'),
+                extension
+            ),
+        )
+        .streaming(Box::pin(body)))
+}
+
+/// Load and format one batch of exported events. Returns the cursor for the next batch if the log
+/// may contain more events.
+async fn export_batch(
+    ctx: &DbContext,
+    channel_id: i32,
+    filter: ChatLogFilter,
+    after: Option<ChatLogCursor>,
+    format: ExportFormat,
+) -> ApiResult<(Bytes, Option<ChatLogCursor>)> {
+    let events =
+        ChatEvent::search(&ctx.db_pool, channel_id, filter, after, EXPORT_BATCH_SIZE).await?;
+    let next = if events.len() == EXPORT_BATCH_SIZE as usize {
+        events.last().map(ChatLogCursor::from)
+    } else {
+        None
+    };
+
+    let mut output = String::new();
+    for event in with_sender_names(ctx, events).await? {
+        match format {
+            ExportFormat::Jsonl => output.push_str(
+                &serde_json::to_string(&event).map_err(InternalError::from)?,
+            ),
+            ExportFormat::Text => output.push_str(&event.to_irc_line()),
+        }
+        output.push('\n');
+    }
+    Ok((Bytes::from(output), next))
+}
+
+async fn build_filter(ctx: &DbContext, params: &ChatLogParams) -> ApiResult<ChatLogFilter> {
+    params.validate().map_err(UserError::Validation)?;
+
+    let sender_user_id = if let Some(name) = &params.user {
+        Some(User::get_by_name(&ctx.db_pool, name).await?.id)
+    } else {
+        params.user_id
+    };
+
+    Ok(ChatLogFilter {
+        from: params.from,
+        to: params.to,
+        sender_user_id,
+        event_types: params.event_types()?,
+        search: params.search.clone(),
+    })
+}
+
+/// Convert events into API models, looking up the names of all senders
+async fn with_sender_names(
+    ctx: &DbContext,
+    events: Vec<ChatEvent>,
+) -> ApiResult<Vec<ApiChatEvent>> {
+    let mut sender_ids = events
+        .iter()
+        .filter_map(|event| event.sender_user_id)
+        .collect::<Vec<_>>();
+    sender_ids.sort();
+    sender_ids.dedup();
+
+    let names = User::get_many_by_id(&ctx.db_pool, sender_ids)
+        .await?
+        .into_iter()
+        .map(|user| (user.id, user.display_name.unwrap_or(user.name)))
+        .collect::<HashMap<_, _>>();
+
+    Ok(events
+        .into_iter()
+        .map(|event| {
+            let sender_name = event
+                .sender_user_id
+                .and_then(|id| names.get(&id).cloned());
+            ApiChatEvent::new(event, sender_name)
+        })
+        .collect())
+}
@@ -0,0 +1,105 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use actix_web::http::header;
+use actix_web::{get, web, HttpResponse};
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+
+use persistence::channel::Channel;
+use persistence::chat_event::ChatEventType;
+use persistence::live_events::LiveEvent;
+use persistence::permissions::UserPermission;
+use persistence::user::User;
+use persistence::DbContext;
+
+use crate::auth::AuthenticatedUser;
+use crate::error::{ApiError, InternalError, UserError};
+use crate::models::requests::live::LiveFeedParams;
+use crate::models::responses::live::ApiLiveEvent;
+use crate::ApiResult;
+
+/// Interval of comment frames sent to keep idle connections open
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Stream chat events, moderation actions and command executions of a channel as server-sent
+/// events. Only events the user owning the API token has the `livefeed:*` permissions for are
+/// sent.
+#[get("/channels/{id}/live")]
+pub async fn feed(
+    channel_id: web::Path<i32>,
+    params: web::Query<LiveFeedParams>,
+    ctx: web::Data<DbContext>,
+    auth: AuthenticatedUser,
+) -> ApiResult<HttpResponse> {
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let user = User::get_by_id(&ctx.db_pool, auth.user_id).await?;
+    let granted = UserPermission::get_effective(&ctx.db_pool, user.id)
+        .await?
+        .into_iter()
+        .filter(|p| p.granted)
+        .map(|p| p.permission.name)
+        .collect::<HashSet<_>>();
+    let filter = LiveFeedFilter {
+        granted,
+        event_types: params.event_types()?,
+    };
+    if !filter.allows_any() {
+        return Err(UserError::Unauthorized.into());
+    }
+
+    let events = LiveEvent::subscribe(&ctx, channel.id)
+        .await?
+        .filter(move |event| {
+            let include = match event {
+                Ok(event) => filter.allows(event),
+                Err(_) => true,
+            };
+            async move { include }
+        })
+        .map(|event| -> ApiResult<Bytes> {
+            let event = ApiLiveEvent::from(event?);
+            let data = serde_json::to_string(&event).map_err(InternalError::from)?;
+            Ok(Bytes::from(format!(
+                "event: {}\ndata: {}\n\n",
+                event.event_name(),
+                data
+            )))
+        });
+    let keepalive = actix_rt::time::interval(KEEPALIVE_INTERVAL)
+        .map(|_| Ok::<_, ApiError>(Bytes::from_static(b":\n\n")));
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .header(header::CACHE_CONTROL, "no-cache")
+        .streaming(Box::pin(stream::select(events, keepalive))))
+}
+
+struct LiveFeedFilter {
+    /// names of the permissions granted to the watching user
+    granted: HashSet<String>,
+    /// chat event types to include, all types if empty
+    event_types: Vec<ChatEventType>,
+}
+
+impl LiveFeedFilter {
+    fn allows_any(&self) -> bool {
+        ["livefeed:chat", "livefeed:commands", "livefeed:moderation"]
+            .iter()
+            .any(|name| self.granted.contains(*name))
+    }
+
+    fn allows(&self, event: &LiveEvent) -> bool {
+        match event {
+            LiveEvent::Chat(event) => {
+                let permission = match event.event_type {
+                    ChatEventType::Clearchat | ChatEventType::Clearmsg => "livefeed:moderation",
+                    _ => "livefeed:chat",
+                };
+                self.granted.contains(permission)
+                    && (self.event_types.is_empty() || self.event_types.contains(&event.event_type))
+            }
+            LiveEvent::CommandExecuted(_) => self.granted.contains("livefeed:commands"),
+        }
+    }
+}
@@ -4,15 +4,46 @@ use actix_web::web::{JsonConfig, QueryConfig};
 
 use crate::error::UserError;
 
+pub mod alerts;
+pub mod bot;
+pub mod channels;
+pub mod chat_logs;
 pub mod commands;
+pub mod live;
+pub mod openapi;
+pub mod permissions;
+pub mod stats;
+pub mod users;
 
 pub fn web_config(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/api/1.0")
             .app_data(query_error_handler())
             .app_data(payload_error_handler())
+            .service(alerts::index)
+            .service(alerts::create)
+            .service(alerts::delete)
+            .service(bot::action)
+            .service(bot::queue)
+            .service(channels::storage)
+            .service(channels::set_retention)
+            .service(chat_logs::index)
+            .service(chat_logs::export)
             .service(commands::index)
-            .service(commands::get),
+            .service(commands::get)
+            .service(live::feed)
+            .service(openapi::spec)
+            .service(permissions::index)
+            .service(permissions::create)
+            .service(stats::messages)
+            .service(stats::chatters)
+            .service(stats::commands)
+            .service(stats::retention)
+            .service(users::index)
+            .service(users::get)
+            .service(users::permissions)
+            .service(users::set_permission)
+            .service(users::reset_permission),
     );
 }
 
@@ -0,0 +1,8 @@
+use actix_web::{get, HttpResponse};
+
+use crate::openapi;
+
+#[get("/openapi.json")]
+pub async fn spec() -> HttpResponse {
+    HttpResponse::Ok().json(openapi::spec())
+}
@@ -0,0 +1,52 @@
+use actix_web::{get, post, web, HttpResponse};
+use validator::Validate;
+
+use persistence::control::BotAction;
+use persistence::permissions::Permission;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::UserError;
+use crate::models::requests::permission::NewPermissionRequest;
+use crate::models::responses::permission::ApiPermission;
+use crate::services::bot::notify_bot;
+use crate::ApiResult;
+
+#[get("/permissions")]
+pub async fn index(ctx: web::Data<DbContext>) -> ApiResult<HttpResponse> {
+    let permissions = Permission::all_with_implied(&ctx.db_pool).await?;
+    let response = permissions
+        .into_iter()
+        .map(ApiPermission::from)
+        .collect::<Vec<_>>();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[post("/permissions")]
+pub async fn create(
+    _auth: ApiToken,
+    body: web::Json<NewPermissionRequest>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    body.validate().map_err(UserError::Validation)?;
+
+    let existing = Permission::all(&ctx.db_pool).await?;
+    if !body
+        .implied_by
+        .iter()
+        .all(|id| existing.iter().any(|permission| permission.id == *id))
+    {
+        return Err(UserError::BadRequest("Unknown permission ID in impliedBy.").into());
+    }
+
+    let created = Permission::create(&ctx.db_pool, body.into_inner().into()).await?;
+    notify_bot(&ctx, BotAction::ReloadPermissions).await;
+
+    let response = Permission::all_with_implied(&ctx.db_pool)
+        .await?
+        .into_iter()
+        .find(|permission| permission.permission.id == created.id)
+        .map(ApiPermission::from)
+        .ok_or(UserError::NotFound)?;
+    Ok(HttpResponse::Created().json(response))
+}
@@ -0,0 +1,99 @@
+use actix_web::{get, web, HttpResponse};
+use validator::Validate;
+
+use persistence::channel::Channel;
+use persistence::stats;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::UserError;
+use crate::models::requests::stats::{MessageStatsParams, StatsRangeParams, TopChattersParams};
+use crate::models::responses::stats::{
+    ApiChatterCount, ApiChatterStats, ApiCommandUsage, ApiMessageCount, ApiUserRetention,
+};
+use crate::ApiResult;
+
+#[get("/channels/{id}/stats/messages")]
+pub async fn messages(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    range: web::Query<StatsRangeParams>,
+    params: web::Query<MessageStatsParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let (from, to) = range.range()?;
+    let counts = stats::message_counts(
+        &ctx.db_pool,
+        channel.id,
+        params.granularity.into(),
+        from,
+        to,
+    )
+    .await?;
+    Ok(HttpResponse::Ok().json(
+        counts
+            .into_iter()
+            .map(ApiMessageCount::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[get("/channels/{id}/stats/chatters")]
+pub async fn chatters(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    range: web::Query<StatsRangeParams>,
+    params: web::Query<TopChattersParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    params.validate().map_err(UserError::Validation)?;
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let (from, to) = range.day_range()?;
+    let unique_chatters = stats::unique_chatters(&ctx.db_pool, channel.id, from, to).await?;
+    let top_chatters =
+        stats::top_chatters(&ctx.db_pool, channel.id, from, to, params.limit).await?;
+    Ok(HttpResponse::Ok().json(ApiChatterStats {
+        unique_chatters,
+        top_chatters: top_chatters
+            .into_iter()
+            .map(ApiChatterCount::from)
+            .collect(),
+    }))
+}
+
+#[get("/channels/{id}/stats/commands")]
+pub async fn commands(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    range: web::Query<StatsRangeParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let (from, to) = range.day_range()?;
+    let usage = stats::command_usage(&ctx.db_pool, channel.id, from, to).await?;
+    Ok(HttpResponse::Ok().json(
+        usage
+            .into_iter()
+            .map(ApiCommandUsage::from)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+#[get("/channels/{id}/stats/retention")]
+pub async fn retention(
+    _auth: ApiToken,
+    channel_id: web::Path<i32>,
+    range: web::Query<StatsRangeParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let channel = Channel::get_by_id(&ctx.db_pool, *channel_id).await?;
+    let (from, to) = range.day_range()?;
+    let retention = stats::user_retention(&ctx.db_pool, channel.id, from, to).await?;
+    Ok(HttpResponse::Ok().json(
+        retention
+            .into_iter()
+            .map(ApiUserRetention::from)
+            .collect::<Vec<_>>(),
+    ))
+}
@@ -0,0 +1,101 @@
+use actix_web::{delete, get, put, web, HttpResponse};
+use validator::Validate;
+
+use persistence::control::BotAction;
+use persistence::permissions::{Permission, UserPermission};
+use persistence::user::User;
+use persistence::DbContext;
+
+use crate::auth::ApiToken;
+use crate::error::UserError;
+use crate::models::requests::pagination::PaginationParams;
+use crate::models::requests::permission::SetPermissionStateRequest;
+use crate::models::requests::user::UserSearchParams;
+use crate::models::responses::list::ListResponse;
+use crate::models::responses::permission::ApiUserPermission;
+use crate::models::responses::user::ApiUser;
+use crate::services::bot::notify_bot;
+use crate::ApiResult;
+
+#[get("/users")]
+pub async fn index(
+    _auth: ApiToken,
+    pagination: web::Query<PaginationParams>,
+    search: web::Query<UserSearchParams>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    pagination.validate().map_err(UserError::Validation)?;
+    search.validate().map_err(UserError::Validation)?;
+
+    let (total, users) = User::list(
+        &ctx.db_pool,
+        search.search.as_deref(),
+        pagination.as_offset(),
+    )
+    .await?;
+
+    let response = ListResponse::new(
+        users.into_iter().map(ApiUser::from).collect(),
+        total,
+        pagination.page,
+        pagination.per_page,
+    );
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[get("/users/{id}")]
+pub async fn get(
+    _auth: ApiToken,
+    user_id: web::Path<i32>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let user = User::get_by_id(&ctx.db_pool, *user_id).await?;
+    Ok(HttpResponse::Ok().json(ApiUser::from(user)))
+}
+
+#[get("/users/{id}/permissions")]
+pub async fn permissions(
+    _auth: ApiToken,
+    user_id: web::Path<i32>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let user = User::get_by_id(&ctx.db_pool, *user_id).await?;
+    let response = UserPermission::get_effective(&ctx.db_pool, user.id)
+        .await?
+        .into_iter()
+        .map(ApiUserPermission::from)
+        .collect::<Vec<_>>();
+    Ok(HttpResponse::Ok().json(response))
+}
+
+#[put("/users/{id}/permissions/{permission_id}")]
+pub async fn set_permission(
+    _auth: ApiToken,
+    path: web::Path<(i32, i32)>,
+    body: web::Json<SetPermissionStateRequest>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let (user_id, permission_id) = *path;
+    let user = User::get_by_id(&ctx.db_pool, user_id).await?;
+    let permission = Permission::get(&ctx.db_pool, permission_id).await?;
+
+    UserPermission::set_state(&ctx.db_pool, user.id, permission.id, body.state.into()).await?;
+    notify_bot(&ctx, BotAction::ReloadPermissions).await;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[delete("/users/{id}/permissions/{permission_id}")]
+pub async fn reset_permission(
+    _auth: ApiToken,
+    path: web::Path<(i32, i32)>,
+    ctx: web::Data<DbContext>,
+) -> ApiResult<HttpResponse> {
+    let (user_id, permission_id) = *path;
+    if UserPermission::reset_state(&ctx.db_pool, user_id, permission_id).await? {
+        notify_bot(&ctx, BotAction::ReloadPermissions).await;
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Err(UserError::NotFound.into())
+    }
+}